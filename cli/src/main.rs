@@ -1,18 +1,127 @@
-use std::env;
+use std::{env, fs};
 
-use signer::{signer::*, wallet::Wallet};
+use signer::{
+    combiner::decode_psbts_and_combine,
+    message::{verify_message, SignatureFormat},
+    psbt_decoder::decode_psbt,
+    psbt_details::OperationRequest,
+    signer::*,
+    wallet::Wallet,
+};
 
 fn main() {
     let mut args = env::args().skip(1);
 
     let mut wallet = Wallet::default();
-    //wallet.from_seed_str("wallet 1", "solar goat auto bachelor chronic input twin depth fork scale divorce fury mushroom column image sauce car public artist announce treat spend jacket physical", "Qwerty123").unwrap();
-    wallet.from_xprv_str("wallet 1", "tprv8ZgxMBicQKsPduvXYAnkop1b1UoAY2pS68pe9jHuJwuMvx6G5sh4C67peYZkRawdBWbMbfoybgQJ3g8nTZAezEeHyaW9A9UjtpTRmSyJwUn", "m/48'/1'/1'", "Qwerty123").unwrap();
-    let signed_psbt = decode_psbt_and_sign(
-        &args.next().unwrap(),
-        &mut wallet,
-        "Qwerty123",
-        signer::Network::Bitcoin,
-    );
-    println!("{}", signed_psbt.unwrap())
+    //wallet.from_seed_str("wallet 1", "solar goat auto bachelor chronic input twin depth fork scale divorce fury mushroom column image sauce car public artist announce treat spend jacket physical", signer::Network::Regtest, "Qwerty123").unwrap();
+    wallet.from_xprv_str("wallet 1", "tprv8ZgxMBicQKsPduvXYAnkop1b1UoAY2pS68pe9jHuJwuMvx6G5sh4C67peYZkRawdBWbMbfoybgQJ3g8nTZAezEeHyaW9A9UjtpTRmSyJwUn", "m", None, signer::Network::Regtest, "Qwerty123").unwrap();
+    let input = args.next().unwrap();
+    match input.as_str() {
+        // sign-message <derivation> <message>, the address type comes from the derivation purpose
+        "sign-message" => {
+            let derivation = args.next().unwrap();
+            let message = args.next().unwrap();
+            let signed = wallet
+                .sign_message(
+                    &message,
+                    &derivation,
+                    None,
+                    SignatureFormat::Bip322Simple,
+                    "Qwerty123",
+                    signer::Network::Regtest,
+                )
+                .unwrap();
+            println!("{}\n{}", signed.address, signed.signature)
+        }
+        // verify-message <address> <message> <signature>
+        "verify-message" => {
+            let address = args.next().unwrap();
+            let message = args.next().unwrap();
+            let signature = args.next().unwrap();
+            println!(
+                "{}",
+                verify_message(&address, &message, &signature).unwrap()
+            )
+        }
+        // combine <psbt> <psbt>..., each inline or as a path to a file
+        "combine" => {
+            let psbts: Vec<Vec<u8>> = args
+                .map(|p| fs::read(&p).unwrap_or_else(|_| p.into_bytes()))
+                .collect();
+            let (combined, added) = decode_psbts_and_combine(&psbts).unwrap();
+            for added in added {
+                eprintln!("{added}");
+            }
+            println!("{combined}")
+        }
+        // sign-batch [--allow-unverified-inputs] <psbt>..., each inline or as a path to a file,
+        // printing one signed PSBT per line and the failures to stderr
+        "sign-batch" => {
+            let mut allow_unverified_inputs = false;
+            let psbts: Vec<Vec<u8>> = args
+                .filter(|p| {
+                    let flag = p == "--allow-unverified-inputs";
+                    allow_unverified_inputs |= flag;
+                    !flag
+                })
+                .map(|p| fs::read(&p).unwrap_or_else(|_| p.into_bytes()))
+                .collect();
+            let results = decode_psbts_and_sign(
+                &psbts,
+                &mut wallet,
+                "Qwerty123",
+                signer::Network::Regtest,
+                allow_unverified_inputs,
+            )
+            .unwrap();
+            for (index, result) in results.into_iter().enumerate() {
+                match result {
+                    Ok(signed_psbt) => {
+                        print_invalid_partial_sigs(&signed_psbt);
+                        println!("{signed_psbt}")
+                    }
+                    Err(e) => eprintln!("PSBT {}: {e}", index + 1),
+                }
+            }
+        }
+        // the PSBT can be passed inline or as a path to a file in any supported encoding,
+        // followed by --allow-unverified-inputs to sign inputs without their previous transaction
+        // and by --request-type <type> [--amount <btc>] [--node-pubkey <key>] to only sign it if
+        // it does what the coordinator requested
+        _ => {
+            let psbt = fs::read(&input).unwrap_or_else(|_| input.into_bytes());
+            let options: Vec<String> = args.collect();
+            let option = |name: &str| {
+                options
+                    .iter()
+                    .position(|o| o == name)
+                    .and_then(|i| options.get(i + 1))
+                    .map(String::as_str)
+            };
+            let request = OperationRequest {
+                request_type: option("--request-type"),
+                amount: option("--amount"),
+                node_pubkey: option("--node-pubkey"),
+            };
+            let signed_psbt = decode_psbt_and_sign(
+                &psbt,
+                &mut wallet,
+                "Qwerty123",
+                signer::Network::Regtest,
+                options.iter().any(|o| o == "--allow-unverified-inputs"),
+                request.request_type.is_some().then_some(&request),
+            )
+            .unwrap();
+            print_invalid_partial_sigs(&signed_psbt);
+            println!("{signed_psbt}")
+        }
+    }
+}
+
+// cosigner signatures that would make the transaction fail at broadcast
+fn print_invalid_partial_sigs(signed_psbt: &str) {
+    let psbt = decode_psbt(signed_psbt.as_bytes()).unwrap();
+    for invalid in invalid_partial_sigs(&psbt) {
+        eprintln!("{invalid}");
+    }
 }
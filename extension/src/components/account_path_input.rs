@@ -0,0 +1,60 @@
+use anyhow::Result;
+use signer::{derivation::AccountPreset, Network};
+use yew::prelude::*;
+
+use crate::components::{
+    select::{Select, SelectItem},
+    text_input::TextInput,
+};
+
+const CUSTOM: &str = "Custom";
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    pub network: Network,
+    pub value: String,
+    pub onchange: Callback<String, ()>,
+    pub disabled: Option<bool>,
+}
+
+#[function_component(AccountPathInput)]
+pub fn account_path_input(props: &Props) -> Html {
+    let network = props.network;
+    let items: Vec<SelectItem> = AccountPreset::ALL
+        .iter()
+        .map(ToString::to_string)
+        .chain([CUSTOM.to_string()])
+        .map(|label| SelectItem::new(&label, &label))
+        .collect();
+    let default = AccountPreset::ALL
+        .iter()
+        .find(|p| p.derivation_path(network, 0).to_string() == props.value)
+        .map_or_else(|| CUSTOM.to_string(), ToString::to_string);
+
+    let onchange_preset = {
+        let onchange = props.onchange.clone();
+        Callback::from(move |item: SelectItem| {
+            if let Some(preset) = AccountPreset::ALL
+                .iter()
+                .find(|p| p.to_string() == item.value)
+            {
+                onchange.emit(preset.derivation_path(network, 0).to_string());
+            }
+        })
+    };
+
+    let onchange_path = {
+        let onchange = props.onchange.clone();
+        Callback::from(move |value: Result<String>| {
+            let _ = value.map(|v| onchange.emit(v));
+        })
+    };
+
+    html! {
+        <>
+            <label>{"Account:"}</label>
+            <Select onchange={onchange_preset} {items} {default}/>
+            <TextInput disabled={props.disabled} value={props.value.clone()} onchange={onchange_path} placeholder="m/n'/n'..."/>
+        </>
+    }
+}
@@ -1,3 +1,5 @@
+pub mod account_path_input;
+pub mod network_select;
 pub mod radio_button;
 pub mod select;
 pub mod text_input;
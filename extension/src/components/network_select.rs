@@ -0,0 +1,40 @@
+use signer::Network;
+use std::str::FromStr;
+use yew::prelude::*;
+
+use crate::components::select::{Select, SelectItem};
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    pub value: Network,
+    pub onchange: Callback<Network, ()>,
+}
+
+#[function_component(NetworkSelect)]
+pub fn network_select(props: &Props) -> Html {
+    let items: Vec<SelectItem> = [
+        Network::Bitcoin,
+        Network::Regtest,
+        Network::Signet,
+        Network::Testnet,
+    ]
+    .iter()
+    .map(|n| SelectItem::new(&n.to_string(), &n.to_string()))
+    .collect();
+
+    let onchange = {
+        let onchange = props.onchange.clone();
+        Callback::from(move |item: SelectItem| {
+            if let Ok(network) = Network::from_str(&item.value) {
+                onchange.emit(network);
+            }
+        })
+    };
+
+    html! {
+        <>
+            <label>{"Network:"}</label>
+            <Select {onchange} {items} default={props.value.to_string()}/>
+        </>
+    }
+}
@@ -1,13 +1,15 @@
 interface OperationRequestData {
     psbt?: string,
     request_type?: string,
-    amount?: string;
+    amount?: string,
+    node_pubkey?: string;
 }
 
 function findPSBT() {
     let psbtField = document.getElementById("psbt-to-sign") as HTMLInputElement | null;
     let requestTypeField = document.getElementById("request-type");
     let channelAmountField = document.getElementById("channel-amount");
+    let nodePubkeyField = document.getElementById("node-pubkey");
     let data: OperationRequestData = {};
     if (psbtField?.value) {
         data["psbt"] = psbtField.value;
@@ -18,6 +20,9 @@ function findPSBT() {
     if (channelAmountField?.innerHTML) {
         data["amount"] = channelAmountField.innerHTML;
     }
+    if (nodePubkeyField?.innerHTML) {
+        data["node_pubkey"] = nodePubkeyField.innerHTML;
+    }
     return data;
 }
 
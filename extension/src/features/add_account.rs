@@ -0,0 +1,91 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    components::{account_path_input::AccountPathInput, text_input::TextInput},
+    switch::Route,
+    utils::{helpers::decode_url_string, storage::LocalStorage},
+};
+use anyhow::{anyhow, Result};
+use signer::storage::{SettingsStorage, UserStorage};
+use web_sys::MouseEvent;
+use yew::prelude::*;
+use yew_router::prelude::use_navigator;
+
+#[derive(Properties, PartialEq, Eq)]
+pub struct Props {
+    pub wallet_name: String,
+}
+
+#[function_component(AddAccount)]
+pub fn add_account(props: &Props) -> Html {
+    let decoded_wallet_name = decode_url_string(&props.wallet_name).unwrap();
+    let navigator = use_navigator().unwrap();
+    let storage = Rc::new(RefCell::new(UserStorage::read(LocalStorage::default())));
+    let settings_network = SettingsStorage::read(LocalStorage::default()).get_network();
+    let network = storage
+        .borrow()
+        .get_wallet_ref(&decoded_wallet_name)
+        .map_or(settings_network, |w| w.get_network(settings_network));
+    let wallet_name = use_state(String::default);
+    let derivation = use_state(String::default);
+    let error = use_state(String::default);
+    let wallet_name_value = (*wallet_name).clone();
+    let derivation_value = (*derivation).clone();
+    let error_value = (*error).clone();
+
+    let on_change_name = Callback::from(move |value: Result<String>| {
+        let _ = value.map(|v| wallet_name.set(v));
+    });
+
+    let on_change_derivation = Callback::from(move |value: String| derivation.set(value));
+
+    let onclick_save = {
+        let navigator = navigator.clone();
+        let wallet_name_value = wallet_name_value.clone();
+        let derivation_value = derivation_value.clone();
+        Callback::from(move |_: MouseEvent| {
+            if wallet_name_value.trim().is_empty() {
+                error.set("Wallet name is mandatory".into());
+                return;
+            }
+            if storage
+                .borrow()
+                .get_wallet_ref(&wallet_name_value)
+                .is_some()
+            {
+                error.set("There is already a wallet with that name".into());
+                return;
+            }
+
+            let mut s = storage.borrow_mut();
+            let result = s
+                .get_wallet_ref(&decoded_wallet_name)
+                .ok_or_else(|| anyhow!("Wallet not found"))
+                .and_then(|w| w.derive_account_wallet(wallet_name_value.trim(), &derivation_value))
+                .and_then(|wallet| {
+                    s.wallets.push(wallet);
+                    s.save()
+                });
+
+            match result {
+                Ok(_) => navigator.push(&Route::Home),
+                Err(e) => error.set(format!("Error while adding account {e}")),
+            }
+        })
+    };
+
+    let onclick_goback = Callback::from(move |_: MouseEvent| navigator.back());
+
+    html! {
+        <>
+            <h class="title">{"Add account from this seed"}</h>
+            <TextInput value={wallet_name_value} onchange={on_change_name} placeholder="Input the new wallet's name"/>
+            <AccountPathInput {network} value={derivation_value} onchange={on_change_derivation}/>
+            <div class="error">{error_value}</div>
+            <div class="button-bar">
+                <button class="cancel" onclick={onclick_goback}>{"Go back"}</button>
+                <button onclick={onclick_save}>{"Save"}</button>
+            </div>
+        </>
+    }
+}
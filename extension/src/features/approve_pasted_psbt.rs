@@ -4,15 +4,24 @@ use crate::{
         textarea::TextArea,
     },
     context::UserContext,
-    utils::{helpers::get_clipboard, storage::LocalStorage},
+    features::approve_psbt::{policy_view, sighash_view},
+    utils::{
+        helpers::{get_clipboard, read_file},
+        storage::LocalStorage,
+    },
 };
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 use signer::{
+    combiner::decode_psbts_and_combine,
+    policy::decode_psbt_policies,
+    psbt_decoder::decode_psbt,
     psbt_details::PSBTDetails,
     signer::decode_psbt_and_sign,
     storage::{SettingsStorage, UserStorage},
 };
-use std::{cell::RefCell, rc::Rc, str::FromStr};
+use std::{cell::RefCell, collections::BTreeSet, rc::Rc};
+use wasm_bindgen_futures::spawn_local;
+use web_sys::HtmlInputElement;
 use yew::prelude::*;
 use yew_router::prelude::use_navigator;
 
@@ -33,19 +42,99 @@ pub fn approve_pasted_psbt() -> Html {
     let disabled = password.is_empty();
     let psbt = use_state(String::default);
     let psbt_value = (*psbt).clone();
+    // raw contents of the loaded file, which may be binary
+    let psbt_file = use_state(|| None::<Vec<u8>>);
+    let psbt_bytes = (*psbt_file)
+        .clone()
+        .unwrap_or_else(|| psbt_value.as_bytes().to_vec());
     let signed_psbt = use_state(String::default);
     let signed_psbt_value = (*signed_psbt).clone();
+    let other_psbt = use_state(String::default);
+    let other_psbt_value = (*other_psbt).clone();
+    let combined = use_state(Vec::<String>::new);
+    let combined_value = (*combined).clone();
 
     let onchange_psbt = {
         let psbt = psbt.clone();
+        let psbt_file = psbt_file.clone();
         Callback::from(move |value: Result<String>| {
-            let _ = value.map(|v| psbt.set(v));
+            let _ = value.map(|v| {
+                psbt_file.set(None);
+                psbt.set(v);
+            });
+        })
+    };
+
+    let onchange_file = {
+        let psbt = psbt.clone();
+        let psbt_file = psbt_file.clone();
+        let error = error.clone();
+        Callback::from(move |event: Event| {
+            let file = event
+                .target_dyn_into::<HtmlInputElement>()
+                .and_then(|input| input.files())
+                .and_then(|files| files.get(0))
+                .context("No file selected");
+            let psbt = psbt.clone();
+            let psbt_file = psbt_file.clone();
+            let error = error.clone();
+            spawn_local(async move {
+                let file = match file {
+                    Ok(file) => file,
+                    Err(e) => return error.set(e.to_string()),
+                };
+                // the raw bytes are kept so the details show the file's own encoding,
+                // binary files are only displayed as base64
+                let read = read_file(file)
+                    .await
+                    .and_then(|bytes| decode_psbt(&bytes).map(|p| (bytes, p)));
+                match read {
+                    Ok((bytes, p)) => {
+                        error.set(String::default());
+                        psbt.set(
+                            String::from_utf8(bytes.clone()).unwrap_or_else(|_| p.to_string()),
+                        );
+                        psbt_file.set(Some(bytes));
+                    }
+                    Err(e) => error.set(format!("Error while reading PSBT file {e}")),
+                }
+            });
+        })
+    };
+
+    let onchange_other_psbt = {
+        let other_psbt = other_psbt.clone();
+        Callback::from(move |value: Result<String>| {
+            let _ = value.map(|v| other_psbt.set(v));
+        })
+    };
+
+    // merges the cosigner's PSBT into the pasted one so it can be signed or copied
+    let onclick_combine = {
+        let psbt_bytes = psbt_bytes.clone();
+        let other_psbt_value = other_psbt_value.clone();
+        let error = error.clone();
+        Callback::from(move |_: MouseEvent| {
+            match decode_psbts_and_combine(&[psbt_bytes.as_slice(), other_psbt_value.as_bytes()]) {
+                Ok((combined_psbt, added)) => {
+                    error.set(String::default());
+                    psbt_file.set(None);
+                    psbt.set(combined_psbt);
+                    other_psbt.set(String::default());
+                    combined.set(if added.is_empty() {
+                        vec!["No new signatures".to_string()]
+                    } else {
+                        added.iter().map(ToString::to_string).collect()
+                    });
+                }
+                Err(e) => error.set(format!("Error while combining PSBTs {e}")),
+            }
         })
     };
 
     let onclick_save = {
         let selected_wallet_value = selected_wallet_value.clone();
-        let psbt = psbt.clone();
+        let psbt_bytes = psbt_bytes.clone();
         let storage = storage.clone();
         Callback::from(move |_: MouseEvent| {
             if password.is_empty() {
@@ -58,8 +147,15 @@ pub fn approve_pasted_psbt() -> Html {
                 .get_wallet_mut(&selected_wallet_value)
                 .ok_or_else(|| anyhow!("Wallet not found"))
                 .and_then(|wallet| {
-                    decode_psbt_and_sign(&psbt, wallet, &password, settings_storage.get_network())
-                        .map_err(|e| anyhow!("Error while signing PSBT {e}"))
+                    decode_psbt_and_sign(
+                        &psbt_bytes,
+                        wallet,
+                        &password,
+                        settings_storage.get_network(),
+                        settings_storage.get_allow_unverified_inputs(),
+                        None,
+                    )
+                    .map_err(|e| anyhow!("Error while signing PSBT {e}"))
                 });
 
             match result {
@@ -92,14 +188,40 @@ pub fn approve_pasted_psbt() -> Html {
         .map(|w| SelectItem::new(&w.name, &w.name))
         .collect();
 
+    let own_fingerprints: BTreeSet<_> = storage
+        .borrow()
+        .wallets
+        .iter()
+        .filter(|w| w.name == selected_wallet_value)
+        .filter_map(|w| w.master_fingerprint)
+        .collect();
+    let policies = decode_psbt_policies(&psbt_value, &own_fingerprints).unwrap_or_default();
+
     let parsed_successfully = {
-        let psbt_parsed = PSBTDetails::from_str(&psbt);
+        let psbt_parsed = PSBTDetails::from_bytes(&psbt_bytes);
         match psbt_parsed {
             Ok(psbt) => html! {
-                <div class="display-field">
-                    <strong>{"Tx Id:"}</strong>
-                    <span>{psbt.tx_id}</span>
-                </div>
+                <>
+                    <div class="display-field">
+                        <strong>{"Tx Id:"}</strong>
+                        <span>{psbt.tx_id}</span>
+                    </div>
+                    <div class="display-field">
+                        <strong>{"Format:"}</strong>
+                        <span>{psbt.encoding.map(|e| e.to_string()).unwrap_or_default()}</span>
+                    </div>
+                    <div class="display-field">
+                        <strong>{"Fee:"}</strong>
+                        <span>{psbt.fee}</span>
+                        <span>{"SATS"}</span>
+                    </div>
+                    {sighash_view(&psbt.sighash_types)}
+                    {policy_view(&policies)}
+                    { for psbt.warnings.iter().map(|w| html! { <div class="error">{w}</div> }) }
+                </>
+            },
+            Err(e) if !psbt_value.trim().is_empty() => html! {
+                <div class="error">{e.to_string()}</div>
             },
             Err(_) => {
                 html! {}
@@ -120,12 +242,21 @@ pub fn approve_pasted_psbt() -> Html {
         }
     };
 
+    let combine_disabled = psbt_value.trim().is_empty() || other_psbt_value.trim().is_empty();
     html! {
         <>
             <h class="title">{"Approve PSBT"}</h>
             <Select {onchange} items={items} default={selected_wallet_value}/>
-            <TextArea value={psbt_value} onchange={onchange_psbt} placeholder="Paste your PSBT here"/>
+            <TextArea value={psbt_value} onchange={onchange_psbt} placeholder="Paste your PSBT here (base64, hex, base43 or ur:crypto-psbt parts)"/>
+            <input type="file" onchange={onchange_file} />
             {parsed_successfully}
+            <TextArea value={other_psbt_value} onchange={onchange_other_psbt} placeholder="Paste a cosigner's PSBT to combine"/>
+            <button disabled={combine_disabled} onclick={onclick_combine}>{"Combine"}</button>
+            {
+                combined_value.iter().map(|added| html! {
+                    <div class="display-field"><span>{added}</span></div>
+                }).collect::<Html>()
+            }
             <div class="error">{error_value}</div>
             {signed_successfully}
             <div class="button-bar">
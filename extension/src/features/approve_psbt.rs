@@ -1,5 +1,4 @@
 use crate::{
-    components::select::{Select, SelectItem},
     context::UserContext,
     paste_psbt,
     switch::Route,
@@ -8,15 +7,158 @@ use crate::{
 };
 use anyhow::anyhow;
 use signer::{
-    psbt_details::PSBTDetails,
-    signer::decode_psbt_and_sign,
+    policy::{decode_psbt_policies, InputPolicy},
+    psbt_details::{Action, OperationDetails, OperationRequest, PSBTDetails},
+    signer::{decode_psbt_and_sign_with_wallets, decode_psbt_signing_wallets},
     storage::{SettingsStorage, UserStorage},
 };
-use std::{cell::RefCell, rc::Rc, str::FromStr};
+use std::{cell::RefCell, collections::BTreeSet, rc::Rc, str::FromStr};
 use web_sys::window;
 use yew::prelude::*;
 use yew_router::prelude::{use_location, use_navigator};
 
+/// Sighash type of each input, flagging the ones that don't sign the whole
+/// transaction. Taproot's SIGHASH_DEFAULT signs it like SIGHASH_ALL.
+pub fn sighash_view(sighash_types: &[String]) -> Html {
+    sighash_types
+        .iter()
+        .enumerate()
+        .map(|(index, sighash_type)| {
+            let class = !matches!(sighash_type.as_str(), "SIGHASH_ALL" | "SIGHASH_DEFAULT");
+            let class = class.then_some("error");
+            html! {
+                <div class={classes!("display-field", class)}>
+                    <strong>{format!("Input {index} sighash:")}</strong>
+                    <span>{sighash_type}</span>
+                </div>
+            }
+        })
+        .collect()
+}
+
+/// Policy of each script input, who already signed it and the spending paths
+/// with their timelocks, flagging the ones this transaction can't use.
+pub fn policy_view(policies: &[InputPolicy]) -> Html {
+    policies
+        .iter()
+        .map(|policy| {
+            let signed = if policy.signed.is_empty() {
+                "nobody".to_string()
+            } else {
+                policy.signed.join(", ")
+            };
+            let remaining = policy.remaining_signatures.map_or_else(
+                || "not spendable with this transaction's timelocks".to_string(),
+                |n| n.to_string(),
+            );
+            let paths = if policy.paths.len() > 1 {
+                policy
+                    .paths
+                    .iter()
+                    .map(|path| {
+                        let class = (!path.enabled).then_some("error");
+                        html! {
+                            <div class={classes!("display-field", class)}>
+                                <span>{path.to_string()}</span>
+                            </div>
+                        }
+                    })
+                    .collect::<Html>()
+            } else {
+                html! {}
+            };
+            html! {
+                <>
+                    <div class="display-field">
+                        <strong>{format!("Input {} policy:", policy.input)}</strong>
+                        <span>{&policy.summary}</span>
+                    </div>
+                    <div class="display-field">
+                        <strong>{"Signed by:"}</strong>
+                        <span>{signed}</span>
+                    </div>
+                    <div class="display-field">
+                        <strong>{"Signatures remaining:"}</strong>
+                        <span>{remaining}</span>
+                    </div>
+                    {paths}
+                </>
+            }
+        })
+        .collect()
+}
+
+fn operation_view(operation: &OperationDetails) -> Html {
+    let destinations = operation
+        .destinations
+        .iter()
+        .map(|(address, value)| {
+            html! {
+                <div class="display-field">
+                    <strong>{"Destination:"}</strong>
+                    <span>{address}</span>
+                    <span>{format!("{value} SATS")}</span>
+                </div>
+            }
+        })
+        .collect::<Html>();
+
+    match operation.action {
+        Action::ChannelRequest => {
+            let channel = operation.channel.as_ref().map_or_else(
+                || html! {},
+                |channel| {
+                    html! {
+                        <>
+                            <div class="display-field">
+                                <strong>{"Funding output index:"}</strong>
+                                <span>{channel.output_index}</span>
+                            </div>
+                            <div class="display-field">
+                                <strong>{"Capacity:"}</strong>
+                                <span>{channel.capacity.to_sat()}</span>
+                                <span>{"SATS"}</span>
+                            </div>
+                            <label>{"Funding keys:"}</label>
+                            {
+                                channel.pubkeys.iter().map(|k| html! {
+                                    <div class="display-field"><span>{k.to_string()}</span></div>
+                                }).collect::<Html>()
+                            }
+                        </>
+                    }
+                },
+            );
+
+            html! {
+                <>
+                    <div class="display-field">
+                        <strong>{"Operation Type:"}</strong>
+                        <span>{operation.action.to_string()}</span>
+                    </div>
+                    <div class="display-field">
+                        <strong>{"Channel amount:"}</strong>
+                        <span>{operation.amount.map(|a| a.to_btc().to_string()).unwrap_or_default()}</span>
+                        <span>{"BTC"}</span>
+                    </div>
+                    <label>{"Funding output:"}</label>
+                    {destinations}
+                    {channel}
+                </>
+            }
+        }
+        Action::Withdrawal => html! {
+            <>
+                <div class="display-field">
+                    <strong>{"Operation Type:"}</strong>
+                    <span>{operation.action.to_string()}</span>
+                </div>
+                {destinations}
+            </>
+        },
+    }
+}
+
 #[function_component(ApprovePSBT)]
 pub fn approve_psbt() -> Html {
     let password = use_context::<UserContext>()
@@ -28,16 +170,39 @@ pub fn approve_psbt() -> Html {
     let location = use_location().unwrap();
     let state = location.state::<State>().unwrap();
     let storage = Rc::new(RefCell::new(UserStorage::read(LocalStorage::default())));
-    let default_wallet = storage.borrow().get_default_wallet();
-    let selected_wallet = use_state(|| default_wallet);
-    let error = use_state(String::default);
-    let selected_wallet_value = (*selected_wallet).clone();
-    let error_value = (*error).clone();
     let default_value = &OperationRequestData::default();
     let operation_data = state
         .get_ref::<OperationRequestData>()
         .unwrap_or(default_value);
-    let disabled = password.is_empty();
+    // wallets whose keys are in the PSBT, plus the ones without a known master
+    // fingerprint as they can't be ruled out
+    let signing_wallets = operation_data
+        .psbt
+        .as_ref()
+        .and_then(|psbt| {
+            decode_psbt_signing_wallets(psbt.as_bytes(), &storage.borrow().wallets).ok()
+        })
+        .unwrap_or_default();
+    let selected_wallets = {
+        let default_wallet = storage.borrow().get_default_wallet();
+        let signing_wallets = &signing_wallets;
+        use_state(move || -> BTreeSet<String> {
+            if signing_wallets.matching.is_empty() {
+                signing_wallets
+                    .unknown
+                    .iter()
+                    .filter(|w| **w == default_wallet)
+                    .cloned()
+                    .collect()
+            } else {
+                signing_wallets.matching.iter().cloned().collect()
+            }
+        })
+    };
+    let error = use_state(String::default);
+    let selected_wallets_value = (*selected_wallets).clone();
+    let error_value = (*error).clone();
+    let disabled = password.is_empty() || selected_wallets_value.is_empty();
 
     if operation_data.psbt.is_none() {
         return html! {
@@ -49,27 +214,41 @@ pub fn approve_psbt() -> Html {
     }
 
     let onclick_save = {
-        let psbt = operation_data.psbt.clone().unwrap();
+        let operation_data = operation_data.clone();
         let navigator = navigator.clone();
-        let selected_wallet_value = selected_wallet_value.clone();
+        let selected_wallets_value = selected_wallets_value.clone();
         let storage = storage.clone();
         Callback::from(move |_: MouseEvent| {
             if password.is_empty() {
                 return;
             }
             let settings_storage = SettingsStorage::read(LocalStorage::default());
+            let request = OperationRequest {
+                request_type: operation_data.request_type.as_deref(),
+                amount: operation_data.amount.as_deref(),
+                node_pubkey: operation_data.node_pubkey.as_deref(),
+            };
 
-            let result = storage
-                .borrow_mut()
-                .get_wallet_mut(&selected_wallet_value)
-                .ok_or_else(|| anyhow!("Wallet not found"))
-                .and_then(|wallet| {
-                    decode_psbt_and_sign(&psbt, wallet, &password, settings_storage.get_network())
-                        .map_err(|e| anyhow!("Error while signing PSBT {e}"))
-                })
-                .and_then(|signed_psbt| {
-                    paste_psbt(&signed_psbt).map_err(|_| anyhow!("Error while pasting PSBT"))
-                });
+            let result = decode_psbt_and_sign_with_wallets(
+                operation_data
+                    .psbt
+                    .as_deref()
+                    .unwrap_or_default()
+                    .as_bytes(),
+                storage
+                    .borrow_mut()
+                    .wallets
+                    .iter_mut()
+                    .filter(|w| selected_wallets_value.contains(&w.name)),
+                &password,
+                settings_storage.get_network(),
+                settings_storage.get_allow_unverified_inputs(),
+                Some(&request),
+            )
+            .map_err(|e| anyhow!("Error while signing PSBT {e:#}"))
+            .and_then(|signed_psbt| {
+                paste_psbt(&signed_psbt).map_err(|_| anyhow!("Error while pasting PSBT"))
+            });
 
             match result {
                 Ok(_) => {
@@ -81,23 +260,65 @@ pub fn approve_psbt() -> Html {
         })
     };
 
-    let onchange = {
-        Callback::from(move |value: SelectItem| {
-            selected_wallet.set(value.label);
-        })
-    };
-
     let onclick_goback = { Callback::from(move |_: MouseEvent| navigator.back()) };
 
     let psbt = operation_data.psbt.clone().unwrap();
-    let items: Vec<SelectItem> = storage
+    let wallets_section =
+        if signing_wallets.matching.is_empty() && signing_wallets.unknown.is_empty() {
+            html! { <div class="error">{"None of your wallets has keys in this PSBT"}</div> }
+        } else {
+            signing_wallets
+                .matching
+                .iter()
+                .chain(&signing_wallets.unknown)
+                .map(|name| {
+                    let checked = selected_wallets_value.contains(name);
+                    let onchange = {
+                        let selected_wallets = selected_wallets.clone();
+                        let name = name.clone();
+                        Callback::from(move |_: Event| {
+                            let mut wallets = (*selected_wallets).clone();
+                            if !wallets.remove(&name) {
+                                wallets.insert(name.clone());
+                            }
+                            selected_wallets.set(wallets);
+                        })
+                    };
+                    let label = if signing_wallets.matching.contains(name) {
+                        name.clone()
+                    } else {
+                        format!("{name} (unknown master fingerprint)")
+                    };
+                    html! {
+                        <div class="checkbox-container">
+                            <input type="checkbox" checked={checked} {onchange} />
+                            <label>{label}</label>
+                        </div>
+                    }
+                })
+                .collect::<Html>()
+        };
+    let operation = OperationDetails::new(
+        &psbt,
+        operation_data.request_type.as_deref(),
+        operation_data.amount.as_deref(),
+        operation_data.node_pubkey.as_deref(),
+        SettingsStorage::read(LocalStorage::default()).get_network(),
+    );
+    let disabled = disabled || operation.is_err();
+    let operation_section = operation.map_or_else(
+        |e| html! { <div class="error">{format!("This request can't be signed: {e}")}</div> },
+        |operation| operation_view(&operation),
+    );
+    let own_fingerprints: BTreeSet<_> = storage
         .borrow()
         .wallets
         .iter()
-        .map(|w| SelectItem::new(&w.name, &w.name))
+        .filter(|w| selected_wallets_value.contains(&w.name))
+        .filter_map(|w| w.master_fingerprint)
         .collect();
+    let policies = decode_psbt_policies(&psbt, &own_fingerprints).unwrap_or_default();
     let psbt = PSBTDetails::from_str(&psbt).unwrap_or_default();
-    let data = operation_data.clone();
     html! {
         <>
             <h class="title">{"Approve PSBT"}</h>
@@ -105,21 +326,17 @@ pub fn approve_psbt() -> Html {
                 <strong>{"Tx Id:"}</strong>
                 <span>{psbt.tx_id}</span>
             </div>
-            <div class="display-field">
-                <strong>{"Operation Type:"}</strong>
-                <span>{data.request_type.clone()}</span>
-            </div>
-            <div class="display-field">
-                <strong>{"Amount:"}</strong>
-                <span>{data.amount}</span>
-                <span>{"BTC"}</span>
-            </div>
+            {operation_section}
             <div class="display-field">
                 <strong>{"Fee:"}</strong>
                 <span>{psbt.fee}</span>
                 <span>{"SATS"}</span>
             </div>
-            <Select {onchange} items={items} default={selected_wallet_value}/>
+            {sighash_view(&psbt.sighash_types)}
+            {policy_view(&policies)}
+            { for psbt.warnings.iter().map(|w| html! { <div class="error">{w}</div> }) }
+            <label>{"Sign with:"}</label>
+            {wallets_section}
             <div class="error">{error_value}</div>
             <div class="button-bar">
                 <button class="cancel" onclick={onclick_goback}>{"Go back"}</button>
@@ -0,0 +1,167 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    components::{text_input::TextInput, textarea::TextArea},
+    context::UserContext,
+    utils::{
+        helpers::{decode_url_string, get_clipboard},
+        storage::LocalStorage,
+    },
+};
+use anyhow::{anyhow, Result};
+use signer::storage::{SettingsStorage, UserStorage};
+use web_sys::MouseEvent;
+use yew::prelude::*;
+use yew_router::prelude::use_navigator;
+
+#[derive(Properties, PartialEq, Eq)]
+pub struct Props {
+    pub wallet_name: String,
+}
+
+#[function_component(Bsms)]
+pub fn bsms(props: &Props) -> Html {
+    let decoded_wallet_name = decode_url_string(&props.wallet_name).unwrap();
+    let password = use_context::<UserContext>()
+        .unwrap()
+        .password
+        .clone()
+        .unwrap_or_default();
+    let navigator = use_navigator().unwrap();
+    let storage = Rc::new(RefCell::new(UserStorage::read(LocalStorage::default())));
+    let wallet_derivation = storage
+        .borrow()
+        .get_wallet_ref(&decoded_wallet_name)
+        .map(|w| w.derivation.to_string())
+        .unwrap_or_default();
+    let token = use_state(|| "00".to_string());
+    let description = use_state(String::default);
+    let derivation = use_state(|| wallet_derivation);
+    let key_record = use_state(String::default);
+    let descriptor_record = use_state(String::default);
+    let descriptor = use_state(|| {
+        storage
+            .borrow()
+            .get_wallet_ref(&decoded_wallet_name)
+            .and_then(|w| w.descriptor.clone())
+            .unwrap_or_default()
+    });
+    let error = use_state(String::default);
+    let token_value = (*token).clone();
+    let description_value = (*description).clone();
+    let derivation_value = (*derivation).clone();
+    let key_record_value = (*key_record).clone();
+    let descriptor_record_value = (*descriptor_record).clone();
+    let descriptor_value = (*descriptor).clone();
+    let error_value = (*error).clone();
+
+    let onchange_token = Callback::from(move |value: Result<String>| {
+        let _ = value.map(|v| token.set(v));
+    });
+
+    let onchange_description = Callback::from(move |value: Result<String>| {
+        let _ = value.map(|v| description.set(v));
+    });
+
+    let onchange_derivation = Callback::from(move |value: Result<String>| {
+        let _ = value.map(|v| derivation.set(v));
+    });
+
+    let onchange_descriptor_record = Callback::from(move |value: Result<String>| {
+        let _ = value.map(|v| descriptor_record.set(v));
+    });
+
+    let onclick_generate = {
+        let storage = storage.clone();
+        let decoded_wallet_name = decoded_wallet_name.clone();
+        let password = password.clone();
+        let token_value = token_value.clone();
+        let description_value = description_value.clone();
+        let derivation_value = derivation_value.clone();
+        let error = error.clone();
+        Callback::from(move |_: MouseEvent| {
+            let settings = SettingsStorage::read(LocalStorage::default());
+            let record = storage
+                .borrow_mut()
+                .get_wallet_mut(&decoded_wallet_name)
+                .ok_or_else(|| anyhow!("Wallet not found"))
+                .and_then(|w| {
+                    w.bsms_key_record(
+                        &token_value,
+                        &description_value,
+                        &derivation_value,
+                        &password,
+                        settings.get_network(),
+                    )
+                });
+
+            match record {
+                Ok(record) => {
+                    error.set(String::default());
+                    key_record.set(record);
+                }
+                Err(e) => error.set(format!("Error while generating key record {e}")),
+            }
+        })
+    };
+
+    let onclick_copy = {
+        let key_record_value = key_record_value.clone();
+        Callback::from(move |_: MouseEvent| {
+            let _ = get_clipboard().map(|c| c.write_text(&key_record_value));
+        })
+    };
+
+    let onclick_import = {
+        let descriptor_record_value = descriptor_record_value.clone();
+        Callback::from(move |_: MouseEvent| {
+            let settings = SettingsStorage::read(LocalStorage::default());
+            let mut s = storage.borrow_mut();
+            let result = s
+                .get_wallet_mut(&decoded_wallet_name)
+                .ok_or_else(|| anyhow!("Wallet not found"))
+                .and_then(|w| {
+                    w.import_bsms_descriptor_record(
+                        &descriptor_record_value,
+                        &password,
+                        settings.get_network(),
+                    )?;
+                    Ok(w.descriptor.clone().unwrap_or_default())
+                })
+                .and_then(|imported| s.save().map(|_| imported));
+
+            match result {
+                Ok(imported) => {
+                    error.set(String::default());
+                    descriptor.set(imported);
+                }
+                Err(e) => error.set(format!("Error while importing descriptor record {e}")),
+            }
+        })
+    };
+
+    let onclick_goback = Callback::from(move |_: MouseEvent| navigator.back());
+
+    html! {
+        <>
+            <h class="title">{"Multisig setup (BSMS)"}</h>
+            <label>{"Token:"}</label>
+            <TextInput value={token_value} onchange={onchange_token} placeholder="00 or the token from the coordinator"/>
+            <label>{"Description:"}</label>
+            <TextInput value={description_value} onchange={onchange_description} placeholder="Input a description for this key"/>
+            <label>{"Derivation:"}</label>
+            <TextInput value={derivation_value} onchange={onchange_derivation} placeholder="Input the key derivation"/>
+            <button onclick={onclick_generate}>{"Generate key record"}</button>
+            <textarea disabled={true} value={key_record_value.clone()}/>
+            <button disabled={key_record_value.is_empty()} onclick={onclick_copy}>{"Copy key record"}</button>
+            <hr />
+            <label>{"Descriptor record:"}</label>
+            <TextArea value={descriptor_record_value.clone()} onchange={onchange_descriptor_record} placeholder="Paste the coordinator's descriptor record"/>
+            <button disabled={descriptor_record_value.is_empty()} onclick={onclick_import}>{"Import descriptor record"}</button>
+            <label>{"Descriptor:"}</label>
+            <textarea disabled={true} value={descriptor_value}/>
+            <div class="error">{error_value}</div>
+            <button class="cancel" onclick={onclick_goback}>{"Go back"}</button>
+        </>
+    }
+}
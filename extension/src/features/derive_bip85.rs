@@ -0,0 +1,211 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    components::{
+        select::{Select, SelectItem},
+        text_input::TextInput,
+        textarea::TextArea,
+    },
+    context::UserContext,
+    switch::Route,
+    utils::{
+        helpers::{decode_url_string, get_clipboard},
+        storage::LocalStorage,
+    },
+};
+use anyhow::{anyhow, Result};
+use signer::{
+    bip85::Bip85Application,
+    storage::{SettingsStorage, UserStorage},
+};
+use web_sys::MouseEvent;
+use yew::prelude::*;
+use yew_router::prelude::use_navigator;
+
+#[derive(Properties, PartialEq, Eq)]
+pub struct Props {
+    pub wallet_name: String,
+}
+
+fn applications() -> Vec<Bip85Application> {
+    vec![
+        Bip85Application::Mnemonic { words: 12 },
+        Bip85Application::Mnemonic { words: 18 },
+        Bip85Application::Mnemonic { words: 24 },
+        Bip85Application::Xprv,
+        Bip85Application::Wif,
+        Bip85Application::Hex { num_bytes: 32 },
+        Bip85Application::Hex { num_bytes: 64 },
+    ]
+}
+
+#[function_component(DeriveBip85)]
+pub fn derive_bip85(props: &Props) -> Html {
+    let decoded_wallet_name = decode_url_string(&props.wallet_name).unwrap();
+    let password = use_context::<UserContext>()
+        .unwrap()
+        .password
+        .clone()
+        .unwrap_or_default();
+    let locked = password.is_empty();
+    let navigator = use_navigator().unwrap();
+    let storage = Rc::new(RefCell::new(UserStorage::read(LocalStorage::default())));
+    let application = use_state(|| Bip85Application::Mnemonic { words: 24 });
+    let index = use_state(|| "0".to_string());
+    let derived = use_state(String::default);
+    let new_wallet_name = use_state(String::default);
+    let error = use_state(String::default);
+    let application_value = *application;
+    let index_value = (*index).clone();
+    let derived_value = (*derived).clone();
+    let new_wallet_name_value = (*new_wallet_name).clone();
+    let error_value = (*error).clone();
+
+    let onchange_application = {
+        let derived = derived.clone();
+        Callback::from(move |item: SelectItem| {
+            if let Some(a) = applications()
+                .into_iter()
+                .find(|a| a.to_string() == item.value)
+            {
+                application.set(a);
+                derived.set(String::default());
+            }
+        })
+    };
+
+    let onchange_index = {
+        let derived = derived.clone();
+        Callback::from(move |value: Result<String>| {
+            if let Ok(value) = value {
+                if value.chars().all(|c| c.is_ascii_digit()) {
+                    index.set(value);
+                    derived.set(String::default());
+                }
+            }
+        })
+    };
+
+    let onchange_name = Callback::from(move |value: Result<String>| {
+        let _ = value.map(|v| new_wallet_name.set(v));
+    });
+
+    let onclick_derive = {
+        let storage = storage.clone();
+        let wallet_name = decoded_wallet_name.clone();
+        let index_value = index_value.clone();
+        let password = password.clone();
+        let error = error.clone();
+        Callback::from(move |_: MouseEvent| {
+            let settings = SettingsStorage::read(LocalStorage::default());
+            let result = index_value
+                .parse::<u32>()
+                .map_err(|_| anyhow!("Invalid index"))
+                .and_then(|index| {
+                    storage
+                        .borrow_mut()
+                        .get_wallet_mut(&wallet_name)
+                        .ok_or_else(|| anyhow!("Wallet not found"))?
+                        .derive_bip85(application_value, index, &password, settings.get_network())
+                });
+
+            match result {
+                Ok(secret) => {
+                    error.set(String::default());
+                    derived.set(secret);
+                }
+                Err(e) => error.set(e.to_string()),
+            }
+        })
+    };
+
+    let onclick_import = {
+        let navigator = navigator.clone();
+        let new_wallet_name_value = new_wallet_name_value.clone();
+        let index_value = index_value.clone();
+        Callback::from(move |_: MouseEvent| {
+            if new_wallet_name_value.trim().is_empty() {
+                error.set("Wallet name is mandatory".into());
+                return;
+            }
+            if storage
+                .borrow()
+                .get_wallet_ref(&new_wallet_name_value)
+                .is_some()
+            {
+                error.set("There is already a wallet with that name".into());
+                return;
+            }
+
+            let settings = SettingsStorage::read(LocalStorage::default());
+            let mut s = storage.borrow_mut();
+            let result = index_value
+                .parse::<u32>()
+                .map_err(|_| anyhow!("Invalid index"))
+                .and_then(|index| {
+                    s.get_wallet_mut(&decoded_wallet_name)
+                        .ok_or_else(|| anyhow!("Wallet not found"))?
+                        .derive_bip85_wallet(
+                            new_wallet_name_value.trim(),
+                            application_value,
+                            index,
+                            &password,
+                            settings.get_network(),
+                        )
+                })
+                .and_then(|wallet| {
+                    s.wallets.push(wallet);
+                    s.save()
+                });
+
+            match result {
+                Ok(_) => navigator.push(&Route::Home),
+                Err(e) => error.set(format!("Error while importing wallet {e}")),
+            }
+        })
+    };
+
+    let onclick_copy = {
+        let derived_value = derived_value.clone();
+        Callback::from(move |_: MouseEvent| {
+            let _ = get_clipboard().map(|c| c.write_text(&derived_value));
+        })
+    };
+
+    let onclick_goback = Callback::from(move |_: MouseEvent| navigator.back());
+
+    let items: Vec<SelectItem> = applications()
+        .iter()
+        .map(|a| SelectItem::new(&a.to_string(), &a.to_string()))
+        .collect();
+
+    let importable = matches!(
+        application_value,
+        Bip85Application::Mnemonic { .. } | Bip85Application::Xprv
+    );
+    let import_section = if importable && !derived_value.is_empty() {
+        html! {
+            <>
+                <TextInput value={new_wallet_name_value} onchange={onchange_name} placeholder="Input the new wallet's name"/>
+                <button onclick={onclick_import}>{"Import as new wallet"}</button>
+            </>
+        }
+    } else {
+        html! {}
+    };
+
+    html! {
+        <>
+            <h class="title">{"Derive child key (BIP85)"}</h>
+            <Select onchange={onchange_application} items={items} default={application_value.to_string()}/>
+            <label>{"Index:"}</label>
+            <TextInput value={index_value} onchange={onchange_index} disabled={locked}/>
+            <button disabled={locked} onclick={onclick_derive}>{"Derive"}</button>
+            <TextArea value={derived_value.clone()} disabled={true} />
+            <button disabled={derived_value.is_empty()} onclick={onclick_copy}>{"Copy"}</button>
+            {import_section}
+            <div class="error">{error_value}</div>
+            <button class="cancel" onclick={onclick_goback}>{"Go back"}</button>
+        </>
+    }
+}
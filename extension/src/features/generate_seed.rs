@@ -1,7 +1,11 @@
 use std::cell::RefCell;
 use std::rc::Rc;
 
+use crate::components::account_path_input::AccountPathInput;
+use crate::components::network_select::NetworkSelect;
+use crate::components::select::{Select, SelectItem};
 use crate::components::text_input::TextInput;
+use crate::components::textarea::TextArea;
 use crate::features::input_password_modal::InputPasswordModal;
 use crate::get_password;
 use crate::switch::Route;
@@ -10,12 +14,18 @@ use crate::utils::macros::with_error_msg;
 use crate::utils::state::PasswordFor;
 use crate::utils::storage::LocalStorage;
 use anyhow::Result;
-use signer::storage::UserStorage;
+use signer::entropy::EntropySource;
+use signer::storage::{SettingsStorage, UserStorage};
 use signer::wallet::Wallet;
+use signer::Network;
 use wasm_bindgen_futures::spawn_local;
 use yew::prelude::*;
 use yew_router::prelude::use_navigator;
 
+const RANDOM: &str = "Random";
+const QUIZ_WORDS: usize = 4;
+const SOURCES: [EntropySource; 2] = [EntropySource::Dice, EntropySource::Coin];
+
 fn generate() -> Result<Vec<String>> {
     Wallet::generate_seed().map(|words| words.split_whitespace().map(ToString::to_string).collect())
 }
@@ -27,6 +37,19 @@ pub fn generate_seed() -> Html {
     let wallet_name = use_state(String::default);
     let error = use_state(String::default);
     let popup_visible = use_state(|| false);
+    let network = use_state(|| SettingsStorage::read(LocalStorage::default()).get_network());
+    let network_value = *network;
+    let derivation = use_state(|| "m".to_string());
+    let derivation_value = (*derivation).clone();
+    let source = use_state(|| None::<EntropySource>);
+    let rolls = use_state(String::default);
+    let mix_rng = use_state(|| true);
+    let explanation = use_state(String::default);
+    let source_value = *source;
+    let rolls_value = (*rolls).clone();
+    let explanation_value = (*explanation).clone();
+    let quiz = use_state(Vec::<(usize, String)>::default);
+    let quiz_value = (*quiz).clone();
     let mut seed_value = (*seed).clone();
     let wallet_name_value = (*wallet_name).clone();
     let error_value = (*error).clone();
@@ -46,8 +69,11 @@ pub fn generate_seed() -> Html {
     );
 
     let on_click_generate = {
+        let seed = seed.clone();
         let error = error.clone();
+        let explanation = explanation.clone();
         Callback::from(move |_: MouseEvent| {
+            explanation.set(String::default());
             let seed_str = generate().map(|words| seed.set(words));
             with_error_msg!(
                 seed_str,
@@ -56,6 +82,62 @@ pub fn generate_seed() -> Html {
         })
     };
 
+    let on_click_generate_from_rolls = {
+        let seed = seed.clone();
+        let error = error.clone();
+        let explanation = explanation.clone();
+        let rolls_value = rolls_value.clone();
+        let mix_rng = *mix_rng;
+        Callback::from(move |_: MouseEvent| {
+            let Some(source) = source_value else {
+                return;
+            };
+            match Wallet::generate_seed_from_rolls(source, &rolls_value, mix_rng) {
+                Ok(user_entropy) => {
+                    error.set(String::default());
+                    explanation.set(user_entropy.explanation());
+                    seed.set(
+                        user_entropy
+                            .mnemonic
+                            .split_whitespace()
+                            .map(ToString::to_string)
+                            .collect(),
+                    );
+                }
+                Err(e) => {
+                    // a seed from other rolls or entropy source must not be saved instead
+                    seed.set(vec![]);
+                    explanation.set(String::default());
+                    error.set(format!("Error while generating seed {e}"));
+                }
+            }
+        })
+    };
+
+    // the seed shown must always come from the selected entropy source
+    let on_change_source = {
+        let error = error.clone();
+        Callback::from(move |item: SelectItem| {
+            let selected = SOURCES.into_iter().find(|s| s.to_string() == item.value);
+            source.set(selected);
+            explanation.set(String::default());
+            error.set(String::default());
+            match selected {
+                Some(_) => seed.set(vec![]),
+                None => seed.set(generate().unwrap_or_default()),
+            }
+        })
+    };
+
+    let on_change_rolls = Callback::from(move |value: Result<String>| {
+        let _ = value.map(|v| rolls.set(v));
+    });
+
+    let on_change_mix_rng = {
+        let mix_rng = mix_rng.clone();
+        Callback::from(move |_: Event| mix_rng.set(!*mix_rng))
+    };
+
     let on_click_copy = {
         let seed = seed_value.clone();
         Callback::from(move |_: MouseEvent| {
@@ -67,11 +149,12 @@ pub fn generate_seed() -> Html {
         let seed = seed_value.clone();
         let wallet_name = wallet_name_value.clone();
         let error = error.clone();
-        let popup_visible = popup_visible.clone();
+        let quiz = quiz.clone();
         let storage = storage.clone();
         Callback::from(move |_: MouseEvent| {
             if wallet_name.is_empty() {
                 error.set("Wallet name is mandatory".into());
+                return;
             }
 
             if storage.borrow().get_wallet_ref(&wallet_name).is_some() {
@@ -83,14 +166,63 @@ pub fn generate_seed() -> Html {
                 return;
             }
 
-            popup_visible.set(true);
+            // ask for some of the words before saving, to make sure the seed was written down
+            error.set(String::default());
+            quiz.set(
+                Wallet::seed_quiz_positions(seed.len(), QUIZ_WORDS)
+                    .into_iter()
+                    .map(|position| (position, String::default()))
+                    .collect(),
+            );
         })
     };
 
+    let on_click_verify_quiz = {
+        let seed = seed_value.clone();
+        let quiz = quiz.clone();
+        let quiz_value = quiz_value.clone();
+        let error = error.clone();
+        let popup_visible = popup_visible.clone();
+        Callback::from(
+            move |_: MouseEvent| match Wallet::check_seed_quiz(&seed, &quiz_value) {
+                Ok(_) => {
+                    error.set(String::default());
+                    quiz.set(vec![]);
+                    popup_visible.set(true);
+                }
+                Err(e) => error.set(e.to_string()),
+            },
+        )
+    };
+
+    let on_click_cancel_quiz = {
+        let quiz = quiz.clone();
+        let error = error.clone();
+        Callback::from(move |_: MouseEvent| {
+            error.set(String::default());
+            quiz.set(vec![]);
+        })
+    };
+
+    fn on_change_quiz(
+        quiz: UseStateHandle<Vec<(usize, String)>>,
+        index: usize,
+    ) -> Callback<Result<String>> {
+        Callback::from(move |value: Result<String>| {
+            let mut quiz_value = (*quiz).clone();
+            quiz_value[index].1 = value.unwrap_or_default();
+            quiz.set(quiz_value);
+        })
+    }
+
     let on_change = Callback::from(move |value: Result<String>| {
         let _ = value.map(|v| wallet_name.set(v));
     });
 
+    let on_change_network = Callback::from(move |value: Network| network.set(value));
+
+    let on_change_derivation = Callback::from(move |value: String| derivation.set(value));
+
     let onclick_goback = {
         let navigator = navigator.clone();
         Callback::from(move |_: MouseEvent| navigator.back())
@@ -99,6 +231,7 @@ pub fn generate_seed() -> Html {
     let onsave = {
         let wallet_name_value = wallet_name_value.clone();
         let seed = seed_value.clone();
+        let derivation = derivation_value.clone();
         let popup_visible = popup_visible.clone();
         Callback::from(move |password: String| {
             let mut wallet = Wallet::default();
@@ -108,10 +241,18 @@ pub fn generate_seed() -> Html {
                 return;
             }
 
-            let parsed = wallet.from_seed_str(&wallet_name_value, &(*seed).join(" "), &password);
+            let parsed = wallet.from_seed_str(
+                &wallet_name_value,
+                &(*seed).join(" "),
+                &derivation,
+                network_value,
+                &password,
+            );
 
-            if parsed.is_err() {
-                error.set("Error while parsing secret".to_string());
+            if let Err(e) = parsed {
+                error.set(format!("Error while parsing secret {e}"));
+                popup_visible.set(false);
+                return;
             }
 
             let mut s = storage.borrow_mut();
@@ -134,11 +275,71 @@ pub fn generate_seed() -> Html {
         })
     };
 
+    let source_items: Vec<SelectItem> = std::iter::once(RANDOM.to_string())
+        .chain(SOURCES.iter().map(ToString::to_string))
+        .map(|label| SelectItem::new(&label, &label))
+        .collect();
+
+    let rolls_section = source_value.map_or_else(
+        || {
+            html! {
+                <button disabled={*popup_visible} onclick={on_click_generate}>{"Generate Again"}</button>
+            }
+        },
+        |source| {
+            let placeholder = match source {
+                EntropySource::Dice => format!("Input at least {} dice rolls (1-6)", source.min_rolls()),
+                EntropySource::Coin => format!("Input at least {} coin flips (H/T)", source.min_rolls()),
+            };
+            html! {
+                <>
+                    <TextArea value={rolls_value} onchange={on_change_rolls} {placeholder}/>
+                    <div class="checkbox-container">
+                        <input id="mix-rng-checkbox" type="checkbox" checked={*mix_rng} onchange={on_change_mix_rng} />
+                        <label>{"Mix with random entropy"}</label>
+                    </div>
+                    <button disabled={*popup_visible} onclick={on_click_generate_from_rolls}>{"Generate from rolls"}</button>
+                    <pre>{explanation_value}</pre>
+                </>
+            }
+        },
+    );
+
+    if !quiz_value.is_empty() {
+        return html! {
+            <>
+                <h class="title">{"Verify your backup"}</h>
+                <p>{"Input the following words from the seed you wrote down"}</p>
+                {
+                    quiz_value.iter().enumerate().map(|(index, (position, word))| {
+                        html! {
+                            <div key={*position}>
+                                <label>{format!("Word #{}", position + 1)}</label>
+                                <TextInput value={word.clone()} onchange={on_change_quiz(quiz.clone(), index)}/>
+                            </div>
+                        }
+                    }).collect::<Html>()
+                }
+                <div class="error">{error_value}</div>
+                <div class="button-bar">
+                    <button class="cancel" onclick={on_click_cancel_quiz}>{"Show seed again"}</button>
+                    <button onclick={on_click_verify_quiz}>{"Verify"}</button>
+                </div>
+            </>
+        };
+    }
+
+    let seed_missing = seed_value.len() != 24;
     seed_value.resize(24, String::default());
     html! {
         <>
             <h class="title">{"Import from Seed"}</h>
             <TextInput value={wallet_name_value} onchange={on_change} placeholder="Input your wallet's name"/>
+            <NetworkSelect value={network_value} onchange={on_change_network}/>
+            <AccountPathInput network={network_value} value={derivation_value} onchange={on_change_derivation}/>
+            <label>{"Entropy:"}</label>
+            <Select onchange={on_change_source} items={source_items} default={RANDOM.to_string()}/>
+            {rolls_section}
             <ol>
                 {
                     seed_value.clone().iter().enumerate().map(|(index, word)| {
@@ -151,11 +352,10 @@ pub fn generate_seed() -> Html {
                 }
             </ol>
             <div class="error">{error_value}</div>
-            <button disabled={*popup_visible} onclick={on_click_generate}>{"Generate Again"}</button>
-            <button disabled={*popup_visible} onclick={on_click_copy}>{"Copy Seed"}</button>
+            <button disabled={*popup_visible || seed_missing} onclick={on_click_copy}>{"Copy Seed"}</button>
             <div class="button-bar">
                 <button class="cancel" onclick={onclick_goback}>{"Go back"}</button>
-                <button disabled={*popup_visible} onclick={on_click_save}>{"Save"}</button>
+                <button disabled={*popup_visible || seed_missing} onclick={on_click_save}>{"Save"}</button>
             </div>
             <InputPasswordModal
                 password_for={PasswordFor::ImportingSecret}
@@ -0,0 +1,285 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::components::network_select::NetworkSelect;
+use crate::components::text_input::TextInput;
+use crate::features::input_password_modal::InputPasswordModal;
+use crate::switch::Route;
+use crate::utils::helpers::get_clipboard;
+use crate::utils::state::PasswordFor;
+use crate::utils::storage::LocalStorage;
+use anyhow::{anyhow, Result};
+use signer::slip39::GroupSpec;
+use signer::storage::{SettingsStorage, UserStorage};
+use signer::wallet::Wallet;
+use signer::Network;
+use yew::prelude::*;
+use yew_router::prelude::use_navigator;
+
+fn parse_groups(
+    group_threshold: &str,
+    groups: &[(String, String)],
+) -> Result<(u8, Vec<GroupSpec>)> {
+    let group_threshold = group_threshold
+        .parse::<u8>()
+        .map_err(|_| anyhow!("Invalid group threshold"))?;
+    let groups = groups
+        .iter()
+        .map(|(threshold, count)| {
+            Ok(GroupSpec {
+                member_threshold: threshold
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid member threshold"))?,
+                member_count: count.parse().map_err(|_| anyhow!("Invalid member count"))?,
+            })
+        })
+        .collect::<Result<Vec<GroupSpec>>>()?;
+    Ok((group_threshold, groups))
+}
+
+#[function_component(GenerateSlip39)]
+pub fn generate_slip39() -> Html {
+    let navigator = use_navigator().unwrap();
+    let group_threshold = use_state(|| "1".to_string());
+    let groups = use_state(|| vec![("2".to_string(), "3".to_string())]);
+    let passphrase = use_state(String::default);
+    let shares = use_state(Vec::<Vec<String>>::default);
+    let wallet_name = use_state(String::default);
+    let error = use_state(String::default);
+    let popup_visible = use_state(|| false);
+    let network = use_state(|| SettingsStorage::read(LocalStorage::default()).get_network());
+    let network_value = *network;
+    let group_threshold_value = (*group_threshold).clone();
+    let groups_value = (*groups).clone();
+    let passphrase_value = (*passphrase).clone();
+    let shares_value = (*shares).clone();
+    let wallet_name_value = (*wallet_name).clone();
+    let error_value = (*error).clone();
+    let storage = Rc::new(RefCell::new(UserStorage::read(LocalStorage::default())));
+
+    let on_click_generate = {
+        let group_threshold_value = group_threshold_value.clone();
+        let groups_value = groups_value.clone();
+        let passphrase_value = passphrase_value.clone();
+        let shares = shares.clone();
+        let error = error.clone();
+        Callback::from(move |_: MouseEvent| {
+            let generated = parse_groups(&group_threshold_value, &groups_value).and_then(
+                |(group_threshold, groups)| {
+                    Wallet::generate_slip39(group_threshold, &groups, &passphrase_value)
+                },
+            );
+            match generated {
+                Ok(generated) => {
+                    error.set(String::default());
+                    shares.set(generated);
+                }
+                Err(e) => error.set(format!("Error while generating shares {e}")),
+            }
+        })
+    };
+
+    let on_click_add_group = {
+        let groups = groups.clone();
+        let shares = shares.clone();
+        Callback::from(move |_: MouseEvent| {
+            let mut value = (*groups).clone();
+            value.push(("2".to_string(), "3".to_string()));
+            groups.set(value);
+            shares.set(vec![]);
+        })
+    };
+
+    let on_click_remove_group = {
+        let groups = groups.clone();
+        let shares = shares.clone();
+        Callback::from(move |_: MouseEvent| {
+            let mut value = (*groups).clone();
+            if value.len() > 1 {
+                value.pop();
+            }
+            groups.set(value);
+            shares.set(vec![]);
+        })
+    };
+
+    let on_click_copy = {
+        let shares_value = shares_value.clone();
+        Callback::from(move |_: MouseEvent| {
+            let text = shares_value
+                .iter()
+                .map(|group| group.join("\n"))
+                .collect::<Vec<String>>()
+                .join("\n\n");
+            let _ = get_clipboard().map(|c| c.write_text(&text));
+        })
+    };
+
+    let on_click_save = {
+        let wallet_name = wallet_name_value.clone();
+        let error = error.clone();
+        let popup_visible = popup_visible.clone();
+        let storage = storage.clone();
+        let shares_value = shares_value.clone();
+        Callback::from(move |_: MouseEvent| {
+            if wallet_name.is_empty() {
+                error.set("Wallet name is mandatory".into());
+                return;
+            }
+
+            if storage.borrow().get_wallet_ref(&wallet_name).is_some() {
+                error.set("There is already a wallet with that name".into());
+                return;
+            }
+            if shares_value.is_empty() {
+                error.set("Generate the shares first".into());
+                return;
+            }
+
+            popup_visible.set(true);
+        })
+    };
+
+    let on_change_group_threshold = {
+        let shares = shares.clone();
+        Callback::from(move |value: Result<String>| {
+            let _ = value.map(|v| group_threshold.set(v));
+            shares.set(vec![]);
+        })
+    };
+
+    fn on_change_group(
+        groups: UseStateHandle<Vec<(String, String)>>,
+        shares: UseStateHandle<Vec<Vec<String>>>,
+        index: usize,
+        is_threshold: bool,
+    ) -> Callback<Result<String>> {
+        Callback::from(move |value: Result<String>| {
+            let mut groups_value = (*groups).clone();
+            let value = value.unwrap_or_default();
+            if is_threshold {
+                groups_value[index].0 = value;
+            } else {
+                groups_value[index].1 = value;
+            }
+            groups.set(groups_value);
+            shares.set(vec![]);
+        })
+    }
+
+    let on_change_passphrase = {
+        let shares = shares.clone();
+        Callback::from(move |value: Result<String>| {
+            let _ = value.map(|v| passphrase.set(v));
+            shares.set(vec![]);
+        })
+    };
+
+    let on_change_name = Callback::from(move |value: Result<String>| {
+        let _ = value.map(|v| wallet_name.set(v));
+    });
+
+    let on_change_network = Callback::from(move |value: Network| network.set(value));
+
+    let onclick_goback = {
+        let navigator = navigator.clone();
+        Callback::from(move |_: MouseEvent| navigator.back())
+    };
+
+    let onsave = {
+        let wallet_name_value = wallet_name_value.clone();
+        let shares_value = shares_value.clone();
+        let passphrase_value = passphrase_value.clone();
+        let popup_visible = popup_visible.clone();
+        Callback::from(move |password: String| {
+            let mut wallet = Wallet::default();
+
+            let all_shares: Vec<String> = shares_value.iter().flatten().cloned().collect();
+            let parsed = wallet.from_slip39_shares(
+                &wallet_name_value,
+                &all_shares,
+                &passphrase_value,
+                network_value,
+                &password,
+            );
+
+            if parsed.is_err() {
+                error.set("Error while parsing secret".to_string());
+                popup_visible.set(false);
+                return;
+            }
+
+            let mut s = storage.borrow_mut();
+            s.wallets.push(wallet);
+            let stored = s.save();
+
+            if stored.is_err() {
+                error.set("Error while storing wallet".to_string());
+            } else {
+                navigator.push(&Route::Home);
+            }
+            popup_visible.set(false);
+        })
+    };
+
+    let oncancel = {
+        let popup_visible = popup_visible.clone();
+        Callback::from(move |_| {
+            popup_visible.set(false);
+        })
+    };
+
+    html! {
+        <>
+            <h class="title">{"Generate SLIP-39 shares"}</h>
+            <TextInput value={wallet_name_value} onchange={on_change_name} placeholder="Input your wallet's name"/>
+            <NetworkSelect value={network_value} onchange={on_change_network}/>
+            <label>{"Groups required to recover:"}</label>
+            <TextInput value={group_threshold_value} onchange={on_change_group_threshold}/>
+            {
+                groups_value.iter().enumerate().map(|(index, (threshold, count))| {
+                    html! {
+                        <div class="display-field" key={index}>
+                            <strong>{format!("Group {}:", index + 1)}</strong>
+                            <TextInput value={threshold.clone()} onchange={on_change_group(groups.clone(), shares.clone(), index, true)}/>
+                            <span>{"of"}</span>
+                            <TextInput value={count.clone()} onchange={on_change_group(groups.clone(), shares.clone(), index, false)}/>
+                        </div>
+                    }
+                }).collect::<Html>()
+            }
+            <div class="button-bar">
+                <button onclick={on_click_remove_group}>{"Remove group"}</button>
+                <button onclick={on_click_add_group}>{"Add group"}</button>
+            </div>
+            <TextInput itype="password" value={passphrase_value} onchange={on_change_passphrase} placeholder="SLIP-39 passphrase (optional)"/>
+            <button disabled={*popup_visible} onclick={on_click_generate}>{"Generate"}</button>
+            {
+                shares_value.iter().enumerate().map(|(index, group)| {
+                    html! {
+                        <>
+                            <strong>{format!("Group {}", index + 1)}</strong>
+                            <ol>
+                                {
+                                    group.iter().map(|share| html! { <li>{share}</li> }).collect::<Html>()
+                                }
+                            </ol>
+                        </>
+                    }
+                }).collect::<Html>()
+            }
+            <div class="error">{error_value}</div>
+            <button disabled={*popup_visible || shares_value.is_empty()} onclick={on_click_copy}>{"Copy Shares"}</button>
+            <div class="button-bar">
+                <button class="cancel" onclick={onclick_goback}>{"Go back"}</button>
+                <button disabled={*popup_visible} onclick={on_click_save}>{"Save"}</button>
+            </div>
+            <InputPasswordModal
+                password_for={PasswordFor::ImportingSecret}
+                visible={*popup_visible}
+                onsave={onsave}
+                oncancel={oncancel}
+            />
+        </>
+    }
+}
@@ -3,7 +3,7 @@ use crate::{
     switch::{ImportWalletRoute, Route},
     utils::{events::EventManager, storage::LocalStorage},
 };
-use signer::storage::UserStorage;
+use signer::storage::{SettingsStorage, UserStorage};
 use web_sys::MouseEvent;
 use yew::prelude::*;
 use yew_router::prelude::use_navigator;
@@ -35,6 +35,13 @@ pub fn home() -> Html {
         })
     };
 
+    let onclick_manage = {
+        let navigator = navigator.clone();
+        Callback::from(move |_: MouseEvent| {
+            navigator.push(&Route::ManageWallets);
+        })
+    };
+
     let onclick_settings = {
         let navigator = navigator.clone();
         Callback::from(move |_: MouseEvent| {
@@ -52,6 +59,70 @@ pub fn home() -> Html {
         })
     };
 
+    let onclick_bip85 = {
+        let selected_wallet_value = selected_wallet_value.clone();
+        let navigator = navigator.clone();
+        Callback::from(move |_: MouseEvent| {
+            navigator.push(&Route::DeriveBip85 {
+                wallet_name: selected_wallet_value.clone(),
+            });
+        })
+    };
+
+    let onclick_add_account = {
+        let selected_wallet_value = selected_wallet_value.clone();
+        let navigator = navigator.clone();
+        Callback::from(move |_: MouseEvent| {
+            navigator.push(&Route::AddAccount {
+                wallet_name: selected_wallet_value.clone(),
+            });
+        })
+    };
+
+    let onclick_verify_backup = {
+        let selected_wallet_value = selected_wallet_value.clone();
+        let navigator = navigator.clone();
+        Callback::from(move |_: MouseEvent| {
+            navigator.push(&Route::VerifyBackup {
+                wallet_name: selected_wallet_value.clone(),
+            });
+        })
+    };
+
+    let onclick_bsms = {
+        let selected_wallet_value = selected_wallet_value.clone();
+        let navigator = navigator.clone();
+        Callback::from(move |_: MouseEvent| {
+            navigator.push(&Route::Bsms {
+                wallet_name: selected_wallet_value.clone(),
+            });
+        })
+    };
+
+    let onclick_sign_message = {
+        let selected_wallet_value = selected_wallet_value.clone();
+        let navigator = navigator.clone();
+        Callback::from(move |_: MouseEvent| {
+            navigator.push(&Route::SignMessage {
+                wallet_name: selected_wallet_value.clone(),
+            });
+        })
+    };
+
+    let onclick_verify_message = {
+        let navigator = navigator.clone();
+        Callback::from(move |_: MouseEvent| {
+            navigator.push(&Route::VerifyMessage);
+        })
+    };
+
+    let onclick_sign_batch = {
+        let navigator = navigator.clone();
+        Callback::from(move |_: MouseEvent| {
+            navigator.push(&Route::SignBatch);
+        })
+    };
+
     let onclick_sign_psbt = {
         Callback::from(move |_: MouseEvent| {
             navigator.push(&Route::ApprovePastedPSBT);
@@ -68,6 +139,12 @@ pub fn home() -> Html {
         })
     };
 
+    let settings = SettingsStorage::read(LocalStorage::default());
+    let network = storage
+        .get_wallet_ref(&selected_wallet_value)
+        .map(|w| w.get_network(settings.get_network()).to_string())
+        .unwrap_or_default();
+
     let items: Vec<SelectItem> = storage
         .wallets
         .iter()
@@ -77,9 +154,21 @@ pub fn home() -> Html {
         <>
             <h class="title">{"Your Wallets"}</h>
             <Select {onchange} items={items} default={selected_wallet_value}/>
+            <div class="display-field">
+                <strong>{"Network:"}</strong>
+                <span>{network}</span>
+            </div>
             <button onclick={onclick_import}>{"Import another wallet"}</button>
             <button onclick={onclick_export}>{"Export XPUB"}</button>
+            <button onclick={onclick_add_account}>{"Add account from this seed"}</button>
+            <button onclick={onclick_verify_backup}>{"Verify my backup"}</button>
+            <button onclick={onclick_bip85}>{"Derive child key (BIP85)"}</button>
+            <button onclick={onclick_bsms}>{"Multisig setup (BSMS)"}</button>
             <button onclick={onclick_sign_psbt}>{"Sign a PSBT"}</button>
+            <button onclick={onclick_sign_batch}>{"Sign a batch of PSBTs"}</button>
+            <button onclick={onclick_sign_message}>{"Sign a message"}</button>
+            <button onclick={onclick_verify_message}>{"Verify a message"}</button>
+            <button onclick={onclick_manage}>{"Manage wallets"}</button>
             <button onclick={onclick_settings}>{"Settings"}</button>
         </>
     }
@@ -1,3 +1,5 @@
+use crate::components::account_path_input::AccountPathInput;
+use crate::components::network_select::NetworkSelect;
 use crate::components::text_input::TextInput;
 use crate::features::input_password_modal::InputPasswordModal;
 use crate::switch::Route;
@@ -5,8 +7,9 @@ use crate::utils::macros::with_error_msg;
 use crate::utils::state::PasswordFor;
 use crate::utils::storage::LocalStorage;
 use anyhow::{anyhow, Result};
-use signer::storage::UserStorage;
+use signer::storage::{SettingsStorage, UserStorage};
 use signer::wallet::Wallet;
+use signer::Network;
 use std::cell::RefCell;
 use std::rc::Rc;
 use std::vec;
@@ -22,6 +25,10 @@ pub fn import_from_seed() -> Html {
     let wallet_name = use_state(String::default);
     let error = use_state(String::default);
     let popup_visible = use_state(|| false);
+    let network = use_state(|| SettingsStorage::read(LocalStorage::default()).get_network());
+    let network_value = *network;
+    let derivation = use_state(|| "m".to_string());
+    let derivation_value = (*derivation).clone();
     let seed_value = (*seed).clone();
     let wallet_name_value = (*wallet_name).clone();
     let error_value = (*error).clone();
@@ -82,6 +89,10 @@ pub fn import_from_seed() -> Html {
         let _ = value.map(|v| wallet_name.set(v));
     });
 
+    let on_change_network = Callback::from(move |value: Network| network.set(value));
+
+    let on_change_derivation = Callback::from(move |value: String| derivation.set(value));
+
     let onclick_goback = {
         let navigator = navigator.clone();
         Callback::from(move |_: MouseEvent| navigator.back())
@@ -90,6 +101,7 @@ pub fn import_from_seed() -> Html {
     let onsave = {
         let wallet_name_value = wallet_name_value.clone();
         let seed = seed_value.clone();
+        let derivation = derivation_value.clone();
         let popup_visible = popup_visible.clone();
         Callback::from(move |password: String| {
             let mut wallet = Wallet::default();
@@ -99,10 +111,18 @@ pub fn import_from_seed() -> Html {
                 return;
             }
 
-            let parsed = wallet.from_seed_str(&wallet_name_value, &(*seed).join(" "), &password);
-
-            if parsed.is_err() {
-                error.set("Error while parsing secret".to_string());
+            let parsed = wallet.from_seed_str(
+                &wallet_name_value,
+                &(*seed).join(" "),
+                &derivation,
+                network_value,
+                &password,
+            );
+
+            if let Err(e) = parsed {
+                error.set(format!("Error while parsing secret {e}"));
+                popup_visible.set(false);
+                return;
             }
 
             let mut s = storage.borrow_mut();
@@ -137,6 +157,8 @@ pub fn import_from_seed() -> Html {
         <>
             <h class="title">{"Import from Seed"}</h>
             <TextInput disabled={*popup_visible} value={wallet_name_value} onchange={on_change} placeholder="Input your wallet's name"/>
+            <NetworkSelect value={network_value} onchange={on_change_network}/>
+            <AccountPathInput disabled={*popup_visible} network={network_value} value={derivation_value} onchange={on_change_derivation}/>
             <ol {onpaste}>
                 {
                     seed_value.clone().iter().enumerate().map(|(index, word)| {
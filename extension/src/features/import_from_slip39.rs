@@ -0,0 +1,157 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::components::network_select::NetworkSelect;
+use crate::components::text_input::TextInput;
+use crate::components::textarea::TextArea;
+use crate::features::input_password_modal::InputPasswordModal;
+use crate::switch::Route;
+use crate::utils::state::PasswordFor;
+use crate::utils::storage::LocalStorage;
+use anyhow::Result;
+use signer::slip39::combine_mnemonics;
+use signer::storage::{SettingsStorage, UserStorage};
+use signer::wallet::Wallet;
+use signer::Network;
+use yew::prelude::*;
+use yew_router::prelude::use_navigator;
+
+fn split_shares(shares: &str) -> Vec<String> {
+    shares
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(ToString::to_string)
+        .collect()
+}
+
+#[function_component(ImportFromSlip39)]
+pub fn import_from_slip39() -> Html {
+    let navigator = use_navigator().unwrap();
+    let shares = use_state(String::default);
+    let passphrase = use_state(String::default);
+    let wallet_name = use_state(String::default);
+    let error = use_state(String::default);
+    let popup_visible = use_state(|| false);
+    let network = use_state(|| SettingsStorage::read(LocalStorage::default()).get_network());
+    let network_value = *network;
+    let shares_value = (*shares).clone();
+    let passphrase_value = (*passphrase).clone();
+    let wallet_name_value = (*wallet_name).clone();
+    let error_value = (*error).clone();
+    let storage = Rc::new(RefCell::new(UserStorage::read(LocalStorage::default())));
+
+    let onclick = {
+        let shares = shares_value.clone();
+        let passphrase = passphrase_value.clone();
+        let wallet_name = wallet_name_value.clone();
+        let error = error.clone();
+        let popup_visible = popup_visible.clone();
+        let storage = storage.clone();
+        Callback::from(move |_: MouseEvent| {
+            if wallet_name.is_empty() {
+                error.set("Wallet name is mandatory".into());
+                return;
+            }
+
+            if storage.borrow().get_wallet_ref(&wallet_name).is_some() {
+                error.set("There is already a wallet with that name".into());
+                return;
+            }
+            if let Err(e) = combine_mnemonics(&split_shares(&shares), &passphrase) {
+                error.set(format!("{e}"));
+                return;
+            }
+
+            error.set(String::default());
+            popup_visible.set(true);
+        })
+    };
+
+    let on_change_name = Callback::from(move |value: Result<String>| {
+        let _ = value.map(|v| wallet_name.set(v));
+    });
+
+    let on_change_shares = Callback::from(move |value: Result<String>| {
+        let _ = value.map(|v| shares.set(v));
+    });
+
+    let on_change_passphrase = Callback::from(move |value: Result<String>| {
+        let _ = value.map(|v| passphrase.set(v));
+    });
+
+    let on_change_network = Callback::from(move |value: Network| network.set(value));
+
+    let onclick_goback = {
+        let navigator = navigator.clone();
+        Callback::from(move |_: MouseEvent| navigator.back())
+    };
+
+    let onsave = {
+        let wallet_name_value = wallet_name_value.clone();
+        let shares = shares_value.clone();
+        let passphrase = passphrase_value.clone();
+        let popup_visible = popup_visible.clone();
+        Callback::from(move |password: String| {
+            let mut wallet = Wallet::default();
+
+            if wallet_name_value.is_empty() {
+                error.set("Wallet name is mandatory".into());
+                return;
+            }
+
+            let parsed = wallet.from_slip39_shares(
+                &wallet_name_value,
+                &split_shares(&shares),
+                &passphrase,
+                network_value,
+                &password,
+            );
+
+            if parsed.is_err() {
+                error.set("Error while parsing secret".to_string());
+                popup_visible.set(false);
+                return;
+            }
+
+            let mut s = storage.borrow_mut();
+            s.wallets.push(wallet);
+            let stored = s.save();
+
+            if stored.is_err() {
+                error.set("Error while storing wallet".to_string());
+            } else {
+                navigator.push(&Route::Home);
+            }
+            popup_visible.set(false);
+        })
+    };
+
+    let oncancel = {
+        let popup_visible = popup_visible.clone();
+        Callback::from(move |_| {
+            popup_visible.set(false);
+        })
+    };
+
+    html! {
+        <>
+            <h class="title">{"Import from SLIP-39 shares"}</h>
+            <TextInput disabled={*popup_visible} value={wallet_name_value} onchange={on_change_name} placeholder="Input your wallet's name" />
+            <NetworkSelect value={network_value} onchange={on_change_network}/>
+            <TextArea disabled={*popup_visible} value={shares_value} onchange={on_change_shares} placeholder="Input a quorum of shares, one per line"/>
+            <TextInput disabled={*popup_visible} itype="password" value={passphrase_value} onchange={on_change_passphrase} placeholder="SLIP-39 passphrase (optional)"/>
+            <div class="error">{error_value}</div>
+            <div class="button-bar">
+                <button disabled={*popup_visible} class="cancel" onclick={onclick_goback}>{"Go back"}</button>
+                <button disabled={*popup_visible} {onclick}>{"Save"}</button>
+            </div>
+            <InputPasswordModal
+                password_for={PasswordFor::ImportingSecret}
+                visible={*popup_visible}
+                onsave={onsave}
+                oncancel={oncancel}
+            />
+        </>
+    }
+}
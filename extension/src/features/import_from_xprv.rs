@@ -1,6 +1,7 @@
 use std::cell::RefCell;
 use std::rc::Rc;
 
+use crate::components::network_select::NetworkSelect;
 use crate::components::text_input::TextInput;
 use crate::components::textarea::TextArea;
 use crate::features::input_password_modal::InputPasswordModal;
@@ -8,8 +9,9 @@ use crate::switch::Route;
 use crate::utils::state::PasswordFor;
 use crate::utils::storage::LocalStorage;
 use anyhow::Result;
-use signer::storage::UserStorage;
+use signer::storage::{SettingsStorage, UserStorage};
 use signer::wallet::Wallet;
+use signer::Network;
 use yew::prelude::*;
 use yew_router::prelude::use_navigator;
 
@@ -18,11 +20,15 @@ pub fn import_from_xprv() -> Html {
     let navigator = use_navigator().unwrap();
     let xprv = use_state(String::default);
     let derivation = use_state(String::default);
+    let master_fingerprint = use_state(String::default);
     let wallet_name = use_state(String::default);
     let error = use_state(String::default);
     let popup_visible = use_state(|| false);
+    let network = use_state(|| SettingsStorage::read(LocalStorage::default()).get_network());
+    let network_value = *network;
     let xprv_value = (*xprv).clone();
     let derivation_value = (*derivation).clone();
+    let master_fingerprint_value = (*master_fingerprint).clone();
     let wallet_name_value = (*wallet_name).clone();
     let error_value = (*error).clone();
     let storage = Rc::new(RefCell::new(UserStorage::read(LocalStorage::default())));
@@ -30,6 +36,7 @@ pub fn import_from_xprv() -> Html {
     let onclick = {
         let xprv = xprv_value.clone();
         let derivation = derivation_value.clone();
+        let master_fingerprint = master_fingerprint_value.clone();
         let wallet_name = wallet_name_value.clone();
         let error = error.clone();
         let popup_visible = popup_visible.clone();
@@ -43,7 +50,9 @@ pub fn import_from_xprv() -> Html {
                 error.set("There is already a wallet with that name".into());
                 return;
             }
-            match Wallet::validate(&xprv, &derivation) {
+            let master_fingerprint =
+                (!master_fingerprint.is_empty()).then_some(master_fingerprint.as_str());
+            match Wallet::validate(&xprv, &derivation, master_fingerprint) {
                 Ok(_) => {}
                 Err(e) => {
                     error.set(format!("{e}"));
@@ -67,6 +76,12 @@ pub fn import_from_xprv() -> Html {
         let _ = value.map(|v| derivation.set(v));
     });
 
+    let on_change_master_fingerprint = Callback::from(move |value: Result<String>| {
+        let _ = value.map(|v| master_fingerprint.set(v));
+    });
+
+    let on_change_network = Callback::from(move |value: Network| network.set(value));
+
     let onclick_goback = {
         let navigator = navigator.clone();
         Callback::from(move |_: MouseEvent| navigator.back())
@@ -76,6 +91,7 @@ pub fn import_from_xprv() -> Html {
         let wallet_name_value = wallet_name_value.clone();
         let xprv = xprv_value.clone();
         let derivation = derivation_value.clone();
+        let master_fingerprint = master_fingerprint_value.clone();
         let popup_visible = popup_visible.clone();
         Callback::from(move |password: String| {
             let mut wallet = Wallet::default();
@@ -85,10 +101,19 @@ pub fn import_from_xprv() -> Html {
                 return;
             }
 
-            let parsed = wallet.from_xprv_str(&wallet_name_value, &xprv, &derivation, &password);
-
-            if parsed.is_err() {
-                error.set("Error while parsing secret".to_string());
+            let parsed = wallet.from_xprv_str(
+                &wallet_name_value,
+                &xprv,
+                &derivation,
+                (!master_fingerprint.is_empty()).then_some(master_fingerprint.as_str()),
+                network_value,
+                &password,
+            );
+
+            if let Err(e) = parsed {
+                error.set(format!("Error while parsing secret {e}"));
+                popup_visible.set(false);
+                return;
             }
 
             let mut s = storage.borrow_mut();
@@ -115,8 +140,10 @@ pub fn import_from_xprv() -> Html {
         <>
             <h class="title">{"Import from Seed"}</h>
             <TextInput disabled={*popup_visible} value={wallet_name_value} onchange={on_change_name} placeholder="Input your wallet's name" />
+            <NetworkSelect value={network_value} onchange={on_change_network}/>
             <TextArea disabled={*popup_visible} value={xprv_value} onchange={on_change_xprv} placeholder="tprv/vprv..."/>
             <TextInput disabled={*popup_visible} value={derivation_value}  onchange={on_change_derivation} placeholder="m/n'/n'..."/>
+            <TextInput disabled={*popup_visible} value={master_fingerprint_value} onchange={on_change_master_fingerprint} placeholder="Master fingerprint (optional)"/>
             <div class="error">{error_value}</div>
             <div class="button-bar">
                 <button disabled={*popup_visible} class="cancel" onclick={onclick_goback}>{"Go back"}</button>
@@ -28,6 +28,20 @@ pub fn import_wallet() -> Html {
         })
     };
 
+    let onclick_importslip39 = {
+        let navigator = navigator.clone();
+        Callback::from(move |_: MouseEvent| {
+            navigator.push(&ImportWalletRoute::ImportSlip39);
+        })
+    };
+
+    let onclick_generateslip39 = {
+        let navigator = navigator.clone();
+        Callback::from(move |_: MouseEvent| {
+            navigator.push(&ImportWalletRoute::GenerateSlip39);
+        })
+    };
+
     let onclick_goback = Callback::from(move |_: MouseEvent| navigator.back());
 
     html! {
@@ -37,6 +51,8 @@ pub fn import_wallet() -> Html {
                 <button onclick={onclick_generate}>{"Generate Seed"}</button>
                 <button onclick={onclick_importseed}>{"Import from Seed"}</button>
                 <button onclick={onclick_importxprv}>{"Import from Private Key"}</button>
+                <button onclick={onclick_generateslip39}>{"Generate SLIP-39 Shares"}</button>
+                <button onclick={onclick_importslip39}>{"Import from SLIP-39 Shares"}</button>
                 <button class="cancel" onclick={onclick_goback}>{"Go back"}</button>
             </div>
         </>
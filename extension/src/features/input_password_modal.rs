@@ -41,7 +41,9 @@ pub fn input_password_modal(props: &Props) -> Html {
                 return;
             }
             let _ = match route {
-                PasswordFor::ImportingSecret => focus("confirm-checkbox"),
+                PasswordFor::ImportingSecret
+                | PasswordFor::DeletingWallet
+                | PasswordFor::AllowingAnySighash => focus("confirm-checkbox"),
                 _ => focus("password-input"),
             };
         },
@@ -93,6 +95,8 @@ pub fn input_password_modal(props: &Props) -> Html {
 
     let button_label = match props.password_for {
         PasswordFor::ImportingSecret => "Import",
+        PasswordFor::DeletingWallet => "Delete",
+        PasswordFor::AllowingAnySighash => "Allow",
         PasswordFor::UnlockingApp => "Unlock",
     };
 
@@ -117,16 +121,34 @@ pub fn input_password_modal(props: &Props) -> Html {
                 I understand that if I remove this extension my seed will be lost forever"#}</label>
             </div>
         },
+        PasswordFor::DeletingWallet => html! {
+            <div class="checkbox-container">
+                <input id="confirm-checkbox" type="checkbox" checked={*checkbox_state} onchange={onchange_checkbox} />
+                <label>{r#"I have a backup of this wallet's secret.
+                I understand that once deleted it can't be recovered from this extension"#}</label>
+            </div>
+        },
+        PasswordFor::AllowingAnySighash => html! {
+            <div class="checkbox-container">
+                <input id="confirm-checkbox" type="checkbox" checked={*checkbox_state} onchange={onchange_checkbox} />
+                <label>{r#"I understand that inputs signed without SIGHASH_ALL can be spent
+                in transactions with other inputs or outputs than the ones I approved"#}</label>
+            </div>
+        },
         PasswordFor::UnlockingApp => html! {},
     };
 
     let save_disabled = match props.password_for {
-        PasswordFor::ImportingSecret => !*checkbox_state || password_value.is_empty(),
+        PasswordFor::ImportingSecret
+        | PasswordFor::DeletingWallet
+        | PasswordFor::AllowingAnySighash => !*checkbox_state || password_value.is_empty(),
         PasswordFor::UnlockingApp => password_value.is_empty(),
     };
 
     let title = match props.password_for {
         PasswordFor::ImportingSecret => "Input your password to confirm",
+        PasswordFor::DeletingWallet => "Input your password to delete the wallet",
+        PasswordFor::AllowingAnySighash => "Input your password to allow any sighash type",
         PasswordFor::UnlockingApp => "Input your password to unlock extension",
     };
 
@@ -0,0 +1,245 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    components::text_input::TextInput,
+    features::input_password_modal::InputPasswordModal,
+    switch::{ImportWalletRoute, Route},
+    utils::{state::PasswordFor, storage::LocalStorage},
+};
+use anyhow::Result;
+use signer::storage::UserStorage;
+use web_sys::MouseEvent;
+use yew::prelude::*;
+use yew_router::prelude::use_navigator;
+
+#[function_component(ManageWallets)]
+pub fn manage_wallets() -> Html {
+    let navigator = use_navigator().unwrap();
+    let storage = Rc::new(RefCell::new(UserStorage::read(LocalStorage::default())));
+    let force_update = use_force_update();
+    let renaming = use_state(|| None::<String>);
+    let new_name = use_state(String::default);
+    let deleting = use_state(|| None::<String>);
+    let allowing_any_sighash = use_state(|| None::<String>);
+    let error = use_state(String::default);
+    let renaming_value = (*renaming).clone();
+    let new_name_value = (*new_name).clone();
+    let deleting_value = (*deleting).clone();
+    let allowing_any_sighash_value = (*allowing_any_sighash).clone();
+    let error_value = (*error).clone();
+
+    // Applies a change to the storage, persists it and re-renders the list.
+    let update = {
+        let storage = storage.clone();
+        move |action: &dyn Fn(&mut UserStorage) -> Result<()>| {
+            let mut s = storage.borrow_mut();
+            match action(&mut s).and_then(|_| s.save()) {
+                Ok(_) => {
+                    error.set(String::default());
+                    force_update.force_update();
+                }
+                Err(e) => error.set(e.to_string()),
+            }
+        }
+    };
+    let update = Rc::new(update);
+
+    let onchange_name = {
+        let new_name = new_name.clone();
+        Callback::from(move |value: Result<String>| {
+            let _ = value.map(|v| new_name.set(v));
+        })
+    };
+
+    let onclick_rename = {
+        let update = update.clone();
+        let renaming = renaming.clone();
+        let renaming_value = renaming_value.clone();
+        Callback::from(move |_: MouseEvent| {
+            if let Some(wallet_name) = &renaming_value {
+                update(&|s| s.rename_wallet(wallet_name, &new_name_value));
+                renaming.set(None);
+            }
+        })
+    };
+
+    let onclick_cancel_rename = {
+        let renaming = renaming.clone();
+        Callback::from(move |_: MouseEvent| renaming.set(None))
+    };
+
+    let ondelete = {
+        let update = update.clone();
+        let deleting = deleting.clone();
+        let deleting_value = deleting_value.clone();
+        Callback::from(move |password: String| {
+            if let Some(wallet_name) = &deleting_value {
+                update(&|s| s.delete_wallet(wallet_name, &password).map(|_| ()));
+            }
+            deleting.set(None);
+        })
+    };
+
+    let oncancel_delete = {
+        let deleting = deleting.clone();
+        Callback::from(move |_| deleting.set(None))
+    };
+
+    let onallow_any_sighash = {
+        let update = update.clone();
+        let allowing_any_sighash = allowing_any_sighash.clone();
+        let allowing_any_sighash_value = allowing_any_sighash_value.clone();
+        Callback::from(move |password: String| {
+            if let Some(wallet_name) = &allowing_any_sighash_value {
+                update(&|s| s.set_allow_any_sighash(wallet_name, true, Some(&password)));
+            }
+            allowing_any_sighash.set(None);
+        })
+    };
+
+    let oncancel_allow_any_sighash = {
+        let allowing_any_sighash = allowing_any_sighash.clone();
+        Callback::from(move |_| allowing_any_sighash.set(None))
+    };
+
+    let onclick_goback = {
+        let storage = storage.clone();
+        Callback::from(move |_: MouseEvent| {
+            if storage.borrow().wallets.is_empty() {
+                navigator.push(&ImportWalletRoute::ImportWalletHome);
+            } else {
+                navigator.push(&Route::Home);
+            }
+        })
+    };
+
+    let default_wallet = storage.borrow().get_default_wallet();
+    let wallets: Vec<(String, bool)> = storage
+        .borrow()
+        .wallets
+        .iter()
+        .map(|w| (w.name.clone(), w.allow_any_sighash))
+        .collect();
+    let last_index = wallets.len().saturating_sub(1);
+
+    let rows = wallets
+        .iter()
+        .enumerate()
+        .map(|(index, (wallet_name, allow_any_sighash))| {
+            let allow_any_sighash = *allow_any_sighash;
+            let is_default = *wallet_name == default_wallet;
+
+            let onclick_up = {
+                let update = update.clone();
+                let wallet_name = wallet_name.clone();
+                Callback::from(move |_: MouseEvent| {
+                    update(&|s| s.move_wallet(&wallet_name, index.saturating_sub(1)))
+                })
+            };
+
+            let onclick_down = {
+                let update = update.clone();
+                let wallet_name = wallet_name.clone();
+                Callback::from(move |_: MouseEvent| {
+                    update(&|s| s.move_wallet(&wallet_name, index + 1))
+                })
+            };
+
+            let onclick_default = {
+                let update = update.clone();
+                let wallet_name = wallet_name.clone();
+                Callback::from(move |_: MouseEvent| {
+                    update(&|s| s.set_default_wallet(&wallet_name))
+                })
+            };
+
+            // only allowing SIGHASH_ALL again doesn't need the password
+            let onclick_sighash = {
+                let update = update.clone();
+                let allowing_any_sighash = allowing_any_sighash.clone();
+                let wallet_name = wallet_name.clone();
+                Callback::from(move |_: MouseEvent| {
+                    if allow_any_sighash {
+                        update(&|s| s.set_allow_any_sighash(&wallet_name, false, None));
+                    } else {
+                        allowing_any_sighash.set(Some(wallet_name.clone()));
+                    }
+                })
+            };
+
+            let onclick_start_rename = {
+                let renaming = renaming.clone();
+                let new_name = new_name.clone();
+                let wallet_name = wallet_name.clone();
+                Callback::from(move |_: MouseEvent| {
+                    new_name.set(wallet_name.clone());
+                    renaming.set(Some(wallet_name.clone()));
+                })
+            };
+
+            let onclick_delete = {
+                let deleting = deleting.clone();
+                let wallet_name = wallet_name.clone();
+                Callback::from(move |_: MouseEvent| deleting.set(Some(wallet_name.clone())))
+            };
+
+            let label = if is_default {
+                format!("{wallet_name} (default)")
+            } else {
+                wallet_name.clone()
+            };
+
+            html! {
+                <div class="display-field" key={wallet_name.clone()}>
+                    <strong>{label}</strong>
+                    <div class="button-bar">
+                        <button disabled={index == 0} onclick={onclick_up}>{"Up"}</button>
+                        <button disabled={index == last_index} onclick={onclick_down}>{"Down"}</button>
+                        <button disabled={is_default} onclick={onclick_default}>{"Set default"}</button>
+                        <button onclick={onclick_sighash}>{if allow_any_sighash { "Only SIGHASH_ALL" } else { "Allow any sighash" }}</button>
+                        <button onclick={onclick_start_rename}>{"Rename"}</button>
+                        <button class="cancel" onclick={onclick_delete}>{"Delete"}</button>
+                    </div>
+                </div>
+            }
+        })
+        .collect::<Html>();
+
+    let rename_section = renaming_value.map_or_else(
+        || html! {},
+        |wallet_name| {
+            html! {
+                <>
+                    <label>{format!("New name for {wallet_name}:")}</label>
+                    <TextInput value={(*new_name).clone()} onchange={onchange_name} placeholder="Input the wallet's new name"/>
+                    <div class="button-bar">
+                        <button class="cancel" onclick={onclick_cancel_rename}>{"Cancel"}</button>
+                        <button onclick={onclick_rename}>{"Rename"}</button>
+                    </div>
+                </>
+            }
+        },
+    );
+
+    html! {
+        <>
+            <h class="title">{"Manage wallets"}</h>
+            {rows}
+            {rename_section}
+            <div class="error">{error_value}</div>
+            <button class="cancel" onclick={onclick_goback}>{"Go back"}</button>
+            <InputPasswordModal
+                password_for={PasswordFor::DeletingWallet}
+                visible={deleting_value.is_some()}
+                onsave={ondelete}
+                oncancel={oncancel_delete}
+            />
+            <InputPasswordModal
+                password_for={PasswordFor::AllowingAnySighash}
+                visible={allowing_any_sighash_value.is_some()}
+                onsave={onallow_any_sighash}
+                oncancel={oncancel_allow_any_sighash}
+            />
+        </>
+    }
+}
@@ -1,12 +1,22 @@
+pub mod add_account;
 pub mod approve_pasted_psbt;
 pub mod approve_psbt;
+pub mod bsms;
 pub mod create_account;
+pub mod derive_bip85;
 pub mod export_xpub;
 pub mod generate_seed;
+pub mod generate_slip39;
 pub mod home;
 pub mod import_from_seed;
+pub mod import_from_slip39;
 pub mod import_from_xprv;
 pub mod import_wallet;
 pub mod input_password_modal;
+pub mod manage_wallets;
 pub mod password_injector;
 pub mod settings;
+pub mod sign_batch;
+pub mod sign_message;
+pub mod verify_backup;
+pub mod verify_message;
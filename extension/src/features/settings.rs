@@ -14,8 +14,10 @@ pub fn settings() -> Html {
     let storage = RefCell::new(SettingsStorage::read(LocalStorage::default()));
     let navigator = use_navigator().unwrap();
     let network = use_state(|| storage.borrow().get_network());
+    let allow_unverified_inputs = use_state(|| storage.borrow().get_allow_unverified_inputs());
     let error = use_state(String::new);
     let network_value = *network;
+    let allow_unverified_inputs_value = *allow_unverified_inputs;
     let error_value = (*error).clone();
 
     let on_network_change = Callback::from(move |input_event: Event| {
@@ -23,10 +25,14 @@ pub fn settings() -> Html {
         network.set(Network::from_str(&value).unwrap());
     });
 
+    let on_change_allow_unverified_inputs =
+        Callback::from(move |_: Event| allow_unverified_inputs.set(!*allow_unverified_inputs));
+
     let onclick_save = {
         Callback::from(move |_| {
             let mut s = storage.borrow_mut();
             s.set_network(&network_value.to_string());
+            s.set_allow_unverified_inputs(allow_unverified_inputs_value);
             let stored = s.save();
 
             if stored.is_err() {
@@ -46,6 +52,10 @@ pub fn settings() -> Html {
                 <RadioButton id="signet" name="signet" value={Network::Signet.to_string()} checked={network_value == Network::Signet} label="Signet" />
                 <RadioButton id="testnet" name="testnet" value={Network::Testnet.to_string()} checked={network_value == Network::Testnet} label="Testnet" />
             </fieldset>
+            <div class="checkbox-container">
+                <input id="allow-unverified-inputs-checkbox" type="checkbox" checked={allow_unverified_inputs_value} onchange={on_change_allow_unverified_inputs} />
+                <label>{"Sign multi-input PSBTs without previous transactions (the fee can't be verified)"}</label>
+            </div>
             <div class="error">{error_value}</div>
             <button onclick={onclick_save}>{"Save"}</button>
         </>
@@ -0,0 +1,192 @@
+use crate::{
+    components::{
+        select::{Select, SelectItem},
+        textarea::TextArea,
+    },
+    context::UserContext,
+    utils::{
+        helpers::{get_clipboard, read_file},
+        storage::LocalStorage,
+    },
+};
+use anyhow::{anyhow, Result};
+use signer::{
+    psbt_decoder::decode_psbt,
+    signer::{decode_psbts_and_sign, invalid_partial_sigs},
+    storage::{SettingsStorage, UserStorage},
+};
+use std::{cell::RefCell, rc::Rc};
+use wasm_bindgen_futures::spawn_local;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+use yew_router::prelude::use_navigator;
+
+#[function_component(SignBatch)]
+pub fn sign_batch() -> Html {
+    let password = use_context::<UserContext>()
+        .unwrap()
+        .password
+        .clone()
+        .unwrap_or_default();
+    let navigator = use_navigator().unwrap();
+    let storage = Rc::new(RefCell::new(UserStorage::read(LocalStorage::default())));
+    let default_wallet = storage.borrow().get_default_wallet();
+    let selected_wallet = use_state(|| default_wallet);
+    let error = use_state(String::default);
+    let selected_wallet_value = (*selected_wallet).clone();
+    let error_value = (*error).clone();
+    let disabled = password.is_empty();
+    let psbts = use_state(String::default);
+    let psbts_value = (*psbts).clone();
+    let results = use_state(Vec::<Result<String, String>>::new);
+    let results_value = (*results).clone();
+
+    let onchange_psbts = {
+        let psbts = psbts.clone();
+        Callback::from(move |value: Result<String>| {
+            let _ = value.map(|v| psbts.set(v));
+        })
+    };
+
+    // each file is normalized to a base64 line of the batch
+    let onchange_files = {
+        let psbts_value = psbts_value.clone();
+        let error = error.clone();
+        Callback::from(move |event: Event| {
+            let Some(files) = event
+                .target_dyn_into::<HtmlInputElement>()
+                .and_then(|input| input.files())
+            else {
+                return;
+            };
+            let files: Vec<_> = (0..files.length()).filter_map(|i| files.get(i)).collect();
+            let psbts = psbts.clone();
+            let psbts_value = psbts_value.clone();
+            let error = error.clone();
+            spawn_local(async move {
+                let mut lines: Vec<String> = psbts_value.lines().map(str::to_string).collect();
+                for file in files {
+                    let name = file.name();
+                    match read_file(file).await.and_then(|bytes| decode_psbt(&bytes)) {
+                        Ok(p) => lines.push(p.to_string()),
+                        Err(e) => return error.set(format!("Error while reading {name} {e}")),
+                    }
+                }
+                error.set(String::default());
+                psbts.set(lines.join("\n"));
+            });
+        })
+    };
+
+    let onclick_sign = {
+        let selected_wallet_value = selected_wallet_value.clone();
+        let psbts_value = psbts_value.clone();
+        let storage = storage.clone();
+        Callback::from(move |_: MouseEvent| {
+            if password.is_empty() {
+                return;
+            }
+            let settings_storage = SettingsStorage::read(LocalStorage::default());
+            let batch: Vec<&str> = psbts_value
+                .lines()
+                .map(str::trim)
+                .filter(|l| !l.is_empty())
+                .collect();
+
+            let signed = storage
+                .borrow_mut()
+                .get_wallet_mut(&selected_wallet_value)
+                .ok_or_else(|| anyhow!("Wallet not found"))
+                .and_then(|wallet| {
+                    decode_psbts_and_sign(
+                        &batch,
+                        wallet,
+                        &password,
+                        settings_storage.get_network(),
+                        settings_storage.get_allow_unverified_inputs(),
+                    )
+                });
+
+            match signed {
+                Ok(signed) => {
+                    error.set(String::default());
+                    results.set(
+                        signed
+                            .into_iter()
+                            .map(|r| r.map_err(|e| e.to_string()))
+                            .collect(),
+                    );
+                }
+                Err(e) => error.set(format!("Error while signing PSBTs {e}")),
+            }
+        })
+    };
+
+    let onclick_copy = {
+        let signed: Vec<String> = results_value
+            .iter()
+            .filter_map(|r| r.as_ref().ok().cloned())
+            .collect();
+        Callback::from(move |_: MouseEvent| {
+            let _ = get_clipboard().map(|c| c.write_text(&signed.join("\n")));
+        })
+    };
+
+    let onchange = {
+        Callback::from(move |value: SelectItem| {
+            selected_wallet.set(value.label);
+        })
+    };
+
+    let onclick_goback = { Callback::from(move |_: MouseEvent| navigator.back()) };
+
+    let items: Vec<SelectItem> = storage
+        .borrow()
+        .wallets
+        .iter()
+        .map(|w| SelectItem::new(&w.name, &w.name))
+        .collect();
+    let signed_count = results_value.iter().filter(|r| r.is_ok()).count();
+    let results_section = results_value
+        .iter()
+        .enumerate()
+        .map(|(index, result)| match result {
+            Ok(signed) => {
+                let invalid = decode_psbt(signed.as_bytes())
+                    .map(|psbt| invalid_partial_sigs(&psbt))
+                    .unwrap_or_default();
+                html! {
+                    <>
+                        <div class="display-field">
+                            <strong>{format!("PSBT {}:", index + 1)}</strong>
+                            <span>{"Signed"}</span>
+                        </div>
+                        { for invalid.iter().map(|w| html! { <div class="error">{w}</div> }) }
+                    </>
+                }
+            }
+            Err(e) => html! {
+                <div class="display-field error">
+                    <strong>{format!("PSBT {}:", index + 1)}</strong>
+                    <span>{e}</span>
+                </div>
+            },
+        })
+        .collect::<Html>();
+
+    html! {
+        <>
+            <h class="title">{"Sign a batch of PSBTs"}</h>
+            <Select {onchange} items={items} default={selected_wallet_value}/>
+            <TextArea value={psbts_value.clone()} onchange={onchange_psbts} placeholder="Paste your PSBTs here, one per line"/>
+            <input type="file" multiple={true} onchange={onchange_files} />
+            {results_section}
+            <div class="error">{error_value}</div>
+            <button disabled={signed_count == 0} onclick={onclick_copy}>{format!("Copy {signed_count} signed PSBTs")}</button>
+            <div class="button-bar">
+                <button class="cancel" onclick={onclick_goback}>{"Go back"}</button>
+                <button disabled={disabled || psbts_value.trim().is_empty()} onclick={onclick_sign}>{"Sign all"}</button>
+            </div>
+        </>
+    }
+}
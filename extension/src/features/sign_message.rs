@@ -0,0 +1,163 @@
+use std::cell::RefCell;
+
+use crate::{
+    components::{
+        select::{Select, SelectItem},
+        text_input::TextInput,
+        textarea::TextArea,
+    },
+    context::UserContext,
+    utils::{
+        helpers::{decode_url_string, get_clipboard},
+        storage::LocalStorage,
+    },
+};
+use anyhow::{anyhow, Result};
+use signer::{
+    message::{AddressType, SignatureFormat},
+    storage::{SettingsStorage, UserStorage},
+};
+use web_sys::MouseEvent;
+use yew::prelude::*;
+use yew_router::prelude::use_navigator;
+
+const FROM_DERIVATION: &str = "From derivation";
+
+#[derive(Properties, PartialEq, Eq)]
+pub struct Props {
+    pub wallet_name: String,
+}
+
+#[function_component(SignMessage)]
+pub fn sign_message(props: &Props) -> Html {
+    let decoded_wallet_name = decode_url_string(&props.wallet_name).unwrap();
+    let password = use_context::<UserContext>()
+        .unwrap()
+        .password
+        .clone()
+        .unwrap_or_default();
+    let navigator = use_navigator().unwrap();
+    let storage = RefCell::new(UserStorage::read(LocalStorage::default()));
+    let wallet_derivation = storage
+        .borrow()
+        .get_wallet_ref(&decoded_wallet_name)
+        .map(|w| w.derivation.to_string())
+        .unwrap_or_default();
+    let message = use_state(String::default);
+    let derivation = use_state(|| format!("{wallet_derivation}/0/0"));
+    let address_type = use_state(|| None::<AddressType>);
+    let format = use_state(|| SignatureFormat::Bip322Simple);
+    let address = use_state(String::default);
+    let signature = use_state(String::default);
+    let error = use_state(String::default);
+    let message_value = (*message).clone();
+    let derivation_value = (*derivation).clone();
+    let address_value = (*address).clone();
+    let signature_value = (*signature).clone();
+    let error_value = (*error).clone();
+
+    let onchange_message = Callback::from(move |value: Result<String>| {
+        let _ = value.map(|v| message.set(v));
+    });
+
+    let onchange_derivation = Callback::from(move |value: Result<String>| {
+        let _ = value.map(|v| derivation.set(v));
+    });
+
+    let onchange_address_type = {
+        let address_type = address_type.clone();
+        Callback::from(move |item: SelectItem| {
+            address_type.set(
+                AddressType::ALL
+                    .into_iter()
+                    .find(|t| t.to_string() == item.value),
+            );
+        })
+    };
+
+    let onchange_format = {
+        let format = format.clone();
+        Callback::from(move |item: SelectItem| {
+            if let Some(f) = SignatureFormat::ALL
+                .into_iter()
+                .find(|f| f.to_string() == item.value)
+            {
+                format.set(f);
+            }
+        })
+    };
+
+    let onclick_sign = {
+        let message_value = message_value.clone();
+        let derivation_value = derivation_value.clone();
+        Callback::from(move |_: MouseEvent| {
+            let settings = SettingsStorage::read(LocalStorage::default());
+            let signed = storage
+                .borrow_mut()
+                .get_wallet_mut(&decoded_wallet_name)
+                .ok_or_else(|| anyhow!("Wallet not found"))
+                .and_then(|w| {
+                    w.sign_message(
+                        &message_value,
+                        &derivation_value,
+                        *address_type,
+                        *format,
+                        &password,
+                        settings.get_network(),
+                    )
+                });
+
+            match signed {
+                Ok(signed) => {
+                    error.set(String::default());
+                    address.set(signed.address.to_string());
+                    signature.set(signed.signature);
+                }
+                Err(e) => {
+                    address.set(String::default());
+                    signature.set(String::default());
+                    error.set(format!("Error while signing message {e}"));
+                }
+            }
+        })
+    };
+
+    let onclick_copy = {
+        let signature_value = signature_value.clone();
+        Callback::from(move |_: MouseEvent| {
+            let _ = get_clipboard().map(|c| c.write_text(&signature_value));
+        })
+    };
+
+    let onclick_goback = Callback::from(move |_: MouseEvent| navigator.back());
+
+    let address_type_items: Vec<SelectItem> = std::iter::once(FROM_DERIVATION.to_string())
+        .chain(AddressType::ALL.iter().map(ToString::to_string))
+        .map(|label| SelectItem::new(&label, &label))
+        .collect();
+    let format_items: Vec<SelectItem> = SignatureFormat::ALL
+        .iter()
+        .map(|f| SelectItem::new(&f.to_string(), &f.to_string()))
+        .collect();
+
+    html! {
+        <>
+            <h class="title">{"Sign a message"}</h>
+            <TextArea value={message_value} onchange={onchange_message} placeholder="Input the message to sign"/>
+            <label>{"Derivation:"}</label>
+            <TextInput value={derivation_value} onchange={onchange_derivation} placeholder="Input the address derivation"/>
+            <label>{"Address type:"}</label>
+            <Select onchange={onchange_address_type} items={address_type_items} default={FROM_DERIVATION.to_string()}/>
+            <label>{"Signature format:"}</label>
+            <Select onchange={onchange_format} items={format_items} default={SignatureFormat::Bip322Simple.to_string()}/>
+            <button onclick={onclick_sign}>{"Sign"}</button>
+            <label>{"Address:"}</label>
+            <input disabled={true} value={address_value}/>
+            <label>{"Signature:"}</label>
+            <textarea disabled={true} value={signature_value.clone()}/>
+            <button disabled={signature_value.is_empty()} onclick={onclick_copy}>{"Copy signature"}</button>
+            <div class="error">{error_value}</div>
+            <button class="cancel" onclick={onclick_goback}>{"Go back"}</button>
+        </>
+    }
+}
@@ -0,0 +1,83 @@
+use std::cell::RefCell;
+
+use crate::{
+    components::textarea::TextArea,
+    context::UserContext,
+    utils::{helpers::decode_url_string, storage::LocalStorage},
+};
+use anyhow::{anyhow, Result};
+use signer::storage::{SettingsStorage, UserStorage};
+use web_sys::MouseEvent;
+use yew::prelude::*;
+use yew_router::prelude::use_navigator;
+
+#[derive(Properties, PartialEq, Eq)]
+pub struct Props {
+    pub wallet_name: String,
+}
+
+#[function_component(VerifyBackup)]
+pub fn verify_backup(props: &Props) -> Html {
+    let decoded_wallet_name = decode_url_string(&props.wallet_name).unwrap();
+    let password = use_context::<UserContext>()
+        .unwrap()
+        .password
+        .clone()
+        .unwrap_or_default();
+    let navigator = use_navigator().unwrap();
+    let storage = RefCell::new(UserStorage::read(LocalStorage::default()));
+    let seed = use_state(String::default);
+    let result = use_state(String::default);
+    let error = use_state(String::default);
+    let seed_value = (*seed).clone();
+    let result_value = (*result).clone();
+    let error_value = (*error).clone();
+
+    let onchange_seed = {
+        let result = result.clone();
+        Callback::from(move |value: Result<String>| {
+            let _ = value.map(|v| seed.set(v));
+            result.set(String::default());
+        })
+    };
+
+    let onclick_verify = {
+        let seed_value = seed_value.clone();
+        Callback::from(move |_: MouseEvent| {
+            let settings = SettingsStorage::read(LocalStorage::default());
+            let verified = storage
+                .borrow_mut()
+                .get_wallet_mut(&decoded_wallet_name)
+                .ok_or_else(|| anyhow!("Wallet not found"))
+                .and_then(|w| w.verify_backup(&seed_value, &password, settings.get_network()));
+
+            match verified {
+                Ok(true) => {
+                    error.set(String::default());
+                    result.set("Your backup matches this wallet".to_string());
+                }
+                Ok(false) => {
+                    result.set(String::default());
+                    error.set("Your backup does NOT match this wallet".to_string());
+                }
+                Err(e) => {
+                    result.set(String::default());
+                    error.set(format!("Error while verifying backup {e}"));
+                }
+            }
+        })
+    };
+
+    let onclick_goback = Callback::from(move |_: MouseEvent| navigator.back());
+
+    html! {
+        <>
+            <h class="title">{"Verify my backup"}</h>
+            <TextArea value={seed_value.clone()} onchange={onchange_seed} placeholder="Input the seed you wrote down"/>
+            <button disabled={seed_value.is_empty()} onclick={onclick_verify}>{"Verify"}</button>
+            <div>{result_value}</div>
+            <div class="error">{error_value}</div>
+            <button class="cancel" onclick={onclick_goback}>{"Go back"}</button>
+        </>
+    }
+}
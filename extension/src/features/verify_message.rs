@@ -0,0 +1,70 @@
+use crate::components::{text_input::TextInput, textarea::TextArea};
+use anyhow::Result;
+use signer::message::verify_message;
+use web_sys::MouseEvent;
+use yew::prelude::*;
+use yew_router::prelude::use_navigator;
+
+#[function_component(VerifyMessage)]
+pub fn verify_message_view() -> Html {
+    let navigator = use_navigator().unwrap();
+    let address = use_state(String::default);
+    let message = use_state(String::default);
+    let signature = use_state(String::default);
+    let result = use_state(String::default);
+    let error = use_state(String::default);
+    let address_value = (*address).clone();
+    let message_value = (*message).clone();
+    let signature_value = (*signature).clone();
+    let result_value = (*result).clone();
+    let error_value = (*error).clone();
+
+    let onchange_address = Callback::from(move |value: Result<String>| {
+        let _ = value.map(|v| address.set(v));
+    });
+
+    let onchange_message = Callback::from(move |value: Result<String>| {
+        let _ = value.map(|v| message.set(v));
+    });
+
+    let onchange_signature = Callback::from(move |value: Result<String>| {
+        let _ = value.map(|v| signature.set(v));
+    });
+
+    let onclick_verify = {
+        let address_value = address_value.clone();
+        let message_value = message_value.clone();
+        let signature_value = signature_value.clone();
+        Callback::from(move |_: MouseEvent| {
+            match verify_message(&address_value, &message_value, &signature_value) {
+                Ok(true) => {
+                    error.set(String::default());
+                    result.set("The signature is valid for this address".to_string());
+                }
+                Ok(false) => {
+                    result.set(String::default());
+                    error.set("The signature is NOT valid for this address".to_string());
+                }
+                Err(e) => {
+                    result.set(String::default());
+                    error.set(format!("Error while verifying message {e}"));
+                }
+            }
+        })
+    };
+
+    let onclick_goback = Callback::from(move |_: MouseEvent| navigator.back());
+
+    html! {
+        <>
+            <h class="title">{"Verify a message"}</h>
+            <TextInput value={address_value} onchange={onchange_address} placeholder="Input the address"/>
+            <TextArea value={message_value} onchange={onchange_message} placeholder="Input the signed message"/>
+            <TextInput value={signature_value.clone()} onchange={onchange_signature} placeholder="Input the signature"/>
+            <button disabled={signature_value.is_empty()} onclick={onclick_verify}>{"Verify"}</button>
+            <div>{result_value}</div>
+            <div class="error">{error_value}</div>
+            <button class="cancel" onclick={onclick_goback}>{"Go back"}</button>
+        </>
+    }
+}
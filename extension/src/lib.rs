@@ -31,6 +31,7 @@ pub struct OperationRequestData {
     psbt: Option<String>,
     request_type: Option<String>,
     amount: Option<String>,
+    node_pubkey: Option<String>,
 }
 
 #[wasm_bindgen]
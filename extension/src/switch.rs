@@ -1,8 +1,11 @@
 use crate::features::{
-    approve_pasted_psbt::ApprovePastedPSBT, approve_psbt::ApprovePSBT,
-    create_account::CreateAccount, export_xpub::ExportXPUB, generate_seed::GenerateSeed,
-    home::Home, import_from_seed::ImportFromSeed, import_from_xprv::ImportFromXprv,
-    import_wallet::ImportWallet, settings::Settings,
+    add_account::AddAccount, approve_pasted_psbt::ApprovePastedPSBT, approve_psbt::ApprovePSBT,
+    bsms::Bsms, create_account::CreateAccount, derive_bip85::DeriveBip85, export_xpub::ExportXPUB,
+    generate_seed::GenerateSeed, generate_slip39::GenerateSlip39, home::Home,
+    import_from_seed::ImportFromSeed, import_from_slip39::ImportFromSlip39,
+    import_from_xprv::ImportFromXprv, import_wallet::ImportWallet, manage_wallets::ManageWallets,
+    settings::Settings, sign_batch::SignBatch, sign_message::SignMessage,
+    verify_backup::VerifyBackup, verify_message::VerifyMessage,
 };
 use yew::{function_component, html, Html};
 use yew_router::{prelude::use_navigator, Routable, Switch};
@@ -21,8 +24,24 @@ pub enum Route {
     ApprovePSBT,
     #[at("/approvepasted")]
     ApprovePastedPSBT,
+    #[at("/signbatch")]
+    SignBatch,
     #[at("/exportxpub/:wallet_name")]
     ExportXPUB { wallet_name: String },
+    #[at("/bip85/:wallet_name")]
+    DeriveBip85 { wallet_name: String },
+    #[at("/addaccount/:wallet_name")]
+    AddAccount { wallet_name: String },
+    #[at("/verifybackup/:wallet_name")]
+    VerifyBackup { wallet_name: String },
+    #[at("/bsms/:wallet_name")]
+    Bsms { wallet_name: String },
+    #[at("/signmessage/:wallet_name")]
+    SignMessage { wallet_name: String },
+    #[at("/verifymessage")]
+    VerifyMessage,
+    #[at("/managewallets")]
+    ManageWallets,
     #[at("/settings")]
     Settings,
     #[not_found]
@@ -40,6 +59,10 @@ pub enum ImportWalletRoute {
     GenerateSeed,
     #[at("/import/xprv")]
     ImportXPRV,
+    #[at("/import/slip39")]
+    ImportSlip39,
+    #[at("/import/generatedslip39")]
+    GenerateSlip39,
     #[not_found]
     #[at("/import/404")]
     NotFound,
@@ -62,7 +85,15 @@ pub fn switch(routes: Route) -> Html {
         }
         Route::ApprovePSBT => html! { <ApprovePSBT/> },
         Route::ApprovePastedPSBT => html! { <ApprovePastedPSBT/> },
+        Route::SignBatch => html! { <SignBatch/> },
         Route::ExportXPUB { wallet_name } => html! { <ExportXPUB wallet_name={wallet_name}/> },
+        Route::DeriveBip85 { wallet_name } => html! { <DeriveBip85 wallet_name={wallet_name}/> },
+        Route::AddAccount { wallet_name } => html! { <AddAccount wallet_name={wallet_name}/> },
+        Route::VerifyBackup { wallet_name } => html! { <VerifyBackup wallet_name={wallet_name}/> },
+        Route::Bsms { wallet_name } => html! { <Bsms wallet_name={wallet_name}/> },
+        Route::SignMessage { wallet_name } => html! { <SignMessage wallet_name={wallet_name}/> },
+        Route::VerifyMessage => html! { <VerifyMessage /> },
+        Route::ManageWallets => html! { <ManageWallets /> },
         Route::Settings => html! { <Settings /> },
         Route::NotFound => html! { <Redirect /> },
     };
@@ -78,6 +109,8 @@ fn switch_import_wallets(route: ImportWalletRoute) -> Html {
         ImportWalletRoute::ImportSeed => html! { <ImportFromSeed /> },
         ImportWalletRoute::GenerateSeed => html! { <GenerateSeed /> },
         ImportWalletRoute::ImportXPRV => html! { <ImportFromXprv /> },
+        ImportWalletRoute::ImportSlip39 => html! { <ImportFromSlip39 /> },
+        ImportWalletRoute::GenerateSlip39 => html! { <GenerateSlip39 /> },
         ImportWalletRoute::NotFound => html! { <Redirect /> },
     };
 
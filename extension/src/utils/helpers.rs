@@ -1,7 +1,8 @@
 use anyhow::{anyhow, Context, Result};
-use js_sys::decode_uri_component;
+use js_sys::{decode_uri_component, Uint8Array};
 use wasm_bindgen::JsCast;
-use web_sys::{window, Clipboard, HtmlElement};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{window, Clipboard, File, HtmlElement};
 
 pub fn focus(element_id: &str) -> Result<()> {
     let window = window().context("Window not found")?;
@@ -30,3 +31,11 @@ pub fn decode_url_string(string: &str) -> Result<String> {
         .map_err(|_| anyhow!("Error while decoding url string"))
         .map(|s| s.into())
 }
+
+#[allow(clippy::future_not_send)]
+pub async fn read_file(file: File) -> Result<Vec<u8>> {
+    let buffer = JsFuture::from(file.array_buffer())
+        .await
+        .map_err(|_| anyhow!("Error while reading file"))?;
+    Ok(Uint8Array::new(&buffer).to_vec())
+}
@@ -6,5 +6,7 @@ pub struct PSBTWithWallet {
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum PasswordFor {
     ImportingSecret,
+    DeletingWallet,
+    AllowingAnySighash,
     UnlockingApp,
 }
@@ -0,0 +1,88 @@
+//! 1000 input consolidation shared by the native benchmark and the WASM test,
+//! the size that used to freeze the extension popup.
+
+use bitcoin::blockdata::opcodes::all::OP_CHECKSIG;
+use bitcoin::blockdata::script::Builder;
+use bitcoin::hashes::Hash;
+use bitcoin::psbt::PartiallySignedTransaction;
+use bitcoin::secp256k1::Secp256k1;
+use bitcoin::util::bip32::{DerivationPath, ExtendedPrivKey, ExtendedPubKey};
+use bitcoin::{
+    OutPoint, PackedLockTime, PublicKey, Script, Sequence, Transaction, TxIn, TxOut, Txid,
+};
+use signer::signer::sign_psbt_with_xprv;
+use signer::wallet::Wallet;
+use signer::Network;
+use std::str::FromStr;
+
+pub const INPUTS: u32 = 1000;
+/// Longest the consolidation may take to sign, in the WASM build of the
+/// extension too, without the popup feeling frozen.
+pub const BUDGET_MS: f64 = 5_000.0;
+const XPRV: &str = "tprv8ZgxMBicQKsPduvXYAnkop1b1UoAY2pS68pe9jHuJwuMvx6G5sh4C67peYZkRawdBWbMbfoybgQJ3g8nTZAezEeHyaW9A9UjtpTRmSyJwUn";
+const PASSWORD: &str = "Qwerty123";
+
+/// P2WSH inputs each locked to a key of the wallet, spent to a single output.
+fn consolidation(xprv: &ExtendedPrivKey) -> PartiallySignedTransaction {
+    let secp = Secp256k1::new();
+    let fingerprint = xprv.fingerprint(&secp);
+    let account = DerivationPath::from_str("m/84'/1'/0'/0").unwrap();
+    let account_xpub =
+        ExtendedPubKey::from_priv(&secp, &xprv.derive_priv(&secp, &account).unwrap());
+
+    let tx = Transaction {
+        version: 2,
+        lock_time: PackedLockTime::ZERO,
+        input: (0..INPUTS)
+            .map(|vout| TxIn {
+                previous_output: OutPoint::new(Txid::all_zeros(), vout),
+                script_sig: Script::new(),
+                sequence: Sequence::MAX,
+                witness: Default::default(),
+            })
+            .collect(),
+        output: vec![TxOut {
+            value: u64::from(INPUTS) * 1000,
+            script_pubkey: Script::new(),
+        }],
+    };
+    let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx).unwrap();
+
+    for (index, input) in psbt.inputs.iter_mut().enumerate() {
+        let path = account.child(bitcoin::util::bip32::ChildNumber::from(index as u32));
+        let pubkey = account_xpub
+            .ckd_pub(&secp, *path.as_ref().last().unwrap())
+            .unwrap()
+            .public_key;
+        let witness_script = Builder::new()
+            .push_key(&PublicKey::new(pubkey))
+            .push_opcode(OP_CHECKSIG)
+            .into_script();
+        input.witness_utxo = Some(TxOut {
+            value: 1100,
+            script_pubkey: witness_script.to_v0_p2wsh(),
+        });
+        input.witness_script = Some(witness_script);
+        input.bip32_derivation.insert(pubkey, (fingerprint, path));
+    }
+    psbt
+}
+
+/// Signs the consolidation with the wallet already decrypted, so the time
+/// `now` measures, in milliseconds, is the signing alone.
+pub fn time_signing(now: impl Fn() -> f64) -> f64 {
+    let xprv = ExtendedPrivKey::from_str(XPRV).unwrap();
+    let mut psbt = consolidation(&xprv);
+    let mut wallet = Wallet::default();
+    wallet
+        .from_xprv_str("bench", XPRV, "m", None, Network::Regtest, PASSWORD)
+        .unwrap();
+    let xprv = wallet.get_xprv(PASSWORD, Network::Regtest).unwrap();
+
+    let start = now();
+    // the inputs only have witness utxos, their amounts can't be verified
+    let signed_inputs = sign_psbt_with_xprv(&mut psbt, &wallet, xprv, true).unwrap();
+    let elapsed = now() - start;
+    assert_eq!(signed_inputs, INPUTS as usize);
+    elapsed
+}
@@ -0,0 +1,20 @@
+//! Time to sign a 1000 input consolidation, failing over [`common::BUDGET_MS`].
+//!
+//! `cargo bench -p signer --bench sign` runs it natively, `tests/wasm_sign.rs`
+//! runs it in the `wasm32-unknown-unknown` build the extension ships.
+
+mod common;
+
+use std::time::Instant;
+
+fn main() {
+    let start = Instant::now();
+    let elapsed = common::time_signing(|| start.elapsed().as_secs_f64() * 1000.0);
+    println!(
+        "Signed {} inputs in {elapsed:.0} ms, {:.3} ms per input (budget {} ms)",
+        common::INPUTS,
+        elapsed / f64::from(common::INPUTS),
+        common::BUDGET_MS
+    );
+    assert!(elapsed < common::BUDGET_MS, "Signing is over budget");
+}
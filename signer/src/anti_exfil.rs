@@ -0,0 +1,269 @@
+//! Anti-exfil (sign-to-contract) ECDSA signing, so a host can check the signer
+//! didn't choose its nonces to leak the private key.
+//!
+//! 1. The host picks random `host_data` and sends its [`host_commitment`].
+//! 2. The signer derives its nonce from the key, message and commitment and
+//!    returns the nonce point R0 from [`signer_commitment`].
+//! 3. The host reveals `host_data` and the signer [`sign`]s with the nonce
+//!    tweaked by `host_data`.
+//! 4. The host [`verify`]s the signature nonce is R0 tweaked by `host_data`,
+//!    which the signer couldn't have biased after committing to R0.
+//!
+//! Implements the `ecdsa_s2c` anti-exfil protocol of libsecp256k1-zkp, as used
+//! by hardware wallets, on top of the libsecp256k1 linked by `bitcoin`.
+
+use anyhow::{anyhow, bail, Result};
+use bitcoin::secp256k1::ecdsa::Signature;
+use bitcoin::secp256k1::ffi::{self, types::c_void};
+use bitcoin::secp256k1::{Message, PublicKey, Scalar, Secp256k1, SecretKey, Signing, Verification};
+use std::ptr;
+
+use crate::utils::hash::tagged_hash;
+
+const DATA_TAG: &[u8] = b"s2c/ecdsa/data";
+const POINT_TAG: &[u8] = b"s2c/ecdsa/point";
+
+/// Commitment the host sends before the signer commits to its nonce.
+pub fn host_commitment(host_data: &[u8; 32]) -> [u8; 32] {
+    tagged_hash(DATA_TAG, &[host_data])
+}
+
+/// RFC6979 nonce with the host commitment as extra data, as libsecp256k1
+/// derives it when signing.
+fn original_nonce(
+    secret_key: &SecretKey,
+    message: &Message,
+    host_commitment: &[u8; 32],
+) -> Result<SecretKey> {
+    // SAFETY: reading an immutable static of libsecp256k1
+    let nonce_function = unsafe { ffi::secp256k1_nonce_function_rfc6979 }
+        .ok_or_else(|| anyhow!("Missing RFC6979 nonce function"))?;
+    let key = secret_key.secret_bytes();
+    let mut nonce = [0u8; 32];
+    for attempt in 0.. {
+        // SAFETY: all the pointers are to 32 byte arrays that outlive the call,
+        // the nonce function only reads the commitment
+        let derived = unsafe {
+            nonce_function(
+                nonce.as_mut_ptr(),
+                message.as_ref().as_ptr(),
+                key.as_ptr(),
+                ptr::null(),
+                host_commitment.as_ptr() as *mut c_void,
+                attempt,
+            )
+        };
+        if derived != 1 {
+            bail!("Error while deriving the nonce");
+        }
+        if let Ok(nonce) = SecretKey::from_slice(&nonce) {
+            return Ok(nonce);
+        }
+    }
+    unreachable!("the nonce function gives up before running out of attempts")
+}
+
+fn commitment_tweak(original_nonce: &PublicKey, host_data: &[u8; 32]) -> Result<Scalar> {
+    let tweak = tagged_hash(POINT_TAG, &[&original_nonce.serialize(), host_data]);
+    Scalar::from_be_bytes(tweak).map_err(|_| anyhow!("Invalid nonce commitment"))
+}
+
+/// Nonce point the signer will tweak with the host data, R0.
+pub fn signer_commitment(
+    secret_key: &SecretKey,
+    message: &Message,
+    host_commitment: &[u8; 32],
+) -> Result<PublicKey> {
+    let nonce = original_nonce(secret_key, message, host_commitment)?;
+    Ok(nonce.public_key(&Secp256k1::signing_only()))
+}
+
+/// Nonce function handing libsecp256k1 the nonce passed as its data.
+unsafe extern "C" fn committed_nonce(
+    nonce32: *mut u8,
+    _msg32: *const u8,
+    _key32: *const u8,
+    _algo16: *const u8,
+    data: *mut c_void,
+    attempt: u32,
+) -> i32 {
+    // the nonce is a valid secret key, a retry only happens for a zero
+    // signature and must not reuse it
+    if attempt > 0 {
+        return 0;
+    }
+    ptr::copy_nonoverlapping(data as *const u8, nonce32, 32);
+    1
+}
+
+/// Signs with the committed nonce tweaked by the revealed `host_data`.
+pub fn sign<C: Signing + Verification>(
+    secp: &Secp256k1<C>,
+    secret_key: &SecretKey,
+    message: &Message,
+    host_data: &[u8; 32],
+) -> Result<Signature> {
+    let original = original_nonce(secret_key, message, &host_commitment(host_data))?;
+    let tweak = commitment_tweak(&original.public_key(secp), host_data)?;
+    let nonce = original.add_tweak(&tweak)?.secret_bytes();
+    let key = secret_key.secret_bytes();
+
+    // SAFETY: the signature is only used as an out pointer, the context can
+    // sign and the other pointers are to 32 byte arrays that outlive the call
+    let signature = unsafe {
+        let mut signature = ffi::Signature::new();
+        let signed = ffi::secp256k1_ecdsa_sign(
+            *secp.ctx(),
+            &mut signature,
+            message.as_ref().as_ptr(),
+            key.as_ptr(),
+            Some(committed_nonce),
+            nonce.as_ptr() as *const c_void,
+        );
+        if signed != 1 {
+            bail!("Error while signing with the committed nonce");
+        }
+        Signature::from(signature)
+    };
+    secp.verify_ecdsa(message, &signature, &secret_key.public_key(secp))?;
+    Ok(signature)
+}
+
+/// Host check that `signature` is valid and uses the nonce the signer
+/// committed to, tweaked by `host_data`.
+pub fn verify<C: Verification>(
+    secp: &Secp256k1<C>,
+    public_key: &PublicKey,
+    message: &Message,
+    signature: &Signature,
+    host_data: &[u8; 32],
+    signer_commitment: &PublicKey,
+) -> Result<()> {
+    secp.verify_ecdsa(message, signature, public_key)
+        .map_err(|_| anyhow!("Invalid signature"))?;
+    let nonce_point =
+        signer_commitment.add_exp_tweak(secp, &commitment_tweak(signer_commitment, host_data)?)?;
+    if signature.serialize_compact()[..32] != nonce_point.serialize()[1..] {
+        bail!("The signature doesn't use the committed nonce");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{host_commitment, sign, signer_commitment, verify, DATA_TAG, POINT_TAG};
+    use bitcoin::hashes::hex::{FromHex, ToHex};
+    use bitcoin::hashes::{sha256, Hash, HashEngine};
+    use bitcoin::secp256k1::{Message, PublicKey, Secp256k1, SecretKey};
+    use std::str::FromStr;
+
+    /// Fixed vectors of libsecp256k1-zkp's ecdsa_s2c tests: the data to commit
+    /// to, the nonce commitment when signing with it and the nonce commitment
+    /// when it's the host commitment of the anti-exfil protocol.
+    const VECTORS: [(&str, &str, &str); 2] = [
+        (
+            "1bf6fb42f41eb876c4d7aa0d67242b00baab99dc2084493e4e63277fa1f77f22",
+            "03f030def3188c0f56fcea87435b307643f45dafe22cbc82fd56034fae97417d3a",
+            "02df63755d1f3292bffed82986b106497c93b1f8bdc0454b6b0b0a4779c0ef7188",
+        ),
+        (
+            "35199a8fbf84ad6ef69a184c1b19285befbe06e60b6264e6d373893f6855e24a",
+            "03901717ce7c7484a2ce1b7dc7403b14e0354971393ec092a7f3e0c8e4e2d2639d",
+            "02c04ac7f771e8ebdbf315ff5e58b7fe9516102103500066172c4fac5b20f9e0ea",
+        ),
+    ];
+
+    #[test]
+    fn matches_upstream_tags() {
+        let midstate = |tag: &[u8]| {
+            let tag = sha256::Hash::hash(tag);
+            let mut engine = sha256::Hash::engine();
+            engine.input(&tag[..]);
+            engine.input(&tag[..]);
+            engine.midstate().into_inner().to_hex()
+        };
+        assert_eq!(
+            midstate(DATA_TAG),
+            "feefd67573166c99e2309cb86d45811301d3a51200e1811237ee0874421fc55f"
+        );
+        assert_eq!(
+            midstate(POINT_TAG),
+            "a9b21c7b358c3e3e0b6863d1c62b2035b44b40ce254a89120f85d0d48a5bf91c"
+        );
+    }
+
+    #[test]
+    fn matches_upstream_vectors() {
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[0x55; 32]).unwrap();
+        let public_key = secret_key.public_key(&secp);
+        let message = Message::from_slice(&[0x88; 32]).unwrap();
+
+        for (data, s2c_opening, anti_exfil_opening) in VECTORS {
+            let data = <[u8; 32]>::from_hex(data).unwrap();
+            assert_eq!(
+                signer_commitment(&secret_key, &message, &data).unwrap(),
+                PublicKey::from_str(anti_exfil_opening).unwrap()
+            );
+
+            let opening = signer_commitment(&secret_key, &message, &host_commitment(&data));
+            let opening = opening.unwrap();
+            assert_eq!(opening, PublicKey::from_str(s2c_opening).unwrap());
+            let signature = sign(&secp, &secret_key, &message, &data).unwrap();
+            assert!(verify(&secp, &public_key, &message, &signature, &data, &opening).is_ok());
+        }
+    }
+
+    #[test]
+    fn checks_committed_nonce() {
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[1; 32]).unwrap();
+        let public_key = secret_key.public_key(&secp);
+        let message = Message::from_slice(&[2; 32]).unwrap();
+        let host_data = [3; 32];
+
+        let commitment = host_commitment(&host_data);
+        let signer_commitment = signer_commitment(&secret_key, &message, &commitment).unwrap();
+        let signature = sign(&secp, &secret_key, &message, &host_data).unwrap();
+        assert!(verify(
+            &secp,
+            &public_key,
+            &message,
+            &signature,
+            &host_data,
+            &signer_commitment
+        )
+        .is_ok());
+
+        // a signer ignoring the host data or the host revealing other data
+        let other = sign(&secp, &secret_key, &message, &[4; 32]).unwrap();
+        assert!(verify(
+            &secp,
+            &public_key,
+            &message,
+            &other,
+            &host_data,
+            &signer_commitment
+        )
+        .is_err());
+        assert!(verify(
+            &secp,
+            &public_key,
+            &message,
+            &signature,
+            &[4; 32],
+            &signer_commitment
+        )
+        .is_err());
+        let plain = secp.sign_ecdsa(&message, &secret_key);
+        assert!(verify(
+            &secp,
+            &public_key,
+            &message,
+            &plain,
+            &host_data,
+            &signer_commitment
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,164 @@
+//! Deterministic entropy from a master key, as specified in BIP85.
+//! <https://github.com/bitcoin/bips/blob/master/bip-0085.mediawiki>
+
+use anyhow::{anyhow, bail, Result};
+use bdk::keys::bip39::Mnemonic;
+use bitcoin::hashes::hex::ToHex;
+use bitcoin::hashes::{sha512, Hash, HashEngine, Hmac, HmacEngine};
+use bitcoin::secp256k1::{Secp256k1, SecretKey};
+use bitcoin::util::bip32::{ChainCode, ChildNumber, DerivationPath, ExtendedPrivKey};
+use bitcoin::PrivateKey;
+use std::fmt;
+
+const BIP85_PURPOSE: u32 = 83696968;
+const BIP39_APPLICATION: u32 = 39;
+const WIF_APPLICATION: u32 = 2;
+const XPRV_APPLICATION: u32 = 32;
+const HEX_APPLICATION: u32 = 128169;
+const ENGLISH_LANGUAGE: u32 = 0;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Bip85Application {
+    Mnemonic { words: u32 },
+    Xprv,
+    Wif,
+    Hex { num_bytes: u32 },
+}
+
+impl fmt::Display for Bip85Application {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Bip85Application::Mnemonic { words } => write!(f, "BIP39 {words} words"),
+            Bip85Application::Xprv => write!(f, "XPRV"),
+            Bip85Application::Wif => write!(f, "WIF"),
+            Bip85Application::Hex { num_bytes } => write!(f, "HEX {num_bytes} bytes"),
+        }
+    }
+}
+
+impl Bip85Application {
+    pub fn derivation_path(&self, index: u32) -> Result<DerivationPath> {
+        let path = match self {
+            Bip85Application::Mnemonic { words } => {
+                if ![12, 18, 24].contains(words) {
+                    bail!("BIP85 mnemonics must have 12, 18 or 24 words");
+                }
+                vec![BIP39_APPLICATION, ENGLISH_LANGUAGE, *words, index]
+            }
+            Bip85Application::Xprv => vec![XPRV_APPLICATION, index],
+            Bip85Application::Wif => vec![WIF_APPLICATION, index],
+            Bip85Application::Hex { num_bytes } => {
+                if !(16..=64).contains(num_bytes) {
+                    bail!("BIP85 hex entropy must be between 16 and 64 bytes");
+                }
+                vec![HEX_APPLICATION, *num_bytes, index]
+            }
+        };
+
+        std::iter::once(BIP85_PURPOSE)
+            .chain(path)
+            .map(ChildNumber::from_hardened_idx)
+            .collect::<Result<Vec<ChildNumber>, _>>()
+            .map(DerivationPath::from)
+            .map_err(|e| anyhow!("{e}"))
+    }
+}
+
+fn derive_entropy(master: &ExtendedPrivKey, path: &DerivationPath) -> Result<[u8; 64]> {
+    if master.depth != 0 {
+        bail!("BIP85 requires a master key");
+    }
+
+    let secp = Secp256k1::new();
+    let derived = master.derive_priv(&secp, path)?;
+
+    let mut engine = HmacEngine::<sha512::Hash>::new(b"bip-entropy-from-k");
+    engine.input(&derived.private_key.secret_bytes());
+    Ok(Hmac::<sha512::Hash>::from_engine(engine).into_inner())
+}
+
+/// Derives the child secret for the given application and index, serialized
+/// as a mnemonic, xprv, WIF or hex string.
+pub fn derive(
+    master: &ExtendedPrivKey,
+    application: Bip85Application,
+    index: u32,
+) -> Result<String> {
+    let entropy = derive_entropy(master, &application.derivation_path(index)?)?;
+
+    match application {
+        Bip85Application::Mnemonic { words } => {
+            let length = (words as usize) * 4 / 3;
+            Mnemonic::from_entropy(&entropy[..length])
+                .map(|m| m.to_string())
+                .map_err(|e| anyhow!("{e}"))
+        }
+        Bip85Application::Xprv => {
+            let xprv = ExtendedPrivKey {
+                network: master.network,
+                depth: 0,
+                parent_fingerprint: Default::default(),
+                child_number: ChildNumber::from_normal_idx(0)?,
+                private_key: SecretKey::from_slice(&entropy[32..])?,
+                chain_code: ChainCode::from(&entropy[..32]),
+            };
+            Ok(xprv.to_string())
+        }
+        Bip85Application::Wif => {
+            let private_key = SecretKey::from_slice(&entropy[..32])?;
+            Ok(PrivateKey::new(private_key, master.network).to_wif())
+        }
+        Bip85Application::Hex { num_bytes } => Ok(entropy[..num_bytes as usize].to_hex()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{derive, derive_entropy, Bip85Application};
+    use bitcoin::hashes::hex::ToHex;
+    use bitcoin::util::bip32::{DerivationPath, ExtendedPrivKey};
+    use std::str::FromStr;
+
+    const MASTER: &str = "xprv9s21ZrQH143K2LBWUUQRFXhucrQqBpKdRRxNVq2zBqsx8HVqFk2uYo8kmbaLLHRdqtQpUm98uKfu3vca1LqdGhUtyoFnCNkfmXRyPXLjbKb";
+
+    #[test]
+    fn derives_entropy() {
+        let master = ExtendedPrivKey::from_str(MASTER).unwrap();
+        let path = DerivationPath::from_str("m/83696968'/0'/0'").unwrap();
+        assert_eq!(
+            derive_entropy(&master, &path).unwrap().to_hex(),
+            "efecfbccffea313214232d29e71563d941229afb4338c21f9517c41aaa0d16f00b83d2a09ef747e7a64e8e2bd5a14869e693da66ce94ac2da570ab7ee48618f7"
+        );
+    }
+
+    #[test]
+    fn derives_applications() {
+        let master = ExtendedPrivKey::from_str(MASTER).unwrap();
+        let cases = [
+            (
+                Bip85Application::Mnemonic { words: 12 },
+                "girl mad pet galaxy egg matter matrix prison refuse sense ordinary nose",
+            ),
+            (
+                Bip85Application::Mnemonic { words: 24 },
+                "puppy ocean match cereal symbol another shed magic wrap hammer bulb intact gadget divorce twin tonight reason outdoor destroy simple truth cigar social volcano",
+            ),
+            (
+                Bip85Application::Wif,
+                "Kzyv4uF39d4Jrw2W7UryTHwZr1zQVNk4dAFyqE6BuMrMh1Za7uhp",
+            ),
+            (
+                Bip85Application::Xprv,
+                "xprv9s21ZrQH143K2srSbCSg4m4kLvPMzcWydgmKEnMmoZUurYuBuYG46c6P71UGXMzmriLzCCBvKQWBUv3vPB3m1SATMhp3uEjXHJ42jFg7myX",
+            ),
+            (
+                Bip85Application::Hex { num_bytes: 64 },
+                "492db4698cf3b73a5a24998aa3e9d7fa96275d85724a91e71aa2d645442f878555d078fd1f1f67e368976f04137b1f7a0d19232136ca50c44614af72b5582a5c",
+            ),
+        ];
+
+        for (application, expected) in cases {
+            assert_eq!(derive(&master, application, 0).unwrap(), expected);
+        }
+    }
+}
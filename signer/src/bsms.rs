@@ -0,0 +1,300 @@
+//! Signer side of BIP129 Bitcoin Secure Multisig Setup, records are exchanged
+//! unencrypted so the token only authenticates the session.
+//! <https://github.com/bitcoin/bips/blob/master/bip-0129.mediawiki>
+
+use anyhow::{anyhow, bail, Context, Result};
+use bdk::descriptor::checksum::calc_checksum;
+use bitcoin::hashes::hex::FromHex;
+use bitcoin::secp256k1::Secp256k1;
+use bitcoin::util::bip32::{DerivationPath, ExtendedPrivKey, ExtendedPubKey, Fingerprint};
+use bitcoin::util::misc::{signed_msg_hash, MessageSignature};
+use bitcoin::Network;
+use miniscript::descriptor::{Descriptor, DescriptorPublicKey};
+use miniscript::ForEachKey;
+use std::str::FromStr;
+
+pub const BSMS_VERSION: &str = "BSMS 1.0";
+const MAX_DESCRIPTION_LENGTH: usize = 80;
+
+/// Tokens are `00` when not used or 64/128 bits in hex.
+fn check_token(token: &str) -> Result<()> {
+    match token.len() {
+        2 if token == "00" => Ok(()),
+        16 | 32 => {
+            Vec::<u8>::from_hex(token).context("The token must be hexadecimal")?;
+            Ok(())
+        }
+        _ => bail!("The token must be 00 or 16 or 32 hexadecimal characters"),
+    }
+}
+
+/// Builds the key record for `xprv`, the key at `derivation` from the master
+/// key with `master_fingerprint`, signed with the same key.
+pub fn key_record(
+    token: &str,
+    xprv: &ExtendedPrivKey,
+    master_fingerprint: Fingerprint,
+    derivation: &DerivationPath,
+    description: &str,
+) -> Result<String> {
+    let token = token.trim().to_lowercase();
+    check_token(&token)?;
+    let description = description.trim();
+    if description.len() > MAX_DESCRIPTION_LENGTH {
+        bail!("The description can't be longer than {MAX_DESCRIPTION_LENGTH} characters");
+    }
+
+    let secp = Secp256k1::new();
+    let xpub = ExtendedPubKey::from_priv(&secp, xprv);
+    let path = derivation.to_string();
+    let origin = path.strip_prefix('m').unwrap_or(&path);
+    let record =
+        format!("{BSMS_VERSION}\n{token}\n[{master_fingerprint}{origin}]{xpub}\n{description}");
+
+    let msg_hash = signed_msg_hash(&record);
+    let message = bitcoin::secp256k1::Message::from_slice(&msg_hash[..])?;
+    let signature = MessageSignature::new(
+        secp.sign_ecdsa_recoverable(&message, &xprv.private_key),
+        true,
+    );
+
+    Ok(format!(
+        "{record}\n{}",
+        crate::utils::base64::to_base64(&signature.serialize())
+    ))
+}
+
+/// Parses a descriptor, expanding the BIP129 `/**` and `/<0;1>/*` shorthands
+/// to the receive addresses `/0/*`. Their checksum covers the shorthand, so
+/// it is checked before expanding.
+pub(crate) fn parse_descriptor(descriptor: &str) -> Result<Descriptor<DescriptorPublicKey>> {
+    let descriptor = descriptor.trim();
+    let shorthands = ["/**", "/<0;1>/*"];
+    if !shorthands.iter().any(|s| descriptor.contains(s)) {
+        return Descriptor::from_str(descriptor).map_err(|e| anyhow!("Invalid descriptor: {e}"));
+    }
+
+    if descriptor.contains('#') {
+        calc_checksum(descriptor).map_err(|e| anyhow!("Invalid descriptor checksum: {e}"))?;
+    }
+    let expanded = shorthands.iter().fold(
+        descriptor.split('#').next().unwrap_or_default().to_string(),
+        |d, s| d.replace(s, "/0/*"),
+    );
+    Descriptor::from_str(&expanded).map_err(|e| anyhow!("Invalid descriptor: {e}"))
+}
+
+/// Xpub and origin path of the descriptor key from `master_fingerprint`.
+fn find_key(
+    descriptor: &Descriptor<DescriptorPublicKey>,
+    master_fingerprint: Fingerprint,
+) -> Option<(ExtendedPubKey, DerivationPath)> {
+    let mut found = None;
+    descriptor.for_any_key(|key| match key {
+        DescriptorPublicKey::XPub(xkey) => match &xkey.origin {
+            Some((fingerprint, path)) if *fingerprint == master_fingerprint => {
+                found = Some((xkey.xkey, path.clone()));
+                true
+            }
+            _ => false,
+        },
+        _ => false,
+    });
+    found
+}
+
+/// Whether two xpubs are the same key. The network is ignored as a `tpub` is
+/// used for testnet, signet and regtest alike.
+fn same_key(a: &ExtendedPubKey, b: &ExtendedPubKey) -> bool {
+    a.public_key == b.public_key
+        && a.chain_code == b.chain_code
+        && a.depth == b.depth
+        && a.child_number == b.child_number
+}
+
+/// Checks a coordinator's descriptor record includes our key, the one from
+/// `master_fingerprint` for which `derive_xpub` gives the same xpub at the
+/// key's origin path, returning the descriptor.
+pub fn check_descriptor_record(
+    record: &str,
+    master_fingerprint: Fingerprint,
+    network: Network,
+    derive_xpub: impl FnOnce(&DerivationPath) -> Result<ExtendedPubKey>,
+) -> Result<String> {
+    let lines: Vec<&str> = record
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect();
+    let [version, descriptor, _path_restrictions, first_address] = lines.as_slice() else {
+        bail!("A descriptor record must have 4 lines");
+    };
+    if *version != BSMS_VERSION {
+        bail!("Unsupported BSMS version {version}");
+    }
+
+    let parsed = parse_descriptor(descriptor)?;
+    let (xpub, derivation) = find_key(&parsed, master_fingerprint)
+        .with_context(|| format!("The descriptor doesn't include the key {master_fingerprint}"))?;
+    if !same_key(&xpub, &derive_xpub(&derivation)?) {
+        bail!("The descriptor key {master_fingerprint} doesn't match our key at {derivation}");
+    }
+
+    let address = parsed
+        .at_derivation_index(0)
+        .address(network)
+        .map_err(|e| anyhow!("{e}"))?;
+    if address.to_string() != *first_address {
+        bail!("The first address {first_address} doesn't match the descriptor address {address}");
+    }
+
+    Ok(descriptor.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{check_descriptor_record, key_record, parse_descriptor};
+    use bdk::descriptor::checksum::calc_checksum;
+    use bitcoin::secp256k1::Secp256k1;
+    use bitcoin::util::bip32::{DerivationPath, ExtendedPrivKey, ExtendedPubKey};
+    use bitcoin::util::misc::{signed_msg_hash, MessageSignature};
+    use bitcoin::{Address, Network};
+    use std::str::FromStr;
+
+    const MASTER: &str = "xprv9s21ZrQH143K2LBWUUQRFXhucrQqBpKdRRxNVq2zBqsx8HVqFk2uYo8kmbaLLHRdqtQpUm98uKfu3vca1LqdGhUtyoFnCNkfmXRyPXLjbKb";
+    const COSIGNER: &str = "xpub6ERApfZwUNrhLCkDtcHTcxd75RbzS1ed54G1LkBUHQVHQKqhMkhgbmJbZRkrgZw4koxb5JaHWkY4ALHY2grBGRjaDMzQLcgJvLJuZZvRcEL";
+
+    fn account() -> (ExtendedPrivKey, DerivationPath) {
+        let secp = Secp256k1::new();
+        let derivation = DerivationPath::from_str("m/48'/0'/0'/2'").unwrap();
+        let xprv = ExtendedPrivKey::from_str(MASTER)
+            .unwrap()
+            .derive_priv(&secp, &derivation)
+            .unwrap();
+        (xprv, derivation)
+    }
+
+    #[test]
+    fn signs_key_record() {
+        let secp = Secp256k1::new();
+        let (xprv, derivation) = account();
+        let fingerprint = ExtendedPrivKey::from_str(MASTER)
+            .unwrap()
+            .fingerprint(&secp);
+        let record = key_record(
+            "a54044308ceac9b7",
+            &xprv,
+            fingerprint,
+            &derivation,
+            "Signer",
+        )
+        .unwrap();
+
+        let lines: Vec<&str> = record.lines().collect();
+        assert_eq!(lines[0], "BSMS 1.0");
+        assert_eq!(
+            lines[2],
+            format!(
+                "[{fingerprint}/48'/0'/0'/2']{}",
+                ExtendedPubKey::from_priv(&secp, &xprv)
+            )
+        );
+        let signature =
+            MessageSignature::from_slice(&crate::utils::base64::from_base64(lines[4]).unwrap())
+                .unwrap();
+        let address = Address::p2pkh(
+            &bitcoin::PublicKey::new(xprv.private_key.public_key(&secp)),
+            Network::Bitcoin,
+        );
+        assert!(signature
+            .is_signed_by_address(&secp, &address, signed_msg_hash(&lines[..4].join("\n")))
+            .unwrap());
+
+        assert!(key_record("1234", &xprv, fingerprint, &derivation, "Signer").is_err());
+        assert!(key_record("00", &xprv, fingerprint, &derivation, &"a".repeat(81)).is_err());
+    }
+
+    #[test]
+    fn checks_descriptor_record() {
+        let secp = Secp256k1::new();
+        let (xprv, derivation) = account();
+        let fingerprint = ExtendedPrivKey::from_str(MASTER)
+            .unwrap()
+            .fingerprint(&secp);
+        let xpub = ExtendedPubKey::from_priv(&secp, &xprv);
+        let descriptor = format!("wsh(sortedmulti(2,[{fingerprint}/48'/0'/0'/2']{xpub}/**,[00000000/48'/0'/0'/2']{COSIGNER}/**))");
+        let descriptor = format!("{descriptor}#{}", calc_checksum(&descriptor).unwrap());
+        let address = parse_descriptor(&descriptor)
+            .unwrap()
+            .at_derivation_index(0)
+            .address(Network::Bitcoin)
+            .unwrap();
+        let record = format!("BSMS 1.0\n{descriptor}\n/0/*,/1/*\n{address}");
+        let check = |record: &str, our_xpub: ExtendedPubKey| {
+            check_descriptor_record(record, fingerprint, Network::Bitcoin, |path| {
+                assert_eq!(*path, derivation);
+                Ok(our_xpub)
+            })
+        };
+
+        assert_eq!(check(&record, xpub).unwrap(), descriptor);
+        assert!(check(&record, ExtendedPubKey::from_str(COSIGNER).unwrap()).is_err());
+        let wrong_address = record.replace(
+            &address.to_string(),
+            "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq",
+        );
+        assert!(check(&wrong_address, xpub).is_err());
+        assert!(check(&record.replace('#', "#x"), xpub).is_err());
+        assert!(check(&record.replace("BSMS 1.0", "BSMS 2.0"), xpub).is_err());
+    }
+
+    #[test]
+    fn checks_regtest_descriptor_record() {
+        let secp = Secp256k1::new();
+        let (xprv, derivation) = account();
+        let fingerprint = ExtendedPrivKey::from_str(MASTER)
+            .unwrap()
+            .fingerprint(&secp);
+        let mut tpub = ExtendedPubKey::from_priv(&secp, &xprv);
+        tpub.network = Network::Testnet;
+        let mut cosigner = ExtendedPubKey::from_str(COSIGNER).unwrap();
+        cosigner.network = Network::Testnet;
+        let descriptor = format!("wsh(sortedmulti(2,[{fingerprint}/48'/0'/0'/2']{tpub}/**,[00000000/48'/0'/0'/2']{cosigner}/**))");
+        let address = parse_descriptor(&descriptor)
+            .unwrap()
+            .at_derivation_index(0)
+            .address(Network::Regtest)
+            .unwrap();
+        let record = format!("BSMS 1.0\n{descriptor}\n/0/*,/1/*\n{address}");
+
+        // a regtest wallet derives its xpub for the regtest network
+        let mut regtest_xpub = tpub;
+        regtest_xpub.network = Network::Regtest;
+        assert!(
+            check_descriptor_record(&record, fingerprint, Network::Regtest, |path| {
+                assert_eq!(*path, derivation);
+                Ok(regtest_xpub)
+            })
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn parses_descriptor_shorthands() {
+        let expanded = format!("wsh(sortedmulti(1,[00000000/48'/0'/0'/2']{COSIGNER}/0/*))");
+        let address = |descriptor: &str| {
+            parse_descriptor(descriptor)
+                .unwrap()
+                .at_derivation_index(0)
+                .address(Network::Bitcoin)
+                .unwrap()
+        };
+        for shorthand in ["/**", "/<0;1>/*"] {
+            let descriptor = expanded.replace("/0/*", shorthand);
+            let descriptor = format!("{descriptor}#{}", calc_checksum(&descriptor).unwrap());
+            assert_eq!(address(&descriptor), address(&expanded));
+            assert!(parse_descriptor(&descriptor.replace('#', "#x")).is_err());
+        }
+        assert!(parse_descriptor(&expanded.replace("(1,", "(2,")).is_err());
+    }
+}
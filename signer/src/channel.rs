@@ -0,0 +1,174 @@
+//! Checks of lightning channel funding outputs, a P2WSH 2-of-2 of both
+//! nodes' funding keys as defined in BOLT 3.
+//! <https://github.com/lightning/bolts/blob/master/03-transactions.md#funding-transaction-output>
+
+use anyhow::{bail, Context, Result};
+use bitcoin::blockdata::opcodes::all::{OP_CHECKMULTISIG, OP_PUSHNUM_2};
+use bitcoin::blockdata::script::Instruction;
+use bitcoin::psbt::PartiallySignedTransaction;
+use bitcoin::secp256k1::PublicKey;
+use bitcoin::{Amount, Script};
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ChannelFunding {
+    pub output_index: usize,
+    pub capacity: Amount,
+    /// Funding keys in script order.
+    pub pubkeys: [PublicKey; 2],
+}
+
+/// Parses `OP_2 <pubkey1> <pubkey2> OP_2 OP_CHECKMULTISIG` with the keys in
+/// lexicographic order.
+fn parse_funding_script(witness_script: &Script) -> Result<[PublicKey; 2]> {
+    let instructions = witness_script
+        .instructions()
+        .collect::<Result<Vec<Instruction>, _>>()
+        .context("Invalid funding witness script")?;
+    let [Instruction::Op(m), Instruction::PushBytes(first), Instruction::PushBytes(second), Instruction::Op(n), Instruction::Op(checkmultisig)] =
+        instructions.as_slice()
+    else {
+        bail!("The funding output isn't a 2-of-2 multisig");
+    };
+    if *m != OP_PUSHNUM_2 || *n != OP_PUSHNUM_2 || *checkmultisig != OP_CHECKMULTISIG {
+        bail!("The funding output isn't a 2-of-2 multisig");
+    }
+
+    let pubkeys = [
+        PublicKey::from_slice(first)?,
+        PublicKey::from_slice(second)?,
+    ];
+    if pubkeys[0].serialize() >= pubkeys[1].serialize() {
+        bail!("The funding keys aren't sorted as required by BOLT 3");
+    }
+    Ok(pubkeys)
+}
+
+/// Finds the funding output of `capacity` and checks its witness script is a
+/// channel 2-of-2, including `node_pubkey` when given.
+pub fn verify_channel_funding(
+    psbt: &PartiallySignedTransaction,
+    capacity: Amount,
+    node_pubkey: Option<&PublicKey>,
+) -> Result<ChannelFunding> {
+    let candidates: Vec<usize> = psbt
+        .unsigned_tx
+        .output
+        .iter()
+        .enumerate()
+        .filter(|(_, o)| o.script_pubkey.is_v0_p2wsh() && o.value == capacity.to_sat())
+        .map(|(index, _)| index)
+        .collect();
+    let [output_index] = candidates.as_slice() else {
+        bail!(
+            "Expected exactly one P2WSH output of the channel capacity, found {}",
+            candidates.len()
+        );
+    };
+
+    let witness_script = psbt
+        .outputs
+        .get(*output_index)
+        .and_then(|o| o.witness_script.as_ref())
+        .context("The funding output doesn't include its witness script")?;
+    if Script::new_v0_p2wsh(&witness_script.wscript_hash())
+        != psbt.unsigned_tx.output[*output_index].script_pubkey
+    {
+        bail!("The funding witness script doesn't match the funding output");
+    }
+
+    let pubkeys = parse_funding_script(witness_script)?;
+    if let Some(node_pubkey) = node_pubkey {
+        if !pubkeys.contains(node_pubkey) {
+            bail!("The funding output doesn't include the expected key {node_pubkey}");
+        }
+    }
+
+    Ok(ChannelFunding {
+        output_index: *output_index,
+        capacity,
+        pubkeys,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::verify_channel_funding;
+    use bitcoin::blockdata::opcodes::all::{OP_CHECKMULTISIG, OP_PUSHNUM_2};
+    use bitcoin::blockdata::script::Builder;
+    use bitcoin::psbt::PartiallySignedTransaction;
+    use bitcoin::secp256k1::{PublicKey, Secp256k1, SecretKey};
+    use bitcoin::{Amount, OutPoint, PackedLockTime, Script, Sequence, Transaction, TxIn, TxOut};
+
+    fn pubkey(seed: u8) -> PublicKey {
+        SecretKey::from_slice(&[seed; 32])
+            .unwrap()
+            .public_key(&Secp256k1::new())
+    }
+
+    fn funding_script(first: &PublicKey, second: &PublicKey) -> Script {
+        Builder::new()
+            .push_opcode(OP_PUSHNUM_2)
+            .push_slice(&first.serialize())
+            .push_slice(&second.serialize())
+            .push_opcode(OP_PUSHNUM_2)
+            .push_opcode(OP_CHECKMULTISIG)
+            .into_script()
+    }
+
+    fn psbt(witness_script: Option<Script>, value: u64) -> PartiallySignedTransaction {
+        let script_pubkey = witness_script.as_ref().map_or_else(
+            || funding_script(&pubkey(1), &pubkey(2)).to_v0_p2wsh(),
+            Script::to_v0_p2wsh,
+        );
+        let tx = Transaction {
+            version: 2,
+            lock_time: PackedLockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::default(),
+                script_sig: Script::new(),
+                sequence: Sequence::MAX,
+                witness: Default::default(),
+            }],
+            output: vec![TxOut {
+                value,
+                script_pubkey,
+            }],
+        };
+        let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx).unwrap();
+        psbt.outputs[0].witness_script = witness_script;
+        psbt
+    }
+
+    #[test]
+    fn verifies_funding_output() {
+        let mut keys = [pubkey(1), pubkey(2)];
+        keys.sort_by_key(PublicKey::serialize);
+        let script = funding_script(&keys[0], &keys[1]);
+        let capacity = Amount::from_sat(100_000);
+
+        let funding = verify_channel_funding(
+            &psbt(Some(script.clone()), 100_000),
+            capacity,
+            Some(&keys[1]),
+        )
+        .unwrap();
+        assert_eq!(funding.output_index, 0);
+        assert_eq!(funding.pubkeys, keys);
+
+        assert!(
+            verify_channel_funding(&psbt(Some(script.clone()), 90_000), capacity, None).is_err()
+        );
+        assert!(
+            verify_channel_funding(&psbt(Some(script), 100_000), capacity, Some(&pubkey(3)))
+                .is_err()
+        );
+        assert!(verify_channel_funding(&psbt(None, 100_000), capacity, None).is_err());
+        let unsorted = funding_script(&keys[1], &keys[0]);
+        assert!(verify_channel_funding(&psbt(Some(unsorted), 100_000), capacity, None).is_err());
+        let single = Builder::new()
+            .push_slice(&keys[0].serialize())
+            .push_opcode(bitcoin::blockdata::opcodes::all::OP_CHECKSIG)
+            .into_script();
+        assert!(verify_channel_funding(&psbt(Some(single), 100_000), capacity, None).is_err());
+    }
+}
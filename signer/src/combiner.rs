@@ -0,0 +1,148 @@
+//! BIP174 Combiner, merges PSBTs of the same unsigned transaction signed by
+//! different cosigners.
+//! <https://github.com/bitcoin/bips/blob/master/bip-0174.mediawiki#user-content-Combiner>
+
+use anyhow::{anyhow, bail, Result};
+use bitcoin::consensus::serialize;
+use bitcoin::psbt::{Input, PartiallySignedTransaction};
+use bitcoin::util::psbt::Error;
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::psbt_decoder::decode_psbt;
+use crate::utils::base64::to_base64;
+
+/// Signatures an input gained when combining.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct AddedSignatures {
+    pub input: usize,
+    /// Keys of the new signatures, `taproot key` for key path signatures.
+    pub keys: Vec<String>,
+}
+
+impl fmt::Display for AddedSignatures {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Input {}: {}", self.input, self.keys.join(", "))
+    }
+}
+
+fn signature_keys(input: &Input) -> BTreeSet<String> {
+    input
+        .partial_sigs
+        .keys()
+        .map(ToString::to_string)
+        .chain(input.tap_script_sigs.keys().map(|(k, _)| k.to_string()))
+        .chain(input.tap_key_sig.map(|_| "taproot key".to_string()))
+        .collect()
+}
+
+/// Merges `others` into `psbt`, returning the signatures added to each input.
+pub fn combine_psbts(
+    psbt: &mut PartiallySignedTransaction,
+    others: Vec<PartiallySignedTransaction>,
+) -> Result<Vec<AddedSignatures>> {
+    let before: Vec<BTreeSet<String>> = psbt.inputs.iter().map(signature_keys).collect();
+
+    for (index, other) in others.into_iter().enumerate() {
+        psbt.combine(other).map_err(|e| match e {
+            Error::UnexpectedUnsignedTx { .. } => {
+                anyhow!("PSBT {} is for a different transaction", index + 2)
+            }
+            e => anyhow!("Error combining PSBT {}: {e}", index + 2),
+        })?;
+    }
+
+    Ok(psbt
+        .inputs
+        .iter()
+        .zip(before)
+        .enumerate()
+        .map(|(input, (i, before))| AddedSignatures {
+            input,
+            keys: signature_keys(i).difference(&before).cloned().collect(),
+        })
+        .filter(|added| !added.keys.is_empty())
+        .collect())
+}
+
+/// Decodes PSBTs in any supported encoding and combines them, returning the
+/// combined PSBT in base64.
+pub fn decode_psbts_and_combine<T: AsRef<[u8]>>(
+    psbts: &[T],
+) -> Result<(String, Vec<AddedSignatures>)> {
+    if psbts.len() < 2 {
+        bail!("At least two PSBTs are needed to combine them");
+    }
+    let mut decoded = psbts
+        .iter()
+        .enumerate()
+        .map(|(index, p)| decode_psbt(p.as_ref()).map_err(|e| anyhow!("PSBT {}: {e}", index + 1)))
+        .collect::<Result<Vec<PartiallySignedTransaction>>>()?;
+    let mut psbt = decoded.remove(0);
+    let added = combine_psbts(&mut psbt, decoded)?;
+
+    Ok((to_base64(&serialize(&psbt)), added))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{combine_psbts, decode_psbts_and_combine};
+    use bitcoin::psbt::PartiallySignedTransaction;
+    use bitcoin::secp256k1::{Message, Secp256k1, SecretKey};
+    use bitcoin::{
+        EcdsaSig, OutPoint, PackedLockTime, PublicKey, Script, Sequence, Transaction, TxIn, TxOut,
+    };
+
+    fn psbt(value: u64) -> PartiallySignedTransaction {
+        let tx = Transaction {
+            version: 2,
+            lock_time: PackedLockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::default(),
+                script_sig: Script::new(),
+                sequence: Sequence::MAX,
+                witness: Default::default(),
+            }],
+            output: vec![TxOut {
+                value,
+                script_pubkey: Script::new(),
+            }],
+        };
+        PartiallySignedTransaction::from_unsigned_tx(tx).unwrap()
+    }
+
+    fn signed(seed: u8) -> (PartiallySignedTransaction, PublicKey) {
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[seed; 32]).unwrap();
+        let public_key = PublicKey::new(secret_key.public_key(&secp));
+        let signature = secp.sign_ecdsa(&Message::from_slice(&[1; 32]).unwrap(), &secret_key);
+        let mut psbt = psbt(1000);
+        psbt.inputs[0]
+            .partial_sigs
+            .insert(public_key, EcdsaSig::sighash_all(signature));
+        (psbt, public_key)
+    }
+
+    #[test]
+    fn combines_signatures() {
+        let (mut first, _) = signed(1);
+        let (second, second_key) = signed(2);
+        let added = combine_psbts(&mut first, vec![second.clone(), psbt(1000)]).unwrap();
+
+        assert_eq!(first.inputs[0].partial_sigs.len(), 2);
+        assert_eq!(added.len(), 1);
+        assert_eq!(added[0].input, 0);
+        assert_eq!(added[0].keys, vec![second_key.to_string()]);
+        assert!(combine_psbts(&mut first, vec![second]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_different_transactions() {
+        let (mut first, _) = signed(1);
+        assert!(combine_psbts(&mut first, vec![psbt(999)]).is_err());
+
+        let encoded = bitcoin::consensus::serialize(&psbt(1000));
+        assert!(decode_psbts_and_combine(&[&encoded]).is_err());
+        assert!(decode_psbts_and_combine(&[&encoded, &encoded]).is_ok());
+    }
+}
@@ -0,0 +1,56 @@
+use bitcoin::util::bip32::{ChildNumber, DerivationPath};
+use bitcoin::Network;
+use std::fmt;
+
+/// Common account derivations a seed can be scoped to.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AccountPreset {
+    Master,
+    Bip48P2WSH,
+    Bip48P2SHP2WSH,
+    Bip48P2TR,
+    Bip84,
+    Bip86,
+}
+
+impl fmt::Display for AccountPreset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AccountPreset::Master => write!(f, "Master key"),
+            AccountPreset::Bip48P2WSH => write!(f, "BIP48 P2WSH multisig"),
+            AccountPreset::Bip48P2SHP2WSH => write!(f, "BIP48 P2SH-P2WSH multisig"),
+            AccountPreset::Bip48P2TR => write!(f, "BIP48 P2TR multisig"),
+            AccountPreset::Bip84 => write!(f, "BIP84 P2WPKH"),
+            AccountPreset::Bip86 => write!(f, "BIP86 P2TR"),
+        }
+    }
+}
+
+impl AccountPreset {
+    pub const ALL: [AccountPreset; 6] = [
+        AccountPreset::Master,
+        AccountPreset::Bip48P2WSH,
+        AccountPreset::Bip48P2SHP2WSH,
+        AccountPreset::Bip48P2TR,
+        AccountPreset::Bip84,
+        AccountPreset::Bip86,
+    ];
+
+    /// Account path for the given network's coin type and account index.
+    pub fn derivation_path(&self, network: Network, account: u32) -> DerivationPath {
+        let coin_type = u32::from(network != Network::Bitcoin);
+        let path = match self {
+            AccountPreset::Master => vec![],
+            AccountPreset::Bip48P2WSH => vec![48, coin_type, account, 2],
+            AccountPreset::Bip48P2SHP2WSH => vec![48, coin_type, account, 1],
+            AccountPreset::Bip48P2TR => vec![48, coin_type, account, 3],
+            AccountPreset::Bip84 => vec![84, coin_type, account],
+            AccountPreset::Bip86 => vec![86, coin_type, account],
+        };
+
+        path.into_iter()
+            .map(|index| ChildNumber::Hardened { index })
+            .collect::<Vec<ChildNumber>>()
+            .into()
+    }
+}
@@ -0,0 +1,173 @@
+//! Seed generation from user supplied dice rolls or coin flips, computed so
+//! it can be reproduced offline: the rolls are hashed with SHA256 and the
+//! digest, optionally XORed with RNG bytes, is used as BIP39 entropy.
+
+use anyhow::{anyhow, bail, Result};
+use bdk::keys::bip39::Mnemonic;
+use bitcoin::hashes::hex::ToHex;
+use bitcoin::hashes::{sha256, Hash};
+use std::fmt;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum EntropySource {
+    Dice,
+    Coin,
+}
+
+impl fmt::Display for EntropySource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EntropySource::Dice => write!(f, "Dice rolls"),
+            EntropySource::Coin => write!(f, "Coin flips"),
+        }
+    }
+}
+
+impl EntropySource {
+    /// Rolls needed to reach 256 bits of entropy, log2(6) ≈ 2.585 bits per
+    /// dice roll and 1 bit per coin flip.
+    pub fn min_rolls(&self) -> usize {
+        match self {
+            EntropySource::Dice => 100,
+            EntropySource::Coin => 256,
+        }
+    }
+
+    /// Maps each roll to a single character, `1`-`6` for dice and `1` for
+    /// heads or `0` for tails for coins, ignoring whitespace and commas.
+    fn normalize(&self, rolls: &str) -> Result<String> {
+        rolls
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != ',')
+            .map(|c| match (self, c.to_ascii_uppercase()) {
+                (EntropySource::Dice, '1'..='6') => Ok(c),
+                (EntropySource::Coin, 'H' | '1') => Ok('1'),
+                (EntropySource::Coin, 'T' | '0') => Ok('0'),
+                _ => Err(anyhow!(
+                    "Invalid {} value '{c}'",
+                    self.to_string().to_lowercase()
+                )),
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UserEntropy {
+    pub source: EntropySource,
+    pub rolls: String,
+    pub rolls_hash: [u8; 32],
+    pub rng: Option<[u8; 32]>,
+    pub entropy: [u8; 32],
+    pub mnemonic: String,
+}
+
+impl UserEntropy {
+    /// Steps to reproduce the seed offline.
+    pub fn explanation(&self) -> String {
+        let mut steps = vec![
+            format!(
+                "{}: {} ({} rolls)",
+                self.source,
+                self.rolls,
+                self.rolls.len()
+            ),
+            format!("sha256(\"{}\") = {}", self.rolls, self.rolls_hash.to_hex()),
+        ];
+        if let Some(rng) = self.rng {
+            steps.push(format!("RNG entropy = {}", rng.to_hex()));
+            steps.push(format!(
+                "{} XOR {} = {}",
+                self.rolls_hash.to_hex(),
+                rng.to_hex(),
+                self.entropy.to_hex()
+            ));
+        }
+        steps.push(format!("BIP39 entropy = {}", self.entropy.to_hex()));
+        steps.join("\n")
+    }
+}
+
+pub fn entropy_from_rolls(
+    source: EntropySource,
+    rolls: &str,
+    rng: Option<[u8; 32]>,
+) -> Result<UserEntropy> {
+    let rolls = source.normalize(rolls)?;
+    if rolls.len() < source.min_rolls() {
+        bail!(
+            "At least {} {} are needed, got {}",
+            source.min_rolls(),
+            source.to_string().to_lowercase(),
+            rolls.len()
+        );
+    }
+
+    let rolls_hash = sha256::Hash::hash(rolls.as_bytes()).into_inner();
+    let mut entropy = rolls_hash;
+    if let Some(rng) = rng {
+        entropy.iter_mut().zip(rng).for_each(|(e, r)| *e ^= r);
+    }
+    let mnemonic = Mnemonic::from_entropy(&entropy)
+        .map_err(|e| anyhow!("{e}"))?
+        .to_string();
+
+    Ok(UserEntropy {
+        source,
+        rolls,
+        rolls_hash,
+        rng,
+        entropy,
+        mnemonic,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{entropy_from_rolls, EntropySource};
+    use bitcoin::hashes::hex::ToHex;
+
+    #[test]
+    fn dice_entropy() {
+        let rolls = "123456".repeat(17);
+        let result = entropy_from_rolls(EntropySource::Dice, &rolls, None).unwrap();
+        assert_eq!(result.entropy.to_hex(), DICE_ENTROPY);
+        assert_eq!(result.mnemonic.split_whitespace().count(), 24);
+
+        let spaced = rolls.chars().map(|c| format!("{c}, ")).collect::<String>();
+        let result = entropy_from_rolls(EntropySource::Dice, &spaced, None).unwrap();
+        assert_eq!(result.entropy.to_hex(), DICE_ENTROPY);
+
+        let mixed = entropy_from_rolls(EntropySource::Dice, &rolls, Some([0xff; 32])).unwrap();
+        assert!(mixed
+            .entropy
+            .iter()
+            .zip(result.entropy)
+            .all(|(m, e)| *m == !e));
+
+        assert!(entropy_from_rolls(EntropySource::Dice, &rolls[..99], None).is_err());
+        assert!(entropy_from_rolls(EntropySource::Dice, &rolls[..100], None).is_ok());
+        assert!(entropy_from_rolls(EntropySource::Dice, &format!("{rolls}7"), None).is_err());
+    }
+
+    #[test]
+    fn coin_entropy() {
+        let flips = "HT".repeat(128);
+        let result = entropy_from_rolls(EntropySource::Coin, &flips, None).unwrap();
+        assert_eq!(result.rolls, "10".repeat(128));
+        assert_eq!(result.entropy.to_hex(), COIN_ENTROPY);
+        assert!(entropy_from_rolls(EntropySource::Coin, &flips[..255], None).is_err());
+    }
+
+    #[test]
+    fn min_rolls_reach_256_bits() {
+        for (source, outcomes) in [(EntropySource::Dice, 6f64), (EntropySource::Coin, 2f64)] {
+            let bits = |rolls: usize| rolls as f64 * outcomes.log2();
+            assert!(bits(source.min_rolls()) >= 256.0);
+            assert!(bits(source.min_rolls() - 1) < 256.0);
+        }
+    }
+
+    const DICE_ENTROPY: &str = "8fd128918b2e29d6dcbfa5b9a118e5c16d60498c7ba107922a8eb6eb1d36c112";
+    const COIN_ENTROPY: &str = "9e2de6152e29d79a7e602f76ea23662a7c5a82ddf879e8a2bbd95506362534c7";
+}
@@ -1,7 +1,19 @@
 pub use bitcoin::Network;
 
+pub mod anti_exfil;
+pub mod bip85;
+pub mod bsms;
+pub mod channel;
+pub mod combiner;
+pub mod derivation;
+pub mod entropy;
+pub mod message;
+pub mod musig;
+pub mod policy;
+pub mod psbt_decoder;
 pub mod psbt_details;
 pub mod signer;
+pub mod slip39;
 pub mod storage;
 pub mod utils;
 pub mod wallet;
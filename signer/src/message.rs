@@ -0,0 +1,461 @@
+//! Proof of address ownership with signed messages, using the legacy BIP137
+//! format or BIP322 for segwit v0 and taproot addresses.
+//! <https://github.com/bitcoin/bips/blob/master/bip-0137.mediawiki>
+//! <https://github.com/bitcoin/bips/blob/master/bip-0322.mediawiki>
+
+use crate::utils::base64::{from_base64, to_base64};
+use anyhow::{bail, Context, Result};
+use bitcoin::blockdata::opcodes::all::OP_RETURN;
+use bitcoin::blockdata::script::Builder;
+use bitcoin::consensus::{deserialize, serialize};
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::secp256k1::ecdsa::{RecoverableSignature, RecoveryId};
+use bitcoin::secp256k1::{KeyPair, Message, Secp256k1, SecretKey, XOnlyPublicKey};
+use bitcoin::util::address::{Payload, WitnessVersion};
+use bitcoin::util::bip32::{ChildNumber, DerivationPath};
+use bitcoin::util::misc::signed_msg_hash;
+use bitcoin::util::schnorr::TapTweak;
+use bitcoin::util::sighash::{Prevouts, SighashCache};
+use bitcoin::{
+    Address, EcdsaSig, EcdsaSighashType, Network, OutPoint, PackedLockTime, PublicKey, SchnorrSig,
+    SchnorrSighashType, Script, Sequence, Transaction, TxIn, TxOut, Witness,
+};
+use rand::Rng;
+use std::fmt;
+use std::str::FromStr;
+
+const BIP322_TAG: &[u8] = b"BIP0322-signed-message";
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AddressType {
+    P2pkh,
+    P2shP2wpkh,
+    P2wpkh,
+    P2tr,
+}
+
+impl fmt::Display for AddressType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AddressType::P2pkh => write!(f, "Legacy (P2PKH)"),
+            AddressType::P2shP2wpkh => write!(f, "Nested segwit (P2SH-P2WPKH)"),
+            AddressType::P2wpkh => write!(f, "Native segwit (P2WPKH)"),
+            AddressType::P2tr => write!(f, "Taproot (P2TR)"),
+        }
+    }
+}
+
+impl AddressType {
+    pub const ALL: [AddressType; 4] = [
+        AddressType::P2pkh,
+        AddressType::P2shP2wpkh,
+        AddressType::P2wpkh,
+        AddressType::P2tr,
+    ];
+
+    /// Address type of the single key BIP44, BIP49, BIP84 and BIP86 paths.
+    pub fn from_derivation(derivation: &DerivationPath) -> Option<AddressType> {
+        match derivation.as_ref().first() {
+            Some(ChildNumber::Hardened { index: 44 }) => Some(AddressType::P2pkh),
+            Some(ChildNumber::Hardened { index: 49 }) => Some(AddressType::P2shP2wpkh),
+            Some(ChildNumber::Hardened { index: 84 }) => Some(AddressType::P2wpkh),
+            Some(ChildNumber::Hardened { index: 86 }) => Some(AddressType::P2tr),
+            _ => None,
+        }
+    }
+
+    fn address(self, public_key: &PublicKey, network: Network) -> Result<Address> {
+        Ok(match self {
+            AddressType::P2pkh => Address::p2pkh(public_key, network),
+            AddressType::P2shP2wpkh => Address::p2shwpkh(public_key, network)?,
+            AddressType::P2wpkh => Address::p2wpkh(public_key, network)?,
+            AddressType::P2tr => Address::p2tr(
+                &Secp256k1::verification_only(),
+                XOnlyPublicKey::from(public_key.inner),
+                None,
+                network,
+            ),
+        })
+    }
+
+    /// Offset of the BIP137 header for the address type.
+    fn bip137_header_offset(self) -> Result<u8> {
+        match self {
+            AddressType::P2pkh => Ok(31),
+            AddressType::P2shP2wpkh => Ok(35),
+            AddressType::P2wpkh => Ok(39),
+            AddressType::P2tr => bail!("Taproot addresses can only sign with BIP322"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SignatureFormat {
+    Bip137,
+    Bip322Simple,
+    Bip322Full,
+}
+
+impl fmt::Display for SignatureFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SignatureFormat::Bip137 => write!(f, "BIP137 (legacy)"),
+            SignatureFormat::Bip322Simple => write!(f, "BIP322 simple"),
+            SignatureFormat::Bip322Full => write!(f, "BIP322 full"),
+        }
+    }
+}
+
+impl SignatureFormat {
+    pub const ALL: [SignatureFormat; 3] = [
+        SignatureFormat::Bip137,
+        SignatureFormat::Bip322Simple,
+        SignatureFormat::Bip322Full,
+    ];
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SignedMessage {
+    pub address: Address,
+    pub signature: String,
+}
+
+fn message_hash(message: &str) -> sha256::Hash {
+    let tag = sha256::Hash::hash(BIP322_TAG);
+    let mut engine = sha256::Hash::engine();
+    engine.input(&tag[..]);
+    engine.input(&tag[..]);
+    engine.input(message.as_bytes());
+    sha256::Hash::from_engine(engine)
+}
+
+/// Virtual transaction committing to the message and spent by `to_sign`.
+fn to_spend(script_pubkey: &Script, message: &str) -> Transaction {
+    Transaction {
+        version: 0,
+        lock_time: PackedLockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint::null(),
+            script_sig: Builder::new()
+                .push_int(0)
+                .push_slice(&message_hash(message)[..])
+                .into_script(),
+            sequence: Sequence::ZERO,
+            witness: Witness::default(),
+        }],
+        output: vec![TxOut {
+            value: 0,
+            script_pubkey: script_pubkey.clone(),
+        }],
+    }
+}
+
+fn to_sign(to_spend: &Transaction, witness: Witness) -> Transaction {
+    Transaction {
+        version: 0,
+        lock_time: PackedLockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint::new(to_spend.txid(), 0),
+            script_sig: Script::new(),
+            sequence: Sequence::ZERO,
+            witness,
+        }],
+        output: vec![TxOut {
+            value: 0,
+            script_pubkey: Builder::new().push_opcode(OP_RETURN).into_script(),
+        }],
+    }
+}
+
+fn sign_bip137(secret_key: &SecretKey, message: &str, address_type: AddressType) -> Result<String> {
+    let header_offset = address_type.bip137_header_offset()?;
+    let msg = Message::from_slice(&signed_msg_hash(message)[..])?;
+    let (recovery_id, signature) = Secp256k1::new()
+        .sign_ecdsa_recoverable(&msg, secret_key)
+        .serialize_compact();
+
+    let mut serialized = vec![header_offset + recovery_id.to_i32() as u8];
+    serialized.extend_from_slice(&signature);
+    Ok(to_base64(&serialized))
+}
+
+fn sign_bip322(
+    secret_key: &SecretKey,
+    message: &str,
+    address: &Address,
+    address_type: AddressType,
+) -> Result<Transaction> {
+    let secp = Secp256k1::new();
+    let to_spend = to_spend(&address.script_pubkey(), message);
+    let mut to_sign = to_sign(&to_spend, Witness::default());
+
+    let witness = match address_type {
+        AddressType::P2wpkh => {
+            let public_key = PublicKey::new(secret_key.public_key(&secp));
+            let script_code = Script::new_p2pkh(&public_key.pubkey_hash());
+            let sighash = SighashCache::new(&to_sign).segwit_signature_hash(
+                0,
+                &script_code,
+                0,
+                EcdsaSighashType::All,
+            )?;
+            let signature = EcdsaSig {
+                sig: secp.sign_ecdsa_low_r(&Message::from_slice(&sighash)?, secret_key),
+                hash_ty: EcdsaSighashType::All,
+            };
+            Witness::from_vec(vec![signature.to_vec(), public_key.to_bytes()])
+        }
+        AddressType::P2tr => {
+            let sighash = SighashCache::new(&to_sign).taproot_key_spend_signature_hash(
+                0,
+                &Prevouts::All(&to_spend.output),
+                SchnorrSighashType::Default,
+            )?;
+            let keypair = KeyPair::from_secret_key(&secp, secret_key).tap_tweak(&secp, None);
+            let signature = secp.sign_schnorr_with_aux_rand(
+                &Message::from_slice(&sighash)?,
+                &keypair.to_inner(),
+                &rand::thread_rng().gen(),
+            );
+            Witness::from_vec(vec![signature.as_ref().to_vec()])
+        }
+        _ => bail!("BIP322 signing is only supported for P2WPKH and P2TR addresses"),
+    };
+
+    to_sign.input[0].witness = witness;
+    Ok(to_sign)
+}
+
+/// Signs `message` with the key behind an address of `address_type`.
+pub fn sign_message(
+    secret_key: &SecretKey,
+    message: &str,
+    address_type: AddressType,
+    format: SignatureFormat,
+    network: Network,
+) -> Result<SignedMessage> {
+    let public_key = PublicKey::new(secret_key.public_key(&Secp256k1::new()));
+    let address = address_type.address(&public_key, network)?;
+    let signature = match format {
+        SignatureFormat::Bip137 => sign_bip137(secret_key, message, address_type)?,
+        SignatureFormat::Bip322Simple => {
+            let to_sign = sign_bip322(secret_key, message, &address, address_type)?;
+            to_base64(&serialize(&to_sign.input[0].witness))
+        }
+        SignatureFormat::Bip322Full => to_base64(&serialize(&sign_bip322(
+            secret_key,
+            message,
+            &address,
+            address_type,
+        )?)),
+    };
+
+    Ok(SignedMessage { address, signature })
+}
+
+/// BIP137 signatures from wallets that don't set the segwit headers are also
+/// accepted for segwit addresses of the recovered key.
+fn verify_bip137(address: &Address, message: &str, signature: &[u8]) -> Result<bool> {
+    let header = signature[0];
+    let recovery_id = RecoveryId::from_i32(i32::from((header - 27) % 4))?;
+    let signature = RecoverableSignature::from_compact(&signature[1..], recovery_id)?;
+    let msg = Message::from_slice(&signed_msg_hash(message)[..])?;
+    let public_key = Secp256k1::verification_only().recover_ecdsa(&msg, &signature)?;
+
+    let candidates = match header {
+        27..=30 => vec![Address::p2pkh(
+            &PublicKey {
+                compressed: false,
+                inner: public_key,
+            },
+            address.network,
+        )],
+        _ => [
+            AddressType::P2pkh,
+            AddressType::P2shP2wpkh,
+            AddressType::P2wpkh,
+        ]
+        .into_iter()
+        .map(|t| t.address(&PublicKey::new(public_key), address.network))
+        .collect::<Result<Vec<Address>>>()?,
+    };
+
+    Ok(candidates
+        .iter()
+        .any(|c| c.script_pubkey() == address.script_pubkey()))
+}
+
+fn verify_bip322(address: &Address, message: &str, signature: &[u8]) -> Result<bool> {
+    let to_spend = to_spend(&address.script_pubkey(), message);
+    let to_sign = match deserialize::<Transaction>(signature) {
+        Ok(signed) => {
+            let witness = signed
+                .input
+                .first()
+                .map(|i| i.witness.clone())
+                .unwrap_or_default();
+            // A transaction that doesn't spend the message signs another one
+            if signed != to_sign(&to_spend, witness) {
+                return Ok(false);
+            }
+            signed
+        }
+        Err(_) => to_sign(
+            &to_spend,
+            deserialize::<Witness>(signature).context("Invalid BIP322 signature")?,
+        ),
+    };
+    let witness = to_sign.input[0].witness.to_vec();
+    let secp = Secp256k1::verification_only();
+
+    match &address.payload {
+        Payload::WitnessProgram {
+            version: WitnessVersion::V0,
+            program,
+        } if program.len() == 20 => {
+            let [signature, public_key] = witness.as_slice() else {
+                bail!("P2WPKH signatures must have 2 witness items");
+            };
+            let public_key = PublicKey::from_slice(public_key)?;
+            if Address::p2wpkh(&public_key, address.network)?.script_pubkey()
+                != address.script_pubkey()
+            {
+                return Ok(false);
+            }
+            let signature = EcdsaSig::from_slice(signature)?;
+            let sighash = SighashCache::new(&to_sign).segwit_signature_hash(
+                0,
+                &Script::new_p2pkh(&public_key.pubkey_hash()),
+                0,
+                signature.hash_ty,
+            )?;
+            Ok(secp
+                .verify_ecdsa(
+                    &Message::from_slice(&sighash)?,
+                    &signature.sig,
+                    &public_key.inner,
+                )
+                .is_ok())
+        }
+        Payload::WitnessProgram {
+            version: WitnessVersion::V1,
+            program,
+        } if program.len() == 32 => {
+            let [signature] = witness.as_slice() else {
+                bail!("P2TR signatures must have 1 witness item");
+            };
+            let signature = SchnorrSig::from_slice(signature)?;
+            let sighash = SighashCache::new(&to_sign).taproot_key_spend_signature_hash(
+                0,
+                &Prevouts::All(&to_spend.output),
+                signature.hash_ty,
+            )?;
+            Ok(secp
+                .verify_schnorr(
+                    &signature.sig,
+                    &Message::from_slice(&sighash)?,
+                    &XOnlyPublicKey::from_slice(program)?,
+                )
+                .is_ok())
+        }
+        _ => bail!("BIP322 verification is only supported for P2WPKH and P2TR addresses"),
+    }
+}
+
+/// Verifies a BIP137 or BIP322 signature of `message` by `address`.
+pub fn verify_message(address: &str, message: &str, signature: &str) -> Result<bool> {
+    let address = Address::from_str(address.trim()).context("Invalid address")?;
+    let signature = from_base64(signature.trim()).context("Invalid base64 signature")?;
+
+    match signature.first() {
+        Some(27..=42) if signature.len() == 65 => verify_bip137(&address, message, &signature),
+        _ => verify_bip322(&address, message, &signature),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{message_hash, sign_message, to_sign, to_spend, verify_message};
+    use super::{AddressType, SignatureFormat};
+    use bitcoin::{Address, Network, PrivateKey, Witness};
+    use std::str::FromStr;
+
+    const WIF: &str = "L3VFeEujGtevx9w18HD1fhRbCH67Az2dpCymeRE1SoPK6XQtaN2k";
+    const P2WPKH: &str = "bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l";
+    const P2TR: &str = "bc1ppv609nr0vr25u07u95waq5lucwfm6tde4nydujnu8npg4q75mr5sxq8lt3";
+
+    #[test]
+    fn bip322_vectors() {
+        assert_eq!(
+            message_hash("").to_string(),
+            "c90c269c4f8fcbe6880f72a721ddfbf1914268a794cbb21cfafee13770ae19f1"
+        );
+        assert_eq!(
+            message_hash("Hello World").to_string(),
+            "f0eb03b1a75ac6d9847f55c624a99169b5dccba2a31f5b23bea77ba270de0a7a"
+        );
+
+        let script_pubkey = Address::from_str(P2WPKH).unwrap().script_pubkey();
+        let to_spend = to_spend(&script_pubkey, "");
+        assert_eq!(
+            to_spend.txid().to_string(),
+            "c5680aa69bb8d860bf82d4e9cd3504b55dde018de765a91bb566283c545a99a7"
+        );
+        assert_eq!(
+            to_sign(&to_spend, Witness::default()).txid().to_string(),
+            "1e9654e951a5ba44c8604c4de6c67fd78a27e81dcadcfe1edf638ba3aaebaed6"
+        );
+
+        assert!(verify_message(P2WPKH, "Hello World", "AkcwRAIgZRfIY3p7/DoVTty6YZbWS71bc5Vct9p9Fia83eRmw2QCICK/ENGfwLtptFluMGs2KsqoNSk89pO7F29zJLUx9a/sASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=").unwrap());
+        assert!(!verify_message(P2WPKH, "Hello World!", "AkcwRAIgZRfIY3p7/DoVTty6YZbWS71bc5Vct9p9Fia83eRmw2QCICK/ENGfwLtptFluMGs2KsqoNSk89pO7F29zJLUx9a/sASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=").unwrap());
+        assert!(verify_message(P2TR, "Hello World", "AUHd69PrJQEv+oKTfZ8l+WROBHuy9HKrbFCJu7U1iK2iiEy1vMU5EfMtjc+VSHM7aU0SDbak5IUZRVno2P5mjSafAQ==").unwrap());
+    }
+
+    #[test]
+    fn sign_and_verify() {
+        let secret_key = PrivateKey::from_wif(WIF).unwrap().inner;
+
+        let signed = sign_message(
+            &secret_key,
+            "Hello World",
+            AddressType::P2wpkh,
+            SignatureFormat::Bip322Simple,
+            Network::Bitcoin,
+        )
+        .unwrap();
+        assert_eq!(signed.address.to_string(), P2WPKH);
+        assert_eq!(signed.signature, "AkcwRAIgZRfIY3p7/DoVTty6YZbWS71bc5Vct9p9Fia83eRmw2QCICK/ENGfwLtptFluMGs2KsqoNSk89pO7F29zJLUx9a/sASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=");
+
+        for address_type in AddressType::ALL {
+            for format in SignatureFormat::ALL {
+                let signed = sign_message(
+                    &secret_key,
+                    "Hello World",
+                    address_type,
+                    format,
+                    Network::Bitcoin,
+                );
+                let supported = match format {
+                    SignatureFormat::Bip137 => address_type != AddressType::P2tr,
+                    _ => matches!(address_type, AddressType::P2wpkh | AddressType::P2tr),
+                };
+                assert_eq!(signed.is_ok(), supported);
+                if let Ok(signed) = signed {
+                    let address = signed.address.to_string();
+                    assert!(verify_message(&address, "Hello World", &signed.signature).unwrap());
+                    assert!(!verify_message(&address, "Hello", &signed.signature).unwrap());
+                }
+            }
+        }
+        let taproot = sign_message(
+            &secret_key,
+            "",
+            AddressType::P2tr,
+            SignatureFormat::Bip322Simple,
+            Network::Bitcoin,
+        )
+        .unwrap();
+        assert_eq!(taproot.address.to_string(), P2TR);
+        assert!(!verify_message(P2WPKH, "", &taproot.signature).unwrap_or_default());
+    }
+}
@@ -0,0 +1,807 @@
+//! MuSig2 participant for Taproot key path multisig, following BIP327 with
+//! the PSBT fields of BIP373.
+//! <https://github.com/bitcoin/bips/blob/master/bip-0327.mediawiki>
+//! <https://github.com/bitcoin/bips/blob/master/bip-0373.mediawiki>
+//!
+//! Signing takes two rounds. Every participant first adds its public nonces
+//! with [`decode_psbt_and_add_musig2_nonces`], then once the PSBT has all of
+//! them adds its partial signature with [`decode_psbt_and_musig2_sign`].
+
+use anyhow::{anyhow, bail, Context, Result};
+use bitcoin::consensus::serialize;
+use bitcoin::hashes::hex::{FromHex, ToHex};
+use bitcoin::hashes::Hash;
+use bitcoin::psbt::{raw, Input, PartiallySignedTransaction};
+use bitcoin::schnorr::TweakedPublicKey;
+use bitcoin::secp256k1::{
+    schnorr, Message, PublicKey, Scalar, Secp256k1, SecretKey, Signing, Verification,
+    XOnlyPublicKey,
+};
+use bitcoin::util::bip32::{DerivationPath, ExtendedPrivKey, Fingerprint};
+use bitcoin::util::sighash::{Prevouts, SighashCache};
+use bitcoin::util::taproot::{TapBranchHash, TapTweakHash};
+use bitcoin::{Network, SchnorrSig, SchnorrSighashType, Script, TxOut};
+use rand::Rng;
+
+use crate::psbt_decoder::decode_psbt;
+use crate::signer::{check_allowed_derivation, check_network, derive_relative_xpriv, input_utxo};
+use crate::storage::MuSig2Storage;
+use crate::utils::base64::to_base64;
+use crate::utils::hash::{reduce, tagged_hash};
+use crate::wallet::Wallet;
+
+const PSBT_IN_MUSIG2_PARTICIPANT_PUBKEYS: u8 = 0x1a;
+const PSBT_IN_MUSIG2_PUB_NONCE: u8 = 0x1b;
+const PSBT_IN_MUSIG2_PARTIAL_SIG: u8 = 0x1c;
+
+/// Scalar modulo the curve order, `None` being zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ModN(Option<SecretKey>);
+
+impl ModN {
+    const ZERO: ModN = ModN(None);
+
+    fn one() -> Self {
+        let mut bytes = [0; 32];
+        bytes[31] = 1;
+        Self::from_bytes(bytes)
+    }
+
+    fn from_bytes(bytes: [u8; 32]) -> Self {
+        ModN(SecretKey::from_slice(&reduce(bytes).to_be_bytes()).ok())
+    }
+
+    fn to_bytes(self) -> [u8; 32] {
+        self.0.map_or([0; 32], |k| k.secret_bytes())
+    }
+
+    fn add(self, other: ModN) -> ModN {
+        match (self.0, other.0) {
+            (None, _) => other,
+            (_, None) => self,
+            (Some(a), Some(b)) => ModN(a.add_tweak(&Scalar::from(b)).ok()),
+        }
+    }
+
+    fn mul(self, other: ModN) -> ModN {
+        match (self.0, other.0) {
+            (Some(a), Some(b)) => ModN(a.mul_tweak(&Scalar::from(b)).ok()),
+            _ => ModN::ZERO,
+        }
+    }
+
+    fn negate_if(self, negate: bool) -> ModN {
+        if negate {
+            ModN(self.0.map(SecretKey::negate))
+        } else {
+            self
+        }
+    }
+
+    /// The point `self·G`.
+    fn point<C: Signing>(self, secp: &Secp256k1<C>) -> Result<PublicKey> {
+        Ok(self.0.context("Point at infinity")?.public_key(secp))
+    }
+}
+
+fn mul_point<C: Verification>(
+    secp: &Secp256k1<C>,
+    point: &PublicKey,
+    scalar: ModN,
+) -> Result<PublicKey> {
+    let scalar = scalar.0.context("Point at infinity")?;
+    Ok(point.mul_tweak(secp, &Scalar::from(scalar))?)
+}
+
+fn has_even_y(point: &PublicKey) -> bool {
+    point.serialize()[0] == 0x02
+}
+
+fn xbytes(point: &PublicKey) -> [u8; 32] {
+    point.x_only_public_key().0.serialize()
+}
+
+fn key_agg_coefficient(pubkeys: &[PublicKey], pubkey: &PublicKey) -> ModN {
+    if pubkeys.iter().find(|p| **p != pubkeys[0]) == Some(pubkey) {
+        return ModN::one();
+    }
+    let list: Vec<u8> = pubkeys.iter().flat_map(PublicKey::serialize).collect();
+    let list_hash = tagged_hash(b"KeyAgg list", &[&list]);
+    ModN::from_bytes(tagged_hash(
+        b"KeyAgg coefficient",
+        &[&list_hash, &pubkey.serialize()],
+    ))
+}
+
+/// Aggregate key of the participants and the tweaks applied to it.
+#[derive(Debug, Clone)]
+pub struct KeyAggContext {
+    pubkeys: Vec<PublicKey>,
+    q: PublicKey,
+    gacc: ModN,
+    tacc: ModN,
+}
+
+impl KeyAggContext {
+    pub fn new(pubkeys: Vec<PublicKey>) -> Result<Self> {
+        if pubkeys.is_empty() {
+            bail!("MuSig2 needs at least one participant");
+        }
+        let secp = Secp256k1::verification_only();
+        let points = pubkeys
+            .iter()
+            .map(|p| mul_point(&secp, p, key_agg_coefficient(&pubkeys, p)))
+            .collect::<Result<Vec<PublicKey>>>()?;
+        let q = PublicKey::combine_keys(&points.iter().collect::<Vec<&PublicKey>>())
+            .context("The MuSig2 aggregate key is infinite")?;
+
+        Ok(Self {
+            pubkeys,
+            q,
+            gacc: ModN::one(),
+            tacc: ModN::ZERO,
+        })
+    }
+
+    /// Aggregate key with the tweaks applied so far.
+    pub fn aggregate_key(&self) -> PublicKey {
+        self.q
+    }
+
+    pub fn output_key(&self) -> XOnlyPublicKey {
+        self.q.x_only_public_key().0
+    }
+
+    pub fn apply_xonly_tweak<C: Verification>(
+        &mut self,
+        secp: &Secp256k1<C>,
+        tweak: Scalar,
+    ) -> Result<()> {
+        let negate = !has_even_y(&self.q);
+        let q = if negate { self.q.negate(secp) } else { self.q };
+        self.q = q.add_exp_tweak(secp, &tweak)?;
+        self.gacc = self.gacc.negate_if(negate);
+        self.tacc = ModN::from_bytes(tweak.to_be_bytes()).add(self.tacc.negate_if(negate));
+        Ok(())
+    }
+
+    /// BIP341 tweak of the aggregate key as internal key.
+    pub fn apply_taproot_tweak<C: Verification>(
+        &mut self,
+        secp: &Secp256k1<C>,
+        merkle_root: Option<TapBranchHash>,
+    ) -> Result<()> {
+        let tweak = TapTweakHash::from_key_and_tweak(self.output_key(), merkle_root);
+        self.apply_xonly_tweak(secp, tweak.to_scalar())
+    }
+}
+
+/// Secret nonces of a session, BIP327 NonceGen.
+fn nonce_gen(
+    randomness: &[u8; 32],
+    secret_key: &SecretKey,
+    pubkey: &PublicKey,
+    output_key: &XOnlyPublicKey,
+    message: &[u8; 32],
+) -> Result<[ModN; 2]> {
+    let aux = tagged_hash(b"MuSig/aux", &[randomness]);
+    let rand: Vec<u8> = secret_key
+        .secret_bytes()
+        .iter()
+        .zip(aux)
+        .map(|(k, a)| k ^ a)
+        .collect();
+    let message_prefixed = [&[1][..], &(message.len() as u64).to_be_bytes(), message].concat();
+    let nonce = |i: u8| {
+        ModN::from_bytes(tagged_hash(
+            b"MuSig/nonce",
+            &[
+                &rand,
+                &[33],
+                &pubkey.serialize(),
+                &[32],
+                &output_key.serialize(),
+                &message_prefixed,
+                &0u32.to_be_bytes(),
+                &[i],
+            ],
+        ))
+    };
+    let nonces = [nonce(0), nonce(1)];
+    if nonces.contains(&ModN::ZERO) {
+        bail!("Invalid MuSig2 nonce");
+    }
+    Ok(nonces)
+}
+
+fn pub_nonce(nonces: &[ModN; 2]) -> Result<Vec<u8>> {
+    let secp = Secp256k1::signing_only();
+    Ok([
+        nonces[0].point(&secp)?.serialize(),
+        nonces[1].point(&secp)?.serialize(),
+    ]
+    .concat())
+}
+
+fn parse_pub_nonce(bytes: &[u8]) -> Result<[PublicKey; 2]> {
+    if bytes.len() != 66 {
+        bail!("Invalid MuSig2 public nonce");
+    }
+    Ok([
+        PublicKey::from_slice(&bytes[..33])?,
+        PublicKey::from_slice(&bytes[33..])?,
+    ])
+}
+
+/// Values shared by every participant once all the nonces are known.
+struct Session {
+    b: ModN,
+    r: PublicKey,
+    e: ModN,
+}
+
+impl Session {
+    fn new<C: Verification + Signing>(
+        secp: &Secp256k1<C>,
+        context: &KeyAggContext,
+        pub_nonces: &[[PublicKey; 2]],
+        message: &[u8; 32],
+    ) -> Result<Self> {
+        // NonceAgg: a component at infinity, `None`, is serialized as zeros
+        let aggregate = |j: usize| {
+            PublicKey::combine_keys(&pub_nonces.iter().map(|n| &n[j]).collect::<Vec<_>>()).ok()
+        };
+        let aggregate_nonce = [aggregate(0), aggregate(1)];
+        let serialized = aggregate_nonce.map(|r| r.map_or([0; 33], |r| r.serialize()));
+        let output_key = context.output_key().serialize();
+
+        let b = ModN::from_bytes(tagged_hash(
+            b"MuSig/noncecoef",
+            &[&serialized[0], &serialized[1], &output_key, message],
+        ));
+        let r2 = match (aggregate_nonce[1], b) {
+            (Some(r2), ModN(Some(_))) => Some(mul_point(secp, &r2, b)?),
+            _ => None,
+        };
+        // the final nonce is G when R1 + b·R2 is infinite
+        let r = match (aggregate_nonce[0], r2) {
+            (Some(r1), Some(r2)) => r1.combine(&r2).ok(),
+            (r1, r2) => r1.or(r2),
+        };
+        let r = match r {
+            Some(r) => r,
+            None => ModN::one().point(secp)?,
+        };
+        let e = ModN::from_bytes(tagged_hash(
+            b"BIP0340/challenge",
+            &[&xbytes(&r), &output_key, message],
+        ));
+        Ok(Self { b, r, e })
+    }
+
+    fn partial_sign<C: Signing>(
+        &self,
+        secp: &Secp256k1<C>,
+        context: &KeyAggContext,
+        nonces: [ModN; 2],
+        secret_key: &SecretKey,
+    ) -> Result<ModN> {
+        let pubkey = secret_key.public_key(secp);
+        if !context.pubkeys.contains(&pubkey) {
+            bail!("The key {pubkey} isn't a MuSig2 participant");
+        }
+        let [k1, k2] = nonces.map(|k| k.negate_if(!has_even_y(&self.r)));
+        let a = key_agg_coefficient(&context.pubkeys, &pubkey);
+        let d = ModN(Some(*secret_key))
+            .mul(context.gacc)
+            .negate_if(!has_even_y(&context.q));
+        Ok(k1.add(self.b.mul(k2)).add(self.e.mul(a).mul(d)))
+    }
+
+    fn aggregate(
+        &self,
+        context: &KeyAggContext,
+        partial_sigs: &[ModN],
+    ) -> Result<schnorr::Signature> {
+        let s = partial_sigs
+            .iter()
+            .fold(ModN::ZERO, |s, p| s.add(*p))
+            .add(self.e.mul(context.tacc).negate_if(!has_even_y(&context.q)));
+        let signature = [xbytes(&self.r), s.to_bytes()].concat();
+        Ok(schnorr::Signature::from_slice(&signature)?)
+    }
+}
+
+/// MuSig2 aggregate keys of an input with their participants.
+fn participants(input: &Input) -> Result<Vec<(PublicKey, Vec<PublicKey>)>> {
+    input
+        .unknown
+        .iter()
+        .filter(|(key, _)| key.type_value == PSBT_IN_MUSIG2_PARTICIPANT_PUBKEYS)
+        .map(|(key, value)| {
+            let aggregate =
+                PublicKey::from_slice(&key.key).context("Invalid MuSig2 aggregate key")?;
+            if value.is_empty() || value.len() % 33 != 0 {
+                bail!("Invalid MuSig2 participants of {aggregate}");
+            }
+            let pubkeys = value
+                .chunks(33)
+                .map(PublicKey::from_slice)
+                .collect::<Result<Vec<PublicKey>, _>>()
+                .with_context(|| format!("Invalid MuSig2 participants of {aggregate}"))?;
+            Ok((aggregate, pubkeys))
+        })
+        .collect()
+}
+
+fn participant_key(type_value: u8, pubkey: &PublicKey, aggregate: &PublicKey) -> raw::Key {
+    raw::Key {
+        type_value,
+        key: [pubkey.serialize(), aggregate.serialize()].concat(),
+    }
+}
+
+/// Public nonces of every participant in the order they are aggregated.
+fn participant_nonces(
+    input: &Input,
+    index: usize,
+    aggregate: &PublicKey,
+    context: &KeyAggContext,
+) -> Result<Vec<[PublicKey; 2]>> {
+    context
+        .pubkeys
+        .iter()
+        .map(|pubkey| {
+            let key = participant_key(PSBT_IN_MUSIG2_PUB_NONCE, pubkey, aggregate);
+            let pub_nonce = input.unknown.get(&key).with_context(|| {
+                format!("Input {index} is missing the MuSig2 nonce of {pubkey}")
+            })?;
+            parse_pub_nonce(pub_nonce)
+        })
+        .collect()
+}
+
+/// Key aggregation context of a key path spend, checked against the output
+/// being spent.
+fn key_path_context<C: Verification>(
+    secp: &Secp256k1<C>,
+    input: &Input,
+    utxo: &TxOut,
+    aggregate: &PublicKey,
+    pubkeys: Vec<PublicKey>,
+) -> Result<KeyAggContext> {
+    let mut context = KeyAggContext::new(pubkeys)?;
+    if context.aggregate_key() != *aggregate {
+        bail!("The MuSig2 participants don't aggregate to {aggregate}");
+    }
+    context.apply_taproot_tweak(secp, input.tap_merkle_root)?;
+    let output_key = TweakedPublicKey::dangerous_assume_tweaked(context.output_key());
+    if utxo.script_pubkey != Script::new_v1_p2tr_tweaked(output_key) {
+        bail!("The MuSig2 aggregate key {aggregate} doesn't match the spent output");
+    }
+    Ok(context)
+}
+
+fn key_path_sighash(
+    psbt: &PartiallySignedTransaction,
+    index: usize,
+    utxos: &[TxOut],
+) -> Result<(SchnorrSighashType, [u8; 32])> {
+    let sighash_type = psbt.inputs[index].schnorr_hash_ty()?;
+    let sighash = SighashCache::new(&psbt.unsigned_tx).taproot_key_spend_signature_hash(
+        index,
+        &Prevouts::All(utxos),
+        sighash_type,
+    )?;
+    Ok((sighash_type, sighash.into_inner()))
+}
+
+/// An input key path this wallet takes part in.
+struct Participation {
+    index: usize,
+    aggregate: PublicKey,
+    context: KeyAggContext,
+    pubkey: PublicKey,
+    secret_key: SecretKey,
+    message: [u8; 32],
+}
+
+fn participations(
+    psbt: &PartiallySignedTransaction,
+    xprv: &ExtendedPrivKey,
+    master_fingerprint: Fingerprint,
+    derivation: &DerivationPath,
+    allow_any_sighash: bool,
+) -> Result<Vec<Participation>> {
+    let secp = Secp256k1::new();
+    let utxos = (0..psbt.inputs.len())
+        .map(|index| input_utxo(psbt, index))
+        .collect::<Result<Vec<TxOut>>>()?;
+    let mut participations = Vec::new();
+
+    for (index, input) in psbt.inputs.iter().enumerate() {
+        for (aggregate, pubkeys) in participants(input)? {
+            let mut keys = Vec::new();
+            for pubkey in &pubkeys {
+                let Some((_, path)) = input
+                    .bip32_derivation
+                    .get(pubkey)
+                    .filter(|(fingerprint, _)| *fingerprint == master_fingerprint)
+                else {
+                    continue;
+                };
+                check_allowed_derivation(path)?;
+                let secret_key = derive_relative_xpriv(xprv, &secp, derivation, path)?.private_key;
+                if secret_key.public_key(&secp) != *pubkey {
+                    bail!("The key {pubkey} of input {index} doesn't derive from this wallet");
+                }
+                keys.push((*pubkey, secret_key));
+            }
+            if keys.is_empty() {
+                continue;
+            }
+
+            let context = key_path_context(&secp, input, &utxos[index], &aggregate, pubkeys)?;
+            let (sighash_type, message) = key_path_sighash(psbt, index, &utxos)?;
+            if !matches!(
+                sighash_type,
+                SchnorrSighashType::Default | SchnorrSighashType::All
+            ) && !allow_any_sighash
+            {
+                bail!("Input {index} requests {sighash_type}, only SIGHASH_ALL is allowed for this wallet");
+            }
+            for (pubkey, secret_key) in keys {
+                participations.push(Participation {
+                    index,
+                    aggregate,
+                    context: context.clone(),
+                    pubkey,
+                    secret_key,
+                    message,
+                });
+            }
+        }
+    }
+
+    if participations.is_empty() {
+        bail!("No MuSig2 keys of this wallet in this psbt");
+    }
+    Ok(participations)
+}
+
+fn add_nonces(
+    psbt: &mut PartiallySignedTransaction,
+    participations: &[Participation],
+    storage: &mut MuSig2Storage,
+) -> Result<()> {
+    for p in participations {
+        let randomness: [u8; 32] = rand::thread_rng().gen();
+        let nonces = nonce_gen(
+            &randomness,
+            &p.secret_key,
+            &p.pubkey,
+            &p.context.output_key(),
+            &p.message,
+        )?;
+        let pub_nonce = pub_nonce(&nonces)?;
+        storage.insert_session(&pub_nonce.to_hex(), &randomness.to_hex());
+
+        let key = participant_key(PSBT_IN_MUSIG2_PUB_NONCE, &p.pubkey, &p.aggregate);
+        if let Some(previous) = psbt.inputs[p.index].unknown.insert(key, pub_nonce) {
+            storage.take_session(&previous.to_hex());
+        }
+    }
+    storage.save()
+}
+
+fn sign_musig2(
+    psbt: &mut PartiallySignedTransaction,
+    participations: &[Participation],
+    storage: &mut MuSig2Storage,
+) -> Result<()> {
+    let secp = Secp256k1::new();
+    let all_nonces = participations
+        .iter()
+        .map(|p| participant_nonces(&psbt.inputs[p.index], p.index, &p.aggregate, &p.context))
+        .collect::<Result<Vec<_>>>()?;
+
+    // Sessions are removed and saved before producing any partial signature,
+    // so a nonce is never used twice.
+    let mut sessions = Vec::new();
+    for p in participations {
+        let key = participant_key(PSBT_IN_MUSIG2_PUB_NONCE, &p.pubkey, &p.aggregate);
+        let pub_nonce = &psbt.inputs[p.index].unknown[&key];
+        let randomness = storage.take_session(&pub_nonce.to_hex()).with_context(|| {
+            format!(
+                "No MuSig2 session for the nonce of input {}, nonces must be added again",
+                p.index
+            )
+        })?;
+        sessions.push((pub_nonce.clone(), randomness));
+    }
+    storage.save()?;
+
+    for ((p, pub_nonces), (stored_nonce, randomness)) in
+        participations.iter().zip(all_nonces).zip(sessions)
+    {
+        let randomness: [u8; 32] = Vec::from_hex(&randomness)?
+            .try_into()
+            .map_err(|_| anyhow!("Invalid MuSig2 session"))?;
+        let nonces = nonce_gen(
+            &randomness,
+            &p.secret_key,
+            &p.pubkey,
+            &p.context.output_key(),
+            &p.message,
+        )?;
+        if pub_nonce(&nonces)? != stored_nonce {
+            bail!(
+                "The MuSig2 nonce of input {} doesn't match its session",
+                p.index
+            );
+        }
+
+        let session = Session::new(&secp, &p.context, &pub_nonces, &p.message)?;
+        let partial_sig = session.partial_sign(&secp, &p.context, nonces, &p.secret_key)?;
+        psbt.inputs[p.index].unknown.insert(
+            participant_key(PSBT_IN_MUSIG2_PARTIAL_SIG, &p.pubkey, &p.aggregate),
+            partial_sig.to_bytes().to_vec(),
+        );
+    }
+    Ok(())
+}
+
+/// Aggregates the partial signatures of the inputs having all of them into
+/// their key path signature, returning how many inputs were completed.
+pub fn aggregate_partial_signatures(psbt: &mut PartiallySignedTransaction) -> Result<usize> {
+    let secp = Secp256k1::new();
+    let utxos = (0..psbt.inputs.len())
+        .map(|index| input_utxo(psbt, index))
+        .collect::<Result<Vec<TxOut>>>()?;
+    let mut completed = 0;
+
+    for index in 0..psbt.inputs.len() {
+        let input = &psbt.inputs[index];
+        for (aggregate, pubkeys) in participants(input)? {
+            let context = key_path_context(&secp, input, &utxos[index], &aggregate, pubkeys)?;
+            let partial_sigs: Option<Vec<ModN>> = context
+                .pubkeys
+                .iter()
+                .map(|pubkey| {
+                    let key = participant_key(PSBT_IN_MUSIG2_PARTIAL_SIG, pubkey, &aggregate);
+                    let bytes: [u8; 32] = input.unknown.get(&key)?.as_slice().try_into().ok()?;
+                    Some(ModN::from_bytes(bytes))
+                })
+                .collect();
+            let Some(partial_sigs) = partial_sigs else {
+                continue;
+            };
+
+            let pub_nonces = participant_nonces(input, index, &aggregate, &context)?;
+            let (hash_ty, message) = key_path_sighash(psbt, index, &utxos)?;
+            let session = Session::new(&secp, &context, &pub_nonces, &message)?;
+            let sig = session.aggregate(&context, &partial_sigs)?;
+            secp.verify_schnorr(&sig, &Message::from_slice(&message)?, &context.output_key())
+                .with_context(|| format!("Invalid MuSig2 signature for input {index}"))?;
+
+            psbt.inputs[index].tap_key_sig = Some(SchnorrSig { sig, hash_ty });
+            completed += 1;
+            break;
+        }
+    }
+    Ok(completed)
+}
+
+fn decode_psbt_and_participations(
+    psbt: &[u8],
+    wallet: &mut Wallet,
+    password: &str,
+    network: Network,
+) -> Result<(PartiallySignedTransaction, Vec<Participation>)> {
+    let psbt = decode_psbt(psbt)?;
+    let xprv = wallet.get_xprv(password, network)?;
+    check_network(&psbt, xprv.network)?;
+    let master_fingerprint = wallet.get_master_fingerprint(&xprv)?;
+    let participations = participations(
+        &psbt,
+        &xprv,
+        master_fingerprint,
+        &wallet.derivation,
+        wallet.allow_any_sighash,
+    )?;
+    Ok((psbt, participations))
+}
+
+/// First round, adds this wallet's public nonces to its MuSig2 inputs.
+pub fn decode_psbt_and_add_musig2_nonces(
+    psbt: &[u8],
+    wallet: &mut Wallet,
+    password: &str,
+    network: Network,
+    storage: &mut MuSig2Storage,
+) -> Result<String> {
+    let (mut psbt, participations) =
+        decode_psbt_and_participations(psbt, wallet, password, network)?;
+    add_nonces(&mut psbt, &participations, storage)?;
+    Ok(to_base64(&serialize(&psbt)))
+}
+
+/// Second round, adds this wallet's partial signatures once the PSBT has the
+/// nonces of every participant.
+pub fn decode_psbt_and_musig2_sign(
+    psbt: &[u8],
+    wallet: &mut Wallet,
+    password: &str,
+    network: Network,
+    storage: &mut MuSig2Storage,
+) -> Result<String> {
+    let (mut psbt, participations) =
+        decode_psbt_and_participations(psbt, wallet, password, network)?;
+    sign_musig2(&mut psbt, &participations, storage)?;
+    Ok(to_base64(&serialize(&psbt)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        add_nonces, aggregate_partial_signatures, mul_point, participations, sign_musig2,
+        KeyAggContext, ModN, Session, PSBT_IN_MUSIG2_PARTICIPANT_PUBKEYS,
+    };
+    use crate::storage::{MuSig2Storage, Store};
+    use crate::utils::hash::tagged_hash;
+    use anyhow::{anyhow, Result};
+    use bitcoin::psbt::{raw, PartiallySignedTransaction};
+    use bitcoin::schnorr::TweakedPublicKey;
+    use bitcoin::secp256k1::{PublicKey, Secp256k1, SecretKey};
+    use bitcoin::util::bip32::{DerivationPath, ExtendedPrivKey};
+    use bitcoin::{
+        Network, OutPoint, PackedLockTime, Script, Sequence, Transaction, TxIn, TxOut, Witness,
+    };
+    use std::str::FromStr;
+
+    struct NoStore;
+
+    impl Store for NoStore {
+        fn get_item(&self, _key: &str) -> Result<String> {
+            Err(anyhow!("Not found"))
+        }
+
+        fn set_item(&self, _key: &str, _data: &str) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn aggregates_keys() {
+        // BIP327 key_agg_vectors
+        let pubkeys = [
+            "02F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9",
+            "03DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659",
+            "023590A94E768F8E1815C2F24B4D80A8E3149316C3518CE7B7AD338368D038CA66",
+        ]
+        .map(|p| PublicKey::from_str(p).unwrap());
+        let context = KeyAggContext::new(pubkeys.to_vec()).unwrap();
+        assert_eq!(
+            context.output_key().to_string(),
+            "90539eede565f5d054f32cc0c220126889ed1e5d193baf15aef344fe59d4610c"
+        );
+    }
+
+    #[test]
+    fn aggregates_infinite_nonces() {
+        let secp = Secp256k1::new();
+        let point = |seed: u8| {
+            SecretKey::from_slice(&[seed; 32])
+                .unwrap()
+                .public_key(&secp)
+        };
+        let context = KeyAggContext::new(vec![point(1), point(2)]).unwrap();
+        let message = [7; 32];
+        let generator = ModN::one().point(&secp).unwrap();
+
+        // both components of the aggregate nonce cancel out, the final nonce is G
+        let pub_nonces = [
+            [point(3), point(4)],
+            [point(3).negate(&secp), point(4).negate(&secp)],
+        ];
+        let session = Session::new(&secp, &context, &pub_nonces, &message).unwrap();
+        assert_eq!(session.r, generator);
+        let b = ModN::from_bytes(tagged_hash(
+            b"MuSig/noncecoef",
+            &[
+                &[0; 33],
+                &[0; 33],
+                &context.output_key().serialize(),
+                &message,
+            ],
+        ));
+        assert_eq!(session.b, b);
+
+        // only the first component cancels out, the final nonce is b·R2
+        let pub_nonces = [[point(3), point(4)], [point(3).negate(&secp), point(5)]];
+        let session = Session::new(&secp, &context, &pub_nonces, &message).unwrap();
+        let r2 = point(4).combine(&point(5)).unwrap();
+        assert_eq!(session.r, mul_point(&secp, &r2, session.b).unwrap());
+    }
+
+    #[test]
+    fn signs_key_path() {
+        let secp = Secp256k1::new();
+        let path = DerivationPath::from_str("m/86'/1'/0'/0/0").unwrap();
+        let signers: Vec<(ExtendedPrivKey, PublicKey)> = (1..=3)
+            .map(|seed| {
+                let xprv = ExtendedPrivKey::new_master(Network::Regtest, &[seed; 32]).unwrap();
+                let key = xprv.derive_priv(&secp, &path).unwrap().private_key;
+                (xprv, key.public_key(&secp))
+            })
+            .collect();
+        let pubkeys: Vec<PublicKey> = signers.iter().map(|(_, p)| *p).collect();
+        let mut context = KeyAggContext::new(pubkeys.clone()).unwrap();
+        let aggregate = context.aggregate_key();
+        context.apply_taproot_tweak(&secp, None).unwrap();
+
+        let tx = Transaction {
+            version: 2,
+            lock_time: PackedLockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::default(),
+                script_sig: Script::new(),
+                sequence: Sequence::MAX,
+                witness: Witness::default(),
+            }],
+            output: vec![TxOut {
+                value: 90_000,
+                script_pubkey: Script::new_op_return(&[]),
+            }],
+        };
+        let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx).unwrap();
+        psbt.inputs[0].witness_utxo = Some(TxOut {
+            value: 100_000,
+            script_pubkey: Script::new_v1_p2tr_tweaked(TweakedPublicKey::dangerous_assume_tweaked(
+                context.output_key(),
+            )),
+        });
+        for (xprv, pubkey) in &signers {
+            psbt.inputs[0]
+                .bip32_derivation
+                .insert(*pubkey, (xprv.fingerprint(&secp), path.clone()));
+        }
+        psbt.inputs[0].unknown.insert(
+            raw::Key {
+                type_value: PSBT_IN_MUSIG2_PARTICIPANT_PUBKEYS,
+                key: aggregate.serialize().to_vec(),
+            },
+            pubkeys.iter().flat_map(PublicKey::serialize).collect(),
+        );
+
+        let master = DerivationPath::master();
+        let mut storages: Vec<MuSig2Storage> = signers
+            .iter()
+            .map(|_| MuSig2Storage::read(NoStore))
+            .collect();
+        for ((xprv, _), storage) in signers.iter().zip(&mut storages) {
+            let p = participations(&psbt, xprv, xprv.fingerprint(&secp), &master, false).unwrap();
+            add_nonces(&mut psbt, &p, storage).unwrap();
+        }
+        assert_eq!(aggregate_partial_signatures(&mut psbt).unwrap(), 0);
+        for ((xprv, _), storage) in signers.iter().zip(&mut storages) {
+            let p = participations(&psbt, xprv, xprv.fingerprint(&secp), &master, false).unwrap();
+            sign_musig2(&mut psbt, &p, storage).unwrap();
+            // the nonce session is gone after signing
+            assert!(sign_musig2(&mut psbt, &p, storage).is_err());
+        }
+
+        assert_eq!(aggregate_partial_signatures(&mut psbt).unwrap(), 1);
+        assert!(psbt.inputs[0].tap_key_sig.is_some());
+
+        let unrelated = ExtendedPrivKey::new_master(Network::Regtest, &[9; 32]).unwrap();
+        assert!(participations(
+            &psbt,
+            &unrelated,
+            unrelated.fingerprint(&secp),
+            &master,
+            false
+        )
+        .is_err());
+    }
+}
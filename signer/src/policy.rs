@@ -0,0 +1,435 @@
+//! Spending conditions of miniscript inputs, so the user can see which keys and
+//! timelocks each spending path needs before signing.
+
+use anyhow::Result;
+use bitcoin::hashes::hex::ToHex;
+use bitcoin::psbt::{Input, PartiallySignedTransaction};
+use bitcoin::secp256k1::XOnlyPublicKey;
+use bitcoin::util::bip32::Fingerprint;
+use bitcoin::{LockTime, Sequence};
+use miniscript::policy::{Liftable, Semantic};
+use miniscript::{Miniscript, MiniscriptKey, Segwitv0, Tap};
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::psbt_decoder::decode_psbt;
+
+/// The policy with each key replaced by its label.
+fn relabel<Pk: MiniscriptKey>(
+    policy: &Semantic<Pk>,
+    label: &mut dyn FnMut(&Pk) -> String,
+) -> Semantic<String> {
+    match policy {
+        Semantic::Unsatisfiable => Semantic::Unsatisfiable,
+        Semantic::Trivial => Semantic::Trivial,
+        Semantic::Key(pk) => Semantic::Key(label(pk)),
+        Semantic::After(t) => Semantic::After(*t),
+        Semantic::Older(t) => Semantic::Older(*t),
+        Semantic::Sha256(h) => Semantic::Sha256(h.to_string()),
+        Semantic::Hash256(h) => Semantic::Hash256(h.to_string()),
+        Semantic::Ripemd160(h) => Semantic::Ripemd160(h.to_string()),
+        Semantic::Hash160(h) => Semantic::Hash160(h.to_string()),
+        Semantic::Threshold(k, subs) => {
+            Semantic::Threshold(*k, subs.iter().map(|s| relabel(s, label)).collect())
+        }
+    }
+}
+
+/// The policy once `keys` have signed.
+fn signed_by(policy: Semantic<String>, keys: &BTreeSet<String>) -> Semantic<String> {
+    match policy {
+        Semantic::Key(key) if keys.contains(&key) => Semantic::Trivial,
+        Semantic::Threshold(k, subs) => {
+            Semantic::Threshold(k, subs.into_iter().map(|s| signed_by(s, keys)).collect())
+        }
+        policy => policy,
+    }
+}
+
+/// Master fingerprint of a key, or the start of the key when unknown.
+fn key_label(origin: Option<&Fingerprint>, key: &[u8]) -> String {
+    origin.map_or_else(|| key[..4].to_hex(), ToString::to_string)
+}
+
+/// Labels of the keys from `own_fingerprints` and of the keys that already
+/// signed the input.
+#[derive(Default)]
+struct KeyLabels {
+    own: BTreeSet<String>,
+    signed: BTreeSet<String>,
+}
+
+impl KeyLabels {
+    fn insert(&mut self, label: &str, own: bool, signed: bool) {
+        if own {
+            self.own.insert(label.to_string());
+        }
+        if signed {
+            self.signed.insert(label.to_string());
+        }
+    }
+}
+
+/// Policy of the `witness_script` or of the taproot key and leaves.
+fn input_semantic(
+    input: &Input,
+    own_fingerprints: &BTreeSet<Fingerprint>,
+) -> Result<Option<(Semantic<String>, KeyLabels)>> {
+    let mut labels = KeyLabels::default();
+
+    let policy = if let Some(witness_script) = &input.witness_script {
+        let mut label = |pk: &bitcoin::PublicKey| {
+            let origin = input.bip32_derivation.get(&pk.inner).map(|(f, _)| f);
+            let label = key_label(origin, &pk.inner.serialize()[1..]);
+            let own = origin.is_some_and(|f| own_fingerprints.contains(f));
+            labels.insert(&label, own, input.partial_sigs.contains_key(pk));
+            label
+        };
+        let lifted =
+            Miniscript::<bitcoin::PublicKey, Segwitv0>::parse_insane(witness_script)?.lift()?;
+        relabel(&lifted, &mut label)
+    } else if input.tap_internal_key.is_some() || !input.tap_scripts.is_empty() {
+        let mut label = |pk: &XOnlyPublicKey| {
+            let origin = input.tap_key_origins.get(pk).map(|(_, (f, _))| f);
+            let label = key_label(origin, &pk.serialize());
+            let own = origin.is_some_and(|f| own_fingerprints.contains(f));
+            let signed = input.tap_script_sigs.keys().any(|(key, _)| key == pk)
+                || (input.tap_key_sig.is_some() && input.tap_internal_key.as_ref() == Some(pk));
+            labels.insert(&label, own, signed);
+            label
+        };
+        let mut branches: Vec<Semantic<String>> = input
+            .tap_internal_key
+            .iter()
+            .map(|key| Semantic::Key(label(key)))
+            .collect();
+        for (script, _) in input.tap_scripts.values() {
+            let lifted = Miniscript::<XOnlyPublicKey, Tap>::parse_insane(script)?.lift()?;
+            branches.push(relabel(&lifted, &mut label));
+        }
+        Semantic::Threshold(1, branches)
+    } else {
+        return Ok(None);
+    };
+
+    Ok(Some((policy.normalized(), labels)))
+}
+
+/// Short description of the policy as "2-of-3: fp1 (you), fp2, fp3" for
+/// multisig, the full policy otherwise.
+fn summary(policy: &Semantic<String>, own: &BTreeSet<String>) -> String {
+    let label = |key: &String| {
+        if own.contains(key) {
+            format!("{key} (you)")
+        } else {
+            key.clone()
+        }
+    };
+    match policy {
+        Semantic::Key(key) => format!("1-of-1: {}", label(key)),
+        Semantic::Threshold(k, subs) => {
+            let keys: Vec<String> = subs
+                .iter()
+                .filter_map(|sub| match sub {
+                    Semantic::Key(key) => Some(label(key)),
+                    _ => None,
+                })
+                .collect();
+            if keys.len() == subs.len() {
+                format!("{k}-of-{}: {}", keys.len(), keys.join(", "))
+            } else {
+                policy.to_string()
+            }
+        }
+        _ => policy.to_string(),
+    }
+}
+
+/// Signatures and timelocks of one way to spend an input.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SpendingPath {
+    /// `older` value needed, in blocks or BIP68 time units.
+    pub relative_timelock: Option<u32>,
+    /// `after` value needed, a block height or a timestamp.
+    pub absolute_timelock: Option<u32>,
+    pub signatures: usize,
+    /// Signatures still needed once this wallet has signed.
+    pub other_signatures: usize,
+    /// Whether the nLockTime and nSequence of this transaction allow it.
+    pub enabled: bool,
+}
+
+impl fmt::Display for SpendingPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let plural = if self.signatures == 1 { "" } else { "s" };
+        write!(f, "{} signature{plural}", self.signatures)?;
+        if self.other_signatures < self.signatures {
+            write!(f, " ({} besides this wallet)", self.other_signatures)?;
+        }
+        if let Some(relative) = self.relative_timelock {
+            let sequence = Sequence(relative);
+            if sequence.is_time_locked() {
+                write!(
+                    f,
+                    ", {} seconds after confirmation",
+                    (relative & 0xffff) * 512
+                )?;
+            } else {
+                write!(f, ", {} blocks after confirmation", relative & 0xffff)?;
+            }
+        }
+        match self.absolute_timelock.map(LockTime::from_consensus) {
+            Some(LockTime::Blocks(height)) => write!(f, ", from block {height}")?,
+            Some(LockTime::Seconds(time)) => write!(f, ", from timestamp {time}")?,
+            None => {}
+        }
+        if !self.enabled {
+            write!(f, " (not enabled by this transaction)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Spending conditions of a PSBT input.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct InputPolicy {
+    pub input: usize,
+    /// Policy with keys shown by their master fingerprint.
+    pub policy: String,
+    /// Multisig as "2-of-3: fp1 (you), fp2, fp3", the policy otherwise.
+    pub summary: String,
+    /// Keys with a signature in the PSBT.
+    pub signed: Vec<String>,
+    /// Signatures still missing under the timelocks of this transaction,
+    /// `None` when it can't be spent with them.
+    pub remaining_signatures: Option<usize>,
+    pub paths: Vec<SpendingPath>,
+}
+
+/// Paths with fewer signatures as more timelocks expire, skipping those no
+/// better than a path with less timelocks.
+fn spending_paths(
+    policy: &Semantic<String>,
+    own: &BTreeSet<String>,
+    sequence: Sequence,
+    lock_time: LockTime,
+) -> Vec<SpendingPath> {
+    let relative: Vec<Option<u32>> = std::iter::once(None)
+        .chain(policy.relative_timelocks().into_iter().map(Some))
+        .collect();
+    let absolute: Vec<Option<u32>> = std::iter::once(None)
+        .chain(policy.absolute_timelocks().into_iter().map(Some))
+        .collect();
+    let mut paths: Vec<SpendingPath> = Vec::new();
+
+    for relative_timelock in &relative {
+        for absolute_timelock in &absolute {
+            let stage = policy
+                .clone()
+                .at_age(Sequence(relative_timelock.unwrap_or(0)))
+                .at_lock_time(LockTime::from_consensus(absolute_timelock.unwrap_or(0)));
+            let Some(signatures) = stage.minimum_n_keys() else {
+                continue;
+            };
+            if paths.iter().any(|p| {
+                p.relative_timelock <= *relative_timelock
+                    && p.absolute_timelock <= *absolute_timelock
+                    && p.signatures <= signatures
+            }) {
+                continue;
+            }
+
+            let other_signatures = signed_by(stage, own).minimum_n_keys().unwrap_or(signatures);
+            let enabled = relative_timelock.is_none_or(|t| {
+                !Semantic::<String>::older(t)
+                    .at_age(sequence)
+                    .is_unsatisfiable()
+            }) && absolute_timelock.is_none_or(|t| {
+                !Semantic::<String>::after(t)
+                    .at_lock_time(lock_time)
+                    .is_unsatisfiable()
+            });
+            paths.push(SpendingPath {
+                relative_timelock: *relative_timelock,
+                absolute_timelock: *absolute_timelock,
+                signatures,
+                other_signatures,
+                enabled,
+            });
+        }
+    }
+    paths
+}
+
+/// Spending paths of the miniscript inputs, counting the keys of every master
+/// fingerprint in `own_fingerprints` as ours.
+pub fn input_policies(
+    psbt: &PartiallySignedTransaction,
+    own_fingerprints: &BTreeSet<Fingerprint>,
+) -> Vec<InputPolicy> {
+    let tx = &psbt.unsigned_tx;
+    let lock_time = if tx
+        .input
+        .iter()
+        .any(|i| i.sequence.enables_absolute_lock_time())
+    {
+        LockTime::from(tx.lock_time)
+    } else {
+        LockTime::ZERO
+    };
+
+    psbt.inputs
+        .iter()
+        .enumerate()
+        .filter_map(|(index, input)| {
+            let (policy, labels) = input_semantic(input, own_fingerprints).ok()??;
+            let sequence = tx.input[index].sequence;
+            let sequence = if tx.version >= 2 && sequence.is_relative_lock_time() {
+                Sequence(sequence.0 & 0x0040_ffff)
+            } else {
+                Sequence::ZERO
+            };
+            let remaining_signatures = signed_by(
+                policy.clone().at_age(sequence).at_lock_time(lock_time),
+                &labels.signed,
+            )
+            .minimum_n_keys();
+            Some(InputPolicy {
+                input: index,
+                policy: policy.to_string(),
+                summary: summary(&policy, &labels.own),
+                signed: labels.signed.iter().cloned().collect(),
+                remaining_signatures,
+                paths: spending_paths(&policy, &labels.own, sequence, lock_time),
+            })
+        })
+        .collect()
+}
+
+pub fn decode_psbt_policies(
+    psbt: &str,
+    own_fingerprints: &BTreeSet<Fingerprint>,
+) -> Result<Vec<InputPolicy>> {
+    Ok(input_policies(
+        &decode_psbt(psbt.as_bytes())?,
+        own_fingerprints,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::input_policies;
+    use bitcoin::psbt::PartiallySignedTransaction;
+    use bitcoin::secp256k1::{Message, PublicKey, Secp256k1, SecretKey};
+    use bitcoin::util::bip32::{DerivationPath, Fingerprint};
+    use bitcoin::{EcdsaSig, OutPoint, PackedLockTime, Script, Sequence, Transaction, TxIn, TxOut};
+    use std::collections::BTreeSet;
+    use std::str::FromStr;
+
+    fn pubkey(seed: u8) -> PublicKey {
+        SecretKey::from_slice(&[seed; 32])
+            .unwrap()
+            .public_key(&Secp256k1::new())
+    }
+
+    /// A 2 of 3 that decays to 1 of 3 a year after confirmation.
+    const DECAYING_MULTISIG: &str =
+        "or_d(multi(2,{a},{b},{c}),and_v(v:older(52560),multi(1,{a},{b},{c})))";
+
+    fn script(miniscript: &str) -> Script {
+        let miniscript = miniscript
+            .replace("{a}", &pubkey(1).to_string())
+            .replace("{b}", &pubkey(2).to_string())
+            .replace("{c}", &pubkey(3).to_string());
+        miniscript::Miniscript::<bitcoin::PublicKey, miniscript::Segwitv0>::from_str_insane(
+            &miniscript,
+        )
+        .unwrap()
+        .encode()
+    }
+
+    fn psbt(witness_script: Script, sequence: u32) -> PartiallySignedTransaction {
+        let tx = Transaction {
+            version: 2,
+            lock_time: PackedLockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::default(),
+                script_sig: Script::new(),
+                sequence: Sequence(sequence),
+                witness: Default::default(),
+            }],
+            output: vec![TxOut {
+                value: 1000,
+                script_pubkey: Script::new(),
+            }],
+        };
+        let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx).unwrap();
+        psbt.inputs[0].witness_utxo = Some(TxOut {
+            value: 2000,
+            script_pubkey: witness_script.to_v0_p2wsh(),
+        });
+        psbt.inputs[0].witness_script = Some(witness_script);
+        for (seed, fingerprint) in [(1, "aaaaaaaa"), (2, "bbbbbbbb"), (3, "cccccccc")] {
+            psbt.inputs[0].bip32_derivation.insert(
+                pubkey(seed),
+                (
+                    Fingerprint::from_str(fingerprint).unwrap(),
+                    DerivationPath::from_str("m/48'/1'/0'/2'/0/0").unwrap(),
+                ),
+            );
+        }
+        psbt
+    }
+
+    #[test]
+    fn finds_spending_paths() {
+        let own = BTreeSet::from([Fingerprint::from_str("bbbbbbbb").unwrap()]);
+        let policies = input_policies(&psbt(script(DECAYING_MULTISIG), 0xffff_fffe), &own);
+        assert_eq!(policies.len(), 1);
+        assert!(policies[0].policy.contains("pk(aaaaaaaa)"));
+
+        let paths = &policies[0].paths;
+        assert_eq!(paths.len(), 2);
+        assert_eq!((paths[0].signatures, paths[0].other_signatures), (2, 1));
+        assert!(paths[0].enabled);
+        assert_eq!(paths[1].relative_timelock, Some(52560));
+        assert_eq!((paths[1].signatures, paths[1].other_signatures), (1, 0));
+        assert!(!paths[1].enabled);
+        assert_eq!(
+            paths[1].to_string(),
+            "1 signature (0 besides this wallet), 52560 blocks after confirmation (not enabled by this transaction)"
+        );
+
+        let recovery = input_policies(&psbt(script(DECAYING_MULTISIG), 52560), &BTreeSet::new());
+        assert!(recovery[0].paths[1].enabled);
+        assert_eq!(recovery[0].paths[0].to_string(), "2 signatures");
+    }
+
+    #[test]
+    fn summarizes_signing_round() {
+        let mut own = BTreeSet::from([Fingerprint::from_str("bbbbbbbb").unwrap()]);
+        let mut psbt = psbt(script("multi(2,{a},{b},{c})"), 0xffff_ffff);
+        let policies = input_policies(&psbt, &own);
+        assert_eq!(
+            policies[0].summary,
+            "2-of-3: aaaaaaaa, bbbbbbbb (you), cccccccc"
+        );
+        own.insert(Fingerprint::from_str("aaaaaaaa").unwrap());
+        assert_eq!(
+            input_policies(&psbt, &own)[0].summary,
+            "2-of-3: aaaaaaaa (you), bbbbbbbb (you), cccccccc"
+        );
+        assert!(policies[0].signed.is_empty());
+        assert_eq!(policies[0].remaining_signatures, Some(2));
+
+        let message = Message::from_slice(&[1; 32]).unwrap();
+        let sig = Secp256k1::new().sign_ecdsa(&message, &SecretKey::from_slice(&[3; 32]).unwrap());
+        psbt.inputs[0].partial_sigs.insert(
+            bitcoin::PublicKey::new(pubkey(3)),
+            EcdsaSig::sighash_all(sig),
+        );
+        let policies = input_policies(&psbt, &own);
+        assert_eq!(policies[0].signed, vec!["cccccccc".to_string()]);
+        assert_eq!(policies[0].remaining_signatures, Some(1));
+    }
+}
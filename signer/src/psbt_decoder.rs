@@ -0,0 +1,151 @@
+use anyhow::{anyhow, bail, Result};
+use bitcoin::consensus::deserialize;
+use bitcoin::hashes::hex::FromHex;
+use bitcoin::psbt::PartiallySignedTransaction;
+use std::fmt;
+
+use crate::utils::base43::from_base43;
+use crate::utils::base64::from_base64;
+use crate::utils::ur::{decode_cbor_bytes, URDecoder};
+
+const PSBT_MAGIC: &[u8] = b"psbt\xff";
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PSBTEncoding {
+    Binary,
+    Hex,
+    Base64,
+    Base43,
+    UR,
+}
+
+impl fmt::Display for PSBTEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PSBTEncoding::Binary => write!(f, "binary"),
+            PSBTEncoding::Hex => write!(f, "hex"),
+            PSBTEncoding::Base64 => write!(f, "base64"),
+            PSBTEncoding::Base43 => write!(f, "base43"),
+            PSBTEncoding::UR => write!(f, "ur:crypto-psbt"),
+        }
+    }
+}
+
+fn deserialize_psbt(bytes: &[u8]) -> Result<PartiallySignedTransaction> {
+    deserialize(bytes).map_err(|e| anyhow!("Invalid PSBT: {e}"))
+}
+
+fn decode_ur(parts: &[&str]) -> Result<Vec<u8>> {
+    let mut decoder = URDecoder::default();
+    for part in parts {
+        decoder.receive(part)?;
+    }
+
+    match decoder.ur_type() {
+        Some("crypto-psbt") | Some("psbt") => {}
+        Some(t) => bail!("Unsupported UR type {t}"),
+        None => bail!("Empty UR"),
+    }
+
+    match decoder.message() {
+        Some(message) => decode_cbor_bytes(message),
+        None => {
+            let (received, expected) = decoder.progress();
+            bail!("Incomplete UR: {received} of {expected} parts recovered, scan more parts")
+        }
+    }
+}
+
+/// Decodes a PSBT from any of the encodings used by common coordinators:
+/// raw binary, hex, base64, Electrum's base43 or single/multi-part
+/// `ur:crypto-psbt`. Multi-part URs are expected to be whitespace separated.
+pub fn decode_psbt_with_encoding(
+    data: &[u8],
+) -> Result<(PartiallySignedTransaction, PSBTEncoding)> {
+    if data.starts_with(PSBT_MAGIC) {
+        return Ok((deserialize_psbt(data)?, PSBTEncoding::Binary));
+    }
+
+    let text = std::str::from_utf8(data)
+        .map_err(|_| anyhow!("PSBT is neither binary nor text"))?
+        .trim();
+    if text.is_empty() {
+        bail!("Empty PSBT");
+    }
+
+    let parts: Vec<&str> = text.split_whitespace().collect();
+    if parts[0].to_ascii_lowercase().starts_with("ur:") {
+        return Ok((deserialize_psbt(&decode_ur(&parts)?)?, PSBTEncoding::UR));
+    }
+
+    let text = parts.concat();
+    let candidates: [(PSBTEncoding, Option<Vec<u8>>); 3] = [
+        (PSBTEncoding::Hex, Vec::<u8>::from_hex(&text).ok()),
+        (PSBTEncoding::Base64, from_base64(&text).ok()),
+        (PSBTEncoding::Base43, from_base43(&text).ok()),
+    ];
+
+    for (encoding, bytes) in candidates {
+        match bytes {
+            Some(bytes) if bytes.starts_with(PSBT_MAGIC) => {
+                return Ok((deserialize_psbt(&bytes)?, encoding))
+            }
+            _ => continue,
+        }
+    }
+
+    bail!("Unrecognized PSBT encoding, expected base64, hex, base43, binary or ur:crypto-psbt")
+}
+
+pub fn decode_psbt(data: &[u8]) -> Result<PartiallySignedTransaction> {
+    decode_psbt_with_encoding(data).map(|(psbt, _)| psbt)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode_psbt_with_encoding, PSBTEncoding};
+    use crate::utils::base43::to_base43;
+    use crate::utils::base64::from_base64;
+    use crate::utils::ur::{encode_cbor_bytes, encode_multipart};
+    use bitcoin::hashes::hex::ToHex;
+
+    const PSBT: &str = "cHNidP8BAIcBAAAAAvTkJTkzr4vYO7aoN4AUZaL3BkMWYQ+mYPFBtjhVmvCSAAAAAAD/////glBxTfjYVipVX+gO+YUDCVSJdWtsFo7aTRQ25n0zqwMAAAAAAP////8BbcQAAAAAAAAiACCNY6b6UCml4qW1ojbPwqJ17aaifLtEGzgCwA97J8cCuAAAAABPAQQ1h88DLxEkLIAAAAH8O+5COgGM620iagZjKI7Q+17g+p15CrNw0DDgRzDmyAOAji4c1IK90HGWlDxagTPRaa+GAitrsDgXcWROwHRE1xAfzOTeMAAAgAEAAIABAACATwEENYfPA1asA/SAAAAB+QiuSmaw/6GrFLVa8M5I8qcCJcuQEJAtaQGvkLk+j4ECUa8V4Fu4t+rBiVs/X0f/7+zYMhoC7yIpj4nQogN98GAQYPOgszAAAIABAACAAQAAgE8BBDWHzwN9uUaNgAAAAfUicMdoejr7NR708bmjDBDzttOSFwaDrqlIjBOaEcmYAyIjhSM46mf7/Y3DUqsmJeeaJ3ZGISNr8cAhMJDTSJ6fEO0CEMgwAACAAQAAgAEAAIAAAQErU8MAAAAAAAAiACADQDxIJhQEIWqn08T37W/Ih8uXW40ehgg3slu0Uf7dRQEDBAIAAAABBWlSIQJDnVTFh6pHCQmatEEVvV6sf1P5+u9Bb7zhz0D829Ay2CEDC0VHn9A3/8QMIRBpZobsevmCDpADo8aiHknTJs9b234hA+HpTXYQbhjCPCQRmQlLH4fKO27ghsThI+ztTmujeM2ZU64iBgJDnVTFh6pHCQmatEEVvV6sf1P5+u9Bb7zhz0D829Ay2BjtAhDIMAAAgAEAAIABAACAAAAAAPIAAAAiBgMLRUef0Df/xAwhEGlmhux6+YIOkAOjxqIeSdMmz1vbfhgfzOTeMAAAgAEAAIABAACAAAAAAPIAAAAiBgPh6U12EG4YwjwkEZkJSx+Hyjtu4IbE4SPs7U5ro3jNmRhg86CzMAAAgAEAAIABAACAAAAAAPIAAAAAAQErOgUAAAAAAAAiACBdERsXcaX+/OJlazhLTNrrL9dmXPtIFX3+wl/vS3rlbgEDBAIAAAABBWlSIQI8/6zXULU4/iE7RqJR664w7V/JTpxAxkBflCGOxcM8CiECTbNidmQvwtF0A/gs/Q/H4yNY+Vhw0h7ZSQ+MZk/T0PMhAtEgc7jMqsCndxV78/4aPsQ5PCOZoIBbzLmbkAzh6xQLU64iBgI8/6zXULU4/iE7RqJR664w7V/JTpxAxkBflCGOxcM8ChgfzOTeMAAAgAEAAIABAACAAAAAAPUAAAAiBgJNs2J2ZC/C0XQD+Cz9D8fjI1j5WHDSHtlJD4xmT9PQ8xhg86CzMAAAgAEAAIABAACAAAAAAPUAAAAiBgLRIHO4zKrAp3cVe/P+Gj7EOTwjmaCAW8y5m5AM4esUCxjtAhDIMAAAgAEAAIABAACAAAAAAPUAAAAAAA==";
+
+    #[test]
+    fn decodes_all_encodings() {
+        let binary = from_base64(PSBT).unwrap();
+        let ur = format!(
+            "ur:crypto-psbt/{}",
+            crate::utils::ur::to_bytewords_minimal(&encode_cbor_bytes(&binary))
+        );
+        let inputs = [
+            (binary.clone(), PSBTEncoding::Binary),
+            (PSBT.as_bytes().to_vec(), PSBTEncoding::Base64),
+            (binary.to_hex().into_bytes(), PSBTEncoding::Hex),
+            (to_base43(&binary).into_bytes(), PSBTEncoding::Base43),
+            (ur.into_bytes(), PSBTEncoding::UR),
+        ];
+
+        for (input, expected_encoding) in inputs {
+            let (psbt, encoding) = decode_psbt_with_encoding(&input).unwrap();
+            assert_eq!(encoding, expected_encoding);
+            assert_eq!(bitcoin::consensus::serialize(&psbt), binary);
+        }
+    }
+
+    #[test]
+    fn decodes_multipart_ur() {
+        let binary = from_base64(PSBT).unwrap();
+        let parts = encode_multipart("crypto-psbt", &encode_cbor_bytes(&binary), 60, 40);
+
+        let (psbt, encoding) = decode_psbt_with_encoding(parts.join("\n").as_bytes()).unwrap();
+        assert_eq!(encoding, PSBTEncoding::UR);
+        assert_eq!(bitcoin::consensus::serialize(&psbt), binary);
+
+        let incomplete = decode_psbt_with_encoding(parts[0].as_bytes());
+        assert!(incomplete
+            .unwrap_err()
+            .to_string()
+            .contains("Incomplete UR"));
+    }
+}
@@ -1,26 +1,427 @@
-use bdk::psbt::PsbtUtils;
+use anyhow::{anyhow, bail, Context, Result};
 use bitcoin::hashes::hex::ToHex;
 use bitcoin::psbt::PartiallySignedTransaction;
+use bitcoin::secp256k1::PublicKey;
+use bitcoin::util::bip32::Fingerprint;
+use bitcoin::{Address, Amount, Denomination, Network, TxOut};
+use std::collections::BTreeSet;
+use std::fmt;
 use std::str::FromStr;
 
+use crate::channel::{verify_channel_funding, ChannelFunding};
+use crate::psbt_decoder::{decode_psbt_with_encoding, PSBTEncoding};
+use crate::signer::{input_utxo, invalid_partial_sigs, sighash_types, unverified_inputs};
+
+/// Operation a coordinator asks to sign, as stated in its request type.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum Action {
     ChannelRequest,
     Withdrawal,
 }
 
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Action::ChannelRequest => write!(f, "Channel request"),
+            Action::Withdrawal => write!(f, "Withdrawal"),
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = anyhow::Error;
+
+    fn from_str(request_type: &str) -> Result<Self> {
+        let normalized: String = request_type
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_lowercase();
+        match normalized.as_str() {
+            "channelrequest" | "channelopening" | "channel" => Ok(Action::ChannelRequest),
+            "withdrawal" | "walletwithdrawal" => Ok(Action::Withdrawal),
+            _ => bail!("Unknown operation type {}", request_type.trim()),
+        }
+    }
+}
+
+fn input_fingerprints(psbt: &PartiallySignedTransaction) -> Vec<BTreeSet<Fingerprint>> {
+    psbt.inputs
+        .iter()
+        .map(|i| i.bip32_derivation.values().map(|(f, _)| *f).collect())
+        .collect()
+}
+
+/// Outputs paying back to the multisig being spent, either to the same script
+/// or to keys from the same set of master fingerprints.
+fn is_own_output(
+    psbt: &PartiallySignedTransaction,
+    index: usize,
+    fingerprints: &[BTreeSet<Fingerprint>],
+) -> bool {
+    let script_pubkey = &psbt.unsigned_tx.output[index].script_pubkey;
+    let output_fingerprints: BTreeSet<Fingerprint> = psbt
+        .outputs
+        .get(index)
+        .map(|o| o.bip32_derivation.values().map(|(f, _)| *f).collect())
+        .unwrap_or_default();
+
+    psbt.inputs.iter().any(|i| {
+        i.witness_utxo
+            .as_ref()
+            .is_some_and(|u| u.script_pubkey == *script_pubkey)
+    }) || (!output_fingerprints.is_empty() && fingerprints.contains(&output_fingerprints))
+}
+
+impl Action {
+    /// Checks the PSBT does what the action states, returning the outputs
+    /// leaving the multisig. `amount` is the amount stated by the request.
+    pub fn validate(
+        &self,
+        psbt: &PartiallySignedTransaction,
+        amount: Option<Amount>,
+    ) -> Result<Vec<TxOut>> {
+        let fingerprints = input_fingerprints(psbt);
+        let (own, external): (Vec<usize>, Vec<usize>) = (0..psbt.unsigned_tx.output.len())
+            .partition(|index| is_own_output(psbt, *index, &fingerprints));
+        let external: Vec<TxOut> = external
+            .into_iter()
+            .map(|index| psbt.unsigned_tx.output[index].clone())
+            .collect();
+
+        match self {
+            Action::ChannelRequest => {
+                let amount = amount.context("A channel request must state the channel amount")?;
+                let funding: Vec<&TxOut> = external
+                    .iter()
+                    .filter(|o| o.script_pubkey.is_v0_p2wsh())
+                    .collect();
+                match funding.as_slice() {
+                    [output] if output.value == amount.to_sat() => {}
+                    [output] => bail!(
+                        "The funding output pays {} sats instead of the channel amount {} sats",
+                        output.value,
+                        amount.to_sat()
+                    ),
+                    _ => bail!(
+                        "A channel request must have exactly one P2WSH funding output, found {}",
+                        funding.len()
+                    ),
+                }
+            }
+            Action::Withdrawal => {
+                if !own.is_empty() {
+                    bail!("A withdrawal must not pay back to the multisig being spent");
+                }
+            }
+        }
+
+        if external.is_empty() {
+            bail!("The PSBT doesn't pay to any destination");
+        }
+        Ok(external)
+    }
+}
+
+/// Parses an amount in BTC as shown by coordinators.
+pub fn parse_btc_amount(amount: &str) -> Result<Amount> {
+    Amount::from_str_in(amount.trim(), Denomination::Bitcoin)
+        .with_context(|| format!("Invalid amount {}", amount.trim()))
+}
+
+/// Operation type and parameters a coordinator page states along with its
+/// PSBT, as found in the page.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OperationRequest<'a> {
+    pub request_type: Option<&'a str>,
+    pub amount: Option<&'a str>,
+    pub node_pubkey: Option<&'a str>,
+}
+
+/// Operation requested by a coordinator page, validated against its PSBT.
+pub struct OperationDetails {
+    pub action: Action,
+    pub amount: Option<Amount>,
+    /// Address, or script when it has no address, and amount of the outputs
+    /// leaving the multisig.
+    pub destinations: Vec<(String, u64)>,
+    /// Funding output of channel requests.
+    pub channel: Option<ChannelFunding>,
+}
+
+impl OperationDetails {
+    pub fn new(
+        psbt_str: &str,
+        request_type: Option<&str>,
+        amount: Option<&str>,
+        node_pubkey: Option<&str>,
+        network: Network,
+    ) -> Result<Self> {
+        let (psbt, _) = decode_psbt_with_encoding(psbt_str.as_bytes())?;
+        let request = OperationRequest {
+            request_type,
+            amount,
+            node_pubkey,
+        };
+        Self::from_psbt(&psbt, &request, network)
+    }
+
+    /// Validates the decoded `psbt` against `request`, as done again right
+    /// before signing it.
+    pub fn from_psbt(
+        psbt: &PartiallySignedTransaction,
+        request: &OperationRequest,
+        network: Network,
+    ) -> Result<Self> {
+        let action = Action::from_str(request.request_type.context("Missing operation type")?)?;
+        let amount = request.amount.map(parse_btc_amount).transpose()?;
+        let node_pubkey = request
+            .node_pubkey
+            .map(|k| PublicKey::from_str(k.trim()).context("Invalid node public key"))
+            .transpose()?;
+        let destinations = action
+            .validate(psbt, amount)?
+            .into_iter()
+            .map(|o| {
+                let address = Address::from_script(&o.script_pubkey, network)
+                    .map_or_else(|_| o.script_pubkey.to_hex(), |a| a.to_string());
+                (address, o.value)
+            })
+            .collect();
+        let channel = match (action, amount) {
+            (Action::ChannelRequest, Some(capacity)) => Some(verify_channel_funding(
+                psbt,
+                capacity,
+                node_pubkey.as_ref(),
+            )?),
+            _ => None,
+        };
+
+        Ok(Self {
+            action,
+            amount,
+            destinations,
+            channel,
+        })
+    }
+}
+
+/// Fee paid according to the verified amounts of the spent outputs.
+fn verified_fee(psbt: &PartiallySignedTransaction) -> Result<u64> {
+    // the amounts come from the PSBT, their sum can't be trusted not to overflow
+    let overflow = || anyhow!("Input/output amounts overflow");
+    let inputs = (0..psbt.inputs.len()).try_fold(0u64, |sum, index| {
+        sum.checked_add(input_utxo(psbt, index)?.value)
+            .ok_or_else(overflow)
+    })?;
+    let outputs = psbt
+        .unsigned_tx
+        .output
+        .iter()
+        .try_fold(0u64, |sum, o| sum.checked_add(o.value))
+        .ok_or_else(overflow)?;
+    inputs
+        .checked_sub(outputs)
+        .context("The outputs spend more than the inputs")
+}
+
 #[derive(Default)]
 pub struct PSBTDetails {
     pub tx_id: String,
     pub fee: u64,
+    pub encoding: Option<PSBTEncoding>,
+    /// Reasons the fee or the cosigner signatures can't be trusted.
+    pub warnings: Vec<String>,
+    /// Sighash type requested by each input.
+    pub sighash_types: Vec<String>,
 }
 
 impl FromStr for PSBTDetails {
     type Err = anyhow::Error;
 
-    fn from_str(psbt_64: &str) -> Result<Self, Self::Err> {
-        let psbt = PartiallySignedTransaction::from_str(psbt_64)?;
+    fn from_str(psbt_str: &str) -> Result<Self, Self::Err> {
+        Self::from_bytes(psbt_str.as_bytes())
+    }
+}
+
+impl PSBTDetails {
+    /// Same as `from_str` for PSBTs that may be raw binary, such as the
+    /// contents of a file.
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let (psbt, encoding) = decode_psbt_with_encoding(data)?;
         let tx_id = psbt.clone().extract_tx().txid().to_hex();
-        let fee = psbt.fee_amount().unwrap_or_default();
-        Ok(Self { tx_id, fee })
+        let mut warnings = Vec::new();
+        let fee = verified_fee(&psbt).unwrap_or_else(|e| {
+            warnings.push(e.to_string());
+            0
+        });
+        let unverified = unverified_inputs(&psbt);
+        if !unverified.is_empty() {
+            warnings.push(format!(
+                "The amounts of inputs {unverified:?} can't be verified without their previous transactions, the fee may be higher than shown"
+            ));
+        }
+        warnings.extend(invalid_partial_sigs(&psbt));
+        Ok(Self {
+            tx_id,
+            fee,
+            encoding: Some(encoding),
+            warnings,
+            sighash_types: sighash_types(&psbt),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_btc_amount, verified_fee, Action};
+    use bitcoin::hashes::Hash;
+    use bitcoin::psbt::{Input, Output, PartiallySignedTransaction};
+    use bitcoin::secp256k1::{PublicKey, Secp256k1, SecretKey};
+    use bitcoin::util::bip32::{DerivationPath, Fingerprint};
+    use bitcoin::{
+        Amount, OutPoint, PackedLockTime, Script, Sequence, Transaction, TxIn, TxOut, Witness,
+    };
+    use std::collections::BTreeMap;
+    use std::str::FromStr;
+
+    fn derivations(keys: &[(u8, &str)]) -> BTreeMap<PublicKey, (Fingerprint, DerivationPath)> {
+        keys.iter()
+            .map(|(key, fingerprint)| {
+                let secret_key = SecretKey::from_slice(&[*key; 32]).unwrap();
+                (
+                    secret_key.public_key(&Secp256k1::new()),
+                    (
+                        Fingerprint::from_str(fingerprint).unwrap(),
+                        DerivationPath::from_str("m/48'/0'/0'/2'/1/0").unwrap(),
+                    ),
+                )
+            })
+            .collect()
+    }
+
+    fn p2wsh(seed: u8) -> Script {
+        Script::new_v0_p2wsh(&Script::from(vec![seed; 10]).wscript_hash())
+    }
+
+    /// PSBT spending a 2 of 2 multisig to `outputs`, with key origins for the
+    /// outputs flagged as change.
+    fn psbt(outputs: &[(Script, u64, bool)]) -> PartiallySignedTransaction {
+        let tx = Transaction {
+            version: 2,
+            lock_time: PackedLockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::default(),
+                script_sig: Script::new(),
+                sequence: Sequence::MAX,
+                witness: Witness::default(),
+            }],
+            output: outputs
+                .iter()
+                .map(|(script_pubkey, value, _)| TxOut {
+                    value: *value,
+                    script_pubkey: script_pubkey.clone(),
+                })
+                .collect(),
+        };
+        let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx).unwrap();
+        psbt.inputs[0] = Input {
+            witness_utxo: Some(TxOut {
+                value: 1_000_000,
+                script_pubkey: p2wsh(0),
+            }),
+            bip32_derivation: derivations(&[(1, "aaaaaaaa"), (2, "bbbbbbbb")]),
+            ..Default::default()
+        };
+        for (output, (_, _, change)) in psbt.outputs.iter_mut().zip(outputs) {
+            if *change {
+                *output = Output {
+                    bip32_derivation: derivations(&[(3, "aaaaaaaa"), (4, "bbbbbbbb")]),
+                    ..Default::default()
+                };
+            }
+        }
+        psbt
+    }
+
+    #[test]
+    fn parses_action() {
+        assert_eq!(
+            Action::from_str("Channel Opening").unwrap(),
+            Action::ChannelRequest
+        );
+        assert_eq!(
+            Action::from_str(" ChannelRequest ").unwrap(),
+            Action::ChannelRequest
+        );
+        assert_eq!(Action::from_str("Withdrawal").unwrap(), Action::Withdrawal);
+        assert!(Action::from_str("Swap").is_err());
+        assert_eq!(
+            parse_btc_amount("0.001").unwrap(),
+            Amount::from_sat(100_000)
+        );
+    }
+
+    #[test]
+    fn validates_channel_request() {
+        let amount = Some(Amount::from_sat(100_000));
+        let channel = psbt(&[(p2wsh(1), 100_000, false), (p2wsh(2), 890_000, true)]);
+        assert_eq!(
+            Action::ChannelRequest
+                .validate(&channel, amount)
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(Action::ChannelRequest.validate(&channel, None).is_err());
+        assert!(Action::ChannelRequest
+            .validate(&channel, Some(Amount::from_sat(99_000)))
+            .is_err());
+
+        let two_funding = psbt(&[(p2wsh(1), 100_000, false), (p2wsh(2), 890_000, false)]);
+        assert!(Action::ChannelRequest
+            .validate(&two_funding, amount)
+            .is_err());
+        let back_to_input = psbt(&[(p2wsh(0), 100_000, false)]);
+        assert!(Action::ChannelRequest
+            .validate(&back_to_input, amount)
+            .is_err());
+    }
+
+    #[test]
+    fn validates_withdrawal() {
+        let external = Script::new_v0_p2wpkh(&bitcoin::WPubkeyHash::from_slice(&[7; 20]).unwrap());
+        let withdrawal = psbt(&[(external.clone(), 990_000, false)]);
+        assert!(Action::Withdrawal.validate(&withdrawal, None).is_ok());
+
+        let with_change = psbt(&[(external, 100_000, false), (p2wsh(2), 890_000, true)]);
+        assert!(Action::Withdrawal.validate(&with_change, None).is_err());
+    }
+
+    #[test]
+    fn rejects_overflowing_amounts() {
+        let half = u64::MAX / 2 + 1;
+        let overflowing = psbt(&[(p2wsh(1), half, false), (p2wsh(2), half, false)]);
+        assert_eq!(
+            verified_fee(&overflowing).unwrap_err().to_string(),
+            "Input/output amounts overflow"
+        );
+        let mut overflowing = psbt(&[(p2wsh(1), 1, false)]);
+        overflowing
+            .unsigned_tx
+            .input
+            .push(overflowing.unsigned_tx.input[0].clone());
+        overflowing.inputs.push(overflowing.inputs[0].clone());
+        for input in &mut overflowing.inputs {
+            input.witness_utxo.as_mut().unwrap().value = half;
+        }
+        assert_eq!(
+            verified_fee(&overflowing).unwrap_err().to_string(),
+            "Input/output amounts overflow"
+        );
+        assert_eq!(
+            verified_fee(&psbt(&[(p2wsh(1), 990_000, false)])).unwrap(),
+            10_000
+        );
     }
 }
@@ -1,30 +1,110 @@
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
+use bitcoin::blockdata::script::Instruction;
 use bitcoin::consensus::serialize;
 use bitcoin::psbt::Input;
-use bitcoin::secp256k1::ecdsa::Signature;
-use bitcoin::secp256k1::{All, Message, Secp256k1};
-use bitcoin::util::bip32::{DerivationPath, ExtendedPrivKey};
+use bitcoin::secp256k1::{self, All, KeyPair, Message, Secp256k1};
+use bitcoin::util::bip32::{ChildNumber, DerivationPath, ExtendedPrivKey, Fingerprint};
 use bitcoin::util::psbt::PartiallySignedTransaction;
-use bitcoin::util::sighash::SighashCache;
-use bitcoin::{EcdsaSig, EcdsaSighashType, Network, PublicKey};
+use bitcoin::util::schnorr::TapTweak;
+use bitcoin::util::sighash::{Prevouts, SighashCache};
+use bitcoin::{
+    EcdsaSig, EcdsaSighashType, Network, PublicKey, SchnorrSig, SchnorrSighashType, Transaction,
+    TxOut,
+};
+use rand::Rng;
+use std::collections::HashMap;
 use std::str::FromStr;
 
+use crate::anti_exfil;
+use crate::psbt_decoder::decode_psbt;
+use crate::psbt_details::{OperationDetails, OperationRequest};
 use crate::utils::base64::to_base64;
 use crate::wallet::Wallet;
 
-fn set_sighash_type(signature: Signature, input: &Input) -> EcdsaSig {
-    let sighash_type = get_sighash_type(input);
-    EcdsaSig {
-        sig: signature,
-        hash_ty: sighash_type,
-    }
+fn get_sighash_type(input: &Input) -> Result<EcdsaSighashType> {
+    input.sighash_type.map_or(Ok(EcdsaSighashType::All), |t| {
+        t.ecdsa_hash_ty()
+            .map_err(|_| anyhow!("Invalid sighash type {t}"))
+    })
 }
 
-fn get_sighash_type(input: &Input) -> EcdsaSighashType {
+fn get_schnorr_sighash_type(input: &Input) -> Result<SchnorrSighashType> {
     input
         .sighash_type
-        .and_then(|t| t.ecdsa_hash_ty().ok())
-        .unwrap_or(EcdsaSighashType::All)
+        .map_or(Ok(SchnorrSighashType::Default), |t| {
+            t.schnorr_hash_ty()
+                .map_err(|_| anyhow!("Invalid sighash type {t}"))
+        })
+}
+
+/// Sighash type requested by each input, as shown to the user before signing.
+/// Taproot inputs use the schnorr sighash types, SIGHASH_DEFAULT included.
+pub fn sighash_types(psbt: &PartiallySignedTransaction) -> Vec<String> {
+    psbt.inputs
+        .iter()
+        .map(|input| {
+            if input.tap_internal_key.is_some() || !input.tap_scripts.is_empty() {
+                get_schnorr_sighash_type(input).map(|t| t.to_string())
+            } else {
+                get_sighash_type(input).map(|t| t.to_string())
+            }
+            .unwrap_or_else(|e| e.to_string())
+        })
+        .collect()
+}
+
+/// Only SIGHASH_ALL commits to every input and output, other types are
+/// refused unless `allow_any_sighash`.
+fn check_sighash_type(
+    input: &Input,
+    index: usize,
+    allow_any_sighash: bool,
+) -> Result<EcdsaSighashType> {
+    let sighash_type = get_sighash_type(input)?;
+    if sighash_type != EcdsaSighashType::All && !allow_any_sighash {
+        bail!("Input {index} requests {sighash_type}, only SIGHASH_ALL is allowed for this wallet");
+    }
+    Ok(sighash_type)
+}
+
+/// Shapes of the key derivations we accept to sign for, `*` matches any index
+/// and a trailing `'` requires it to be hardened.
+pub const ALLOWED_DERIVATIONS: [&str; 10] = [
+    // BIP44, BIP49, BIP84 and BIP86 single sig
+    "m/44'/*'/*'/*/*",
+    "m/49'/*'/*'/*/*",
+    "m/84'/*'/*'/*/*",
+    "m/86'/*'/*'/*/*",
+    // BIP45 and BIP87 multisig
+    "m/45'/*/*/*",
+    "m/87'/*'/*'/*/*",
+    // BIP48 P2SH-P2WSH and P2WSH multisig
+    "m/48'/*'/*'/1'/*/*",
+    "m/48'/*'/*'/2'/*/*",
+    // BIP48 style P2TR multisig
+    "m/48'/*'/*'/3'/*/*",
+    // BIP48 without script type, as used by NodeGuard
+    "m/48'/*'/*'/*/*",
+];
+
+fn matches_shape(path: &DerivationPath, shape: &str) -> bool {
+    let levels: Vec<&str> = shape.split('/').skip(1).collect();
+    levels.len() == path.len()
+        && levels.iter().zip(path).all(|(level, child)| match *level {
+            "*'" => child.is_hardened(),
+            "*" => child.is_normal(),
+            _ => ChildNumber::from_str(level).is_ok_and(|c| c == *child),
+        })
+}
+
+pub(crate) fn check_allowed_derivation(path: &DerivationPath) -> Result<()> {
+    if !ALLOWED_DERIVATIONS
+        .iter()
+        .any(|shape| matches_shape(path, shape))
+    {
+        bail!("The derivation path {path} is not an allowed derivation");
+    }
+    Ok(())
 }
 
 fn get_partial_derivation(
@@ -36,11 +116,14 @@ fn get_partial_derivation(
             "Can't get a partial derivation from a derivation greater than the sub derivation"
         ));
     }
+    if sub_derivation[..derivation.len()] != derivation[..] {
+        bail!("The derivation path {sub_derivation} doesn't extend the wallet derivation {derivation}");
+    }
     let partial = &sub_derivation[derivation.len()..];
-    DerivationPath::try_from(partial).map_err(|e| anyhow!("{e}"))
+    Ok(DerivationPath::from(partial))
 }
 
-fn derive_relative_xpriv(
+pub(crate) fn derive_relative_xpriv(
     xprv: &ExtendedPrivKey,
     secp: &Secp256k1<All>,
     derivation: &DerivationPath,
@@ -50,76 +133,708 @@ fn derive_relative_xpriv(
         .map_err(|e| anyhow!("{e}"))
 }
 
+/// Derives the keys of a wallet, caching the parent xprv of each path so the
+/// keys of a large PSBT cost one child derivation each instead of a full one.
+struct KeyDeriver<'a> {
+    secp: &'a Secp256k1<All>,
+    xprv: &'a ExtendedPrivKey,
+    derivation: &'a DerivationPath,
+    /// Derivations of the other wallets of the same seed signing along.
+    other_derivations: &'a [DerivationPath],
+    parents: HashMap<DerivationPath, ExtendedPrivKey>,
+    keypairs: HashMap<DerivationPath, KeyPair>,
+}
+
+impl<'a> KeyDeriver<'a> {
+    fn new(
+        secp: &'a Secp256k1<All>,
+        xprv: &'a ExtendedPrivKey,
+        derivation: &'a DerivationPath,
+        other_derivations: &'a [DerivationPath],
+    ) -> Self {
+        Self {
+            secp,
+            xprv,
+            derivation,
+            other_derivations,
+            parents: HashMap::new(),
+            keypairs: HashMap::new(),
+        }
+    }
+
+    /// Whether the key is left for another wallet of the same seed, the one
+    /// whose derivation is the longest prefix of its path. Keys of no wallet
+    /// are ours, so deriving them fails naming their path.
+    fn is_of_other_wallet(&self, sub_derivation: &DerivationPath) -> bool {
+        let extends = |d: &DerivationPath| sub_derivation.as_ref().starts_with(d.as_ref());
+        let own = extends(self.derivation).then_some(self.derivation.len());
+        self.other_derivations
+            .iter()
+            .any(|d| extends(d) && own.is_none_or(|len| d.len() > len))
+    }
+
+    fn keypair(&mut self, sub_derivation: &DerivationPath) -> Result<KeyPair> {
+        if let Some(keypair) = self.keypairs.get(sub_derivation) {
+            return Ok(*keypair);
+        }
+        let partial = get_partial_derivation(self.derivation, sub_derivation)?;
+        let xprv = match partial.as_ref().split_last() {
+            None => *self.xprv,
+            Some((child, parent)) => {
+                let parent = DerivationPath::from(parent);
+                let parent_xprv = match self.parents.get(&parent) {
+                    Some(parent_xprv) => *parent_xprv,
+                    None => {
+                        let parent_xprv = self.xprv.derive_priv(self.secp, &parent)?;
+                        self.parents.insert(parent, parent_xprv);
+                        parent_xprv
+                    }
+                };
+                parent_xprv.ckd_priv(self.secp, *child)?
+            }
+        };
+        let keypair = xprv.to_keypair(self.secp);
+        self.keypairs.insert(sub_derivation.clone(), keypair);
+        Ok(keypair)
+    }
+}
+
+/// BIP44 style purposes, whose second level is the coin type.
+const COIN_TYPE_PURPOSES: [u32; 5] = [44, 48, 49, 84, 86];
+
+/// Refuses PSBTs built for another network, looking at the version bytes of
+/// the global xpubs and at the coin type of the key derivations.
+pub(crate) fn check_network(psbt: &PartiallySignedTransaction, network: Network) -> Result<()> {
+    let testnet = network != Network::Bitcoin;
+
+    if psbt
+        .xpub
+        .keys()
+        .any(|xpub| (xpub.network != Network::Bitcoin) != testnet)
+    {
+        bail!("The PSBT xpubs don't belong to the wallet network ({network})");
+    }
+
+    let coin_type = ChildNumber::from_hardened_idx(u32::from(testnet))?;
+    let derivations = psbt
+        .inputs
+        .iter()
+        .flat_map(|input| input.bip32_derivation.values())
+        .chain(
+            psbt.outputs
+                .iter()
+                .flat_map(|output| output.bip32_derivation.values()),
+        );
+    for (_, path) in derivations {
+        if let [ChildNumber::Hardened { index: purpose }, coin @ ChildNumber::Hardened { .. }, ..] =
+            path.as_ref()
+        {
+            if COIN_TYPE_PURPOSES.contains(purpose) && *coin != coin_type {
+                bail!(
+                    "The PSBT derivation {path} doesn't belong to the wallet network ({network})"
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Output spent by an input. It comes from `non_witness_utxo` when present,
+/// after checking it is the transaction being spent and that it agrees with
+/// `witness_utxo`.
+pub fn input_utxo(psbt: &PartiallySignedTransaction, index: usize) -> Result<TxOut> {
+    let input = psbt.inputs.get(index).context("Input not found")?;
+    let previous_output = psbt.unsigned_tx.input[index].previous_output;
+
+    match (&input.non_witness_utxo, &input.witness_utxo) {
+        (Some(previous_tx), witness_utxo) => {
+            if previous_tx.txid() != previous_output.txid {
+                bail!("The previous transaction of input {index} isn't the one it spends");
+            }
+            let utxo = previous_tx
+                .output
+                .get(previous_output.vout as usize)
+                .with_context(|| {
+                    format!(
+                        "The previous transaction of input {index} doesn't have the spent output"
+                    )
+                })?;
+            if witness_utxo.as_ref().is_some_and(|u| u != utxo) {
+                bail!("The witness utxo of input {index} doesn't match its previous transaction");
+            }
+            Ok(utxo.clone())
+        }
+        (None, Some(witness_utxo)) => Ok(witness_utxo.clone()),
+        (None, None) => bail!("Input {index} doesn't include the output it spends"),
+    }
+}
+
+/// Inputs of a multi-input transaction whose amount is only backed by
+/// `witness_utxo`. Segwit signatures only commit to the amount of their own
+/// input, so an attacker can lie about a different input on each signing
+/// session and combine the signatures to overpay fees. Taproot signatures
+/// commit to every amount so a PSBT spending only taproot outputs is safe.
+pub fn unverified_inputs(psbt: &PartiallySignedTransaction) -> Vec<usize> {
+    let all_taproot = psbt.inputs.iter().all(|input| {
+        input
+            .witness_utxo
+            .as_ref()
+            .is_some_and(|utxo| utxo.script_pubkey.is_v1_p2tr())
+    });
+    if psbt.inputs.len() < 2 || all_taproot {
+        return Vec::new();
+    }
+    psbt.inputs
+        .iter()
+        .enumerate()
+        .filter(|(_, input)| input.non_witness_utxo.is_none())
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// Cosigner signatures of the script inputs that are invalid for their
+/// sighash or from a key that isn't in the script, which would make the
+/// transaction fail at broadcast.
+pub fn invalid_partial_sigs(psbt: &PartiallySignedTransaction) -> Vec<String> {
+    let secp = Secp256k1::verification_only();
+    let mut sighash_cache = SighashCache::new(&psbt.unsigned_tx);
+    let mut invalid = Vec::new();
+
+    for (index, input) in psbt.inputs.iter().enumerate() {
+        let Some(witness_script) = &input.witness_script else {
+            continue;
+        };
+        for (pubkey, sig) in &input.partial_sigs {
+            let in_script = witness_script.instructions().any(|instruction| {
+                matches!(instruction, Ok(Instruction::PushBytes(bytes)) if *bytes == pubkey.to_bytes())
+            });
+            if !in_script {
+                invalid.push(format!(
+                    "Input {index} has a signature from {pubkey}, which isn't a key of its script"
+                ));
+                continue;
+            }
+            let valid = input_utxo(psbt, index).is_ok_and(|utxo| {
+                sighash_cache
+                    .segwit_signature_hash(index, witness_script, utxo.value, sig.hash_ty)
+                    .is_ok_and(|sighash| {
+                        Message::from_slice(&sighash).is_ok_and(|message| {
+                            secp.verify_ecdsa(&message, &sig.sig, &pubkey.inner).is_ok()
+                        })
+                    })
+            });
+            if !valid {
+                invalid.push(format!(
+                    "Input {index} has an invalid signature from {pubkey}"
+                ));
+            }
+        }
+    }
+    invalid
+}
+
+/// Signs the key path when our key is the internal key, and every tapscript
+/// leaf our keys appear in. Returns whether anything was signed.
+#[allow(clippy::too_many_arguments)]
+fn sign_taproot_input(
+    secp: &Secp256k1<All>,
+    sighash_cache: &mut SighashCache<&Transaction>,
+    input: &mut Input,
+    index: usize,
+    utxos: &[TxOut],
+    keys: &mut KeyDeriver,
+    master_fingerprint: Fingerprint,
+    allow_any_sighash: bool,
+) -> Result<bool> {
+    let hash_ty = get_schnorr_sighash_type(input)?;
+    if !matches!(
+        hash_ty,
+        SchnorrSighashType::Default | SchnorrSighashType::All
+    ) && !allow_any_sighash
+    {
+        bail!("Input {index} requests {hash_ty}, only SIGHASH_ALL is allowed for this wallet");
+    }
+    let prevouts = Prevouts::All(utxos);
+    let mut signed = false;
+
+    for (xonly, (leaf_hashes, (fingerprint, sub_derivation))) in input.tap_key_origins.clone() {
+        if fingerprint != master_fingerprint || keys.is_of_other_wallet(&sub_derivation) {
+            continue;
+        }
+        check_allowed_derivation(&sub_derivation)?;
+        let keypair = keys.keypair(&sub_derivation)?;
+        if keypair.x_only_public_key().0 != xonly {
+            bail!("The key {xonly} of input {index} doesn't derive from this wallet");
+        }
+
+        if leaf_hashes.is_empty() && input.tap_internal_key == Some(xonly) {
+            let tweaked = keypair.tap_tweak(secp, input.tap_merkle_root).to_inner();
+            let sighash =
+                sighash_cache.taproot_key_spend_signature_hash(index, &prevouts, hash_ty)?;
+            let message = Message::from_slice(&sighash)?;
+            let sig =
+                secp.sign_schnorr_with_aux_rand(&message, &tweaked, &rand::thread_rng().gen());
+            secp.verify_schnorr(&sig, &message, &tweaked.x_only_public_key().0)?;
+            input.tap_key_sig = Some(SchnorrSig { sig, hash_ty });
+            signed = true;
+        }
+
+        for leaf_hash in leaf_hashes {
+            let sighash = sighash_cache
+                .taproot_script_spend_signature_hash(index, &prevouts, leaf_hash, hash_ty)?;
+            let message = Message::from_slice(&sighash)?;
+            let sig =
+                secp.sign_schnorr_with_aux_rand(&message, &keypair, &rand::thread_rng().gen());
+            secp.verify_schnorr(&sig, &message, &xonly)?;
+            input
+                .tap_script_sigs
+                .insert((xonly, leaf_hash), SchnorrSig { sig, hash_ty });
+            signed = true;
+        }
+    }
+
+    Ok(signed)
+}
+
+/// Nonce commitment of one of our keys in the first round of anti-exfil
+/// signing, see [`crate::anti_exfil`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignerCommitment {
+    pub input: usize,
+    pub public_key: secp256k1::PublicKey,
+    pub commitment: secp256k1::PublicKey,
+}
+
+/// Where the nonces of the ECDSA signatures come from.
+enum Nonces<'a> {
+    /// RFC6979 nonces, without host involvement.
+    Deterministic,
+    /// Only collects the signer commitments to the host commitment of each
+    /// input, without signing.
+    AntiExfilCommit(&'a [[u8; 32]], &'a mut Vec<SignerCommitment>),
+    /// The committed nonces tweaked by the host data of each input.
+    AntiExfil(&'a [[u8; 32]]),
+}
+
+/// Signs the inputs with keys of the wallet, skipping the inputs of other
+/// seeds and of `other_derivations`, and returns how many inputs were signed.
+#[allow(clippy::too_many_arguments)]
 fn sign_psbt(
-    mut psbt: PartiallySignedTransaction,
+    psbt: &mut PartiallySignedTransaction,
     xprv: ExtendedPrivKey,
+    master_fingerprint: Fingerprint,
     derivation: &DerivationPath,
-) -> Result<PartiallySignedTransaction> {
+    other_derivations: &[DerivationPath],
+    allow_unverified_inputs: bool,
+    allow_any_sighash: bool,
+    mut nonces: Nonces,
+) -> Result<usize> {
     let secp = Secp256k1::new();
 
     // https://github.com/bitcoin/bips/blob/master/bip-0174.mediawiki#user-content-Signer
 
-    for (index, input) in psbt.inputs.iter_mut().enumerate() {
-        let witness_script = input
-            .witness_script
-            .as_ref()
-            .context("Missing witness script")?;
-
-        let amount = input
-            .witness_utxo
-            .as_ref()
-            .context("Witness utxo not found")?
-            .value;
+    if let Nonces::AntiExfilCommit(host_data, _) | Nonces::AntiExfil(host_data) = &nonces {
+        if host_data.len() != psbt.inputs.len() {
+            bail!(
+                "Expected anti-exfil data for each of the {} inputs, got {}",
+                psbt.inputs.len(),
+                host_data.len()
+            );
+        }
+    }
+    let unverified = unverified_inputs(psbt);
+    if !allow_unverified_inputs && !unverified.is_empty() {
+        bail!(
+            "The amounts of inputs {unverified:?} can't be verified, the PSBT must include their previous transactions"
+        );
+    }
+    let utxos = (0..psbt.inputs.len())
+        .map(|index| input_utxo(psbt, index))
+        .collect::<Result<Vec<TxOut>>>()?;
+    // shared by every input so the hashes of the whole transaction are only
+    // computed once
+    let mut sighash_cache = SighashCache::new(&psbt.unsigned_tx);
+    let mut keys = KeyDeriver::new(&secp, &xprv, derivation, other_derivations);
+    let mut signed_inputs = 0;
 
-        let mut sighash_cache = SighashCache::new(&psbt.unsigned_tx);
-        let sighash = sighash_cache.segwit_signature_hash(
-            index,
-            witness_script,
-            amount,
-            get_sighash_type(input),
-        )?;
+    for (index, input) in psbt.inputs.iter_mut().enumerate() {
+        if input.witness_script.is_none() && utxos[index].script_pubkey.is_v1_p2tr() {
+            let owned = input
+                .tap_key_origins
+                .values()
+                .any(|(_, (fingerprint, path))| {
+                    *fingerprint == master_fingerprint && !keys.is_of_other_wallet(path)
+                });
+            if owned && !matches!(nonces, Nonces::Deterministic) {
+                bail!("Input {index} is a taproot input, anti-exfil signing is only for ECDSA");
+            }
+            let signed = sign_taproot_input(
+                &secp,
+                &mut sighash_cache,
+                input,
+                index,
+                &utxos,
+                &mut keys,
+                master_fingerprint,
+                allow_any_sighash,
+            )?;
+            signed_inputs += usize::from(signed);
+            continue;
+        }
 
         let mut input_keypairs = Vec::new();
 
         for (_, (fingerprint, sub_derivation)) in input.bip32_derivation.iter() {
-            if fingerprint != &xprv.fingerprint(&secp) {
+            if fingerprint != &master_fingerprint || keys.is_of_other_wallet(sub_derivation) {
                 continue;
             }
-            let parent_xprv = derive_relative_xpriv(&xprv, &secp, derivation, sub_derivation)?;
-            input_keypairs.push(parent_xprv.to_keypair(&secp));
+            check_allowed_derivation(sub_derivation)?;
+            input_keypairs.push(keys.keypair(sub_derivation)?);
         }
 
+        // inputs of other wallets or cosigners are left for them to sign
         if input_keypairs.is_empty() {
-            return Err(anyhow!("No private keys to sign this psbt"));
+            continue;
         }
 
+        let witness_script = input
+            .witness_script
+            .as_ref()
+            .context("Missing witness script")?;
+
+        let amount = utxos[index].value;
+        let sighash_type = check_sighash_type(input, index, allow_any_sighash)?;
+
+        let sighash =
+            sighash_cache.segwit_signature_hash(index, witness_script, amount, sighash_type)?;
+
         for keypair in input_keypairs {
             let message = &Message::from_slice(&sighash)?;
-            let signature = secp.sign_ecdsa(message, &keypair.secret_key());
+            let signature = match &mut nonces {
+                Nonces::Deterministic => secp.sign_ecdsa(message, &keypair.secret_key()),
+                Nonces::AntiExfil(host_data) => {
+                    anti_exfil::sign(&secp, &keypair.secret_key(), message, &host_data[index])?
+                }
+                Nonces::AntiExfilCommit(host_commitments, commitments) => {
+                    commitments.push(SignerCommitment {
+                        input: index,
+                        public_key: keypair.public_key(),
+                        commitment: anti_exfil::signer_commitment(
+                            &keypair.secret_key(),
+                            message,
+                            &host_commitments[index],
+                        )?,
+                    });
+                    continue;
+                }
+            };
             input.partial_sigs.insert(
                 PublicKey::new(keypair.public_key()),
-                set_sighash_type(signature, input),
+                EcdsaSig {
+                    sig: signature,
+                    hash_ty: sighash_type,
+                },
             );
 
             secp.verify_ecdsa(message, &signature, &keypair.public_key())?;
         }
+        signed_inputs += 1;
     }
 
-    Ok(psbt)
+    Ok(signed_inputs)
 }
 
+/// Signs the inputs of `wallet`. Unless `allow_unverified_inputs`, refuses
+/// multi-input PSBTs without the previous transaction of every input. Sighash
+/// types other than SIGHASH_ALL need the wallet's `allow_any_sighash`.
+/// Invalid cosigner signatures don't stop the signing, they are left for the
+/// user to review with [`invalid_partial_sigs`]. PSBTs requested by a
+/// coordinator are only signed if they do what its `request` states.
 pub fn decode_psbt_and_sign(
-    psbt_64: &str,
+    psbt: &[u8],
+    wallet: &mut Wallet,
+    password: &str,
+    network: Network,
+    allow_unverified_inputs: bool,
+    request: Option<&OperationRequest>,
+) -> Result<String> {
+    if let Some(request) = request {
+        OperationDetails::from_psbt(&decode_psbt(psbt)?, request, network)?;
+    }
+    decode_psbts_and_sign(&[psbt], wallet, password, network, allow_unverified_inputs)?.remove(0)
+}
+
+/// Signs each PSBT as [`decode_psbt_and_sign`] does, decrypting the wallet
+/// only once. Fails if the wallet can't be decrypted, otherwise returns the
+/// result of each PSBT in order.
+pub fn decode_psbts_and_sign<T: AsRef<[u8]>>(
+    psbts: &[T],
     wallet: &mut Wallet,
     password: &str,
     network: Network,
+    allow_unverified_inputs: bool,
+) -> Result<Vec<Result<String>>> {
+    let xprv = wallet.get_xprv(password, network)?;
+    wallet.get_master_fingerprint(&xprv)?;
+
+    Ok(psbts
+        .iter()
+        .map(|psbt| {
+            let mut psbt = decode_psbt(psbt.as_ref())?;
+            let signed_inputs =
+                sign_psbt_with_xprv(&mut psbt, wallet, xprv, allow_unverified_inputs)?;
+            if signed_inputs == 0 {
+                bail!("No private keys to sign this psbt");
+            }
+            Ok(to_base64(&serialize(&psbt)))
+        })
+        .collect())
+}
+
+/// Signs the inputs of `wallet` with its key `xprv`, already decrypted, and
+/// returns how many inputs were signed. It's what [`decode_psbts_and_sign`]
+/// does for each PSBT, without the wallet decryption.
+pub fn sign_psbt_with_xprv(
+    psbt: &mut PartiallySignedTransaction,
+    wallet: &Wallet,
+    xprv: ExtendedPrivKey,
+    allow_unverified_inputs: bool,
+) -> Result<usize> {
+    let master_fingerprint = wallet.get_master_fingerprint(&xprv)?;
+    check_network(psbt, xprv.network)?;
+    sign_psbt(
+        psbt,
+        xprv,
+        master_fingerprint,
+        &wallet.derivation,
+        &[],
+        allow_unverified_inputs,
+        wallet.allow_any_sighash,
+        Nonces::Deterministic,
+    )
+}
+
+/// Signs with each of `wallets` in turn, for PSBTs where several of our
+/// wallets are cosigners. Each wallet only signs the inputs it owns, it fails
+/// if none of them could sign any input or the PSBT doesn't do what the
+/// coordinator `request` states.
+pub fn decode_psbt_and_sign_with_wallets<'a>(
+    psbt: &[u8],
+    wallets: impl IntoIterator<Item = &'a mut Wallet>,
+    password: &str,
+    network: Network,
+    allow_unverified_inputs: bool,
+    request: Option<&OperationRequest>,
 ) -> Result<String> {
-    let psbt = PartiallySignedTransaction::from_str(psbt_64)?;
+    let mut psbt = decode_psbt(psbt)?;
+    if let Some(request) = request {
+        OperationDetails::from_psbt(&psbt, request, network)?;
+    }
+    let mut wallets = wallets.into_iter().peekable();
+    if wallets.peek().is_none() {
+        bail!("No wallet selected to sign the PSBT");
+    }
+    let wallets = wallets
+        .map(|wallet| {
+            let (xprv, master_fingerprint) = wallet_keys(&psbt, wallet, password, network)
+                .with_context(|| format!("Wallet {}", wallet.name))?;
+            Ok((wallet, xprv, master_fingerprint))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut signed_inputs = 0;
+    for (wallet, xprv, master_fingerprint) in &wallets {
+        // keys of a seed are signed by the wallet of their account
+        let other_derivations: Vec<DerivationPath> = wallets
+            .iter()
+            .filter(|(other, _, fingerprint)| {
+                fingerprint == master_fingerprint && other.name != wallet.name
+            })
+            .map(|(other, _, _)| other.derivation.clone())
+            .collect();
+        signed_inputs += sign_psbt(
+            &mut psbt,
+            *xprv,
+            *master_fingerprint,
+            &wallet.derivation,
+            &other_derivations,
+            allow_unverified_inputs,
+            wallet.allow_any_sighash,
+            Nonces::Deterministic,
+        )
+        .with_context(|| format!("Wallet {}", wallet.name))?;
+    }
+    if signed_inputs == 0 {
+        bail!("No private keys to sign this psbt");
+    }
+    Ok(to_base64(&serialize(&psbt)))
+}
 
+/// Decrypted key and master fingerprint of a wallet able to sign `psbt`.
+fn wallet_keys(
+    psbt: &PartiallySignedTransaction,
+    wallet: &mut Wallet,
+    password: &str,
+    network: Network,
+) -> Result<(ExtendedPrivKey, Fingerprint)> {
     let xprv = wallet.get_xprv(password, network)?;
-    let signed_psbt = sign_psbt(psbt, xprv, &wallet.derivation)?;
+    let master_fingerprint = wallet.get_master_fingerprint(&xprv)?;
+    check_network(psbt, xprv.network)?;
+    Ok((xprv, master_fingerprint))
+}
+
+fn sign_with_wallet(
+    psbt: &mut PartiallySignedTransaction,
+    wallet: &mut Wallet,
+    password: &str,
+    network: Network,
+    allow_unverified_inputs: bool,
+    nonces: Nonces,
+) -> Result<usize> {
+    let (xprv, master_fingerprint) = wallet_keys(psbt, wallet, password, network)?;
+    sign_psbt(
+        psbt,
+        xprv,
+        master_fingerprint,
+        &wallet.derivation,
+        &[],
+        allow_unverified_inputs,
+        wallet.allow_any_sighash,
+        nonces,
+    )
+}
+
+/// First round of anti-exfil signing: the nonce commitment of each of our keys
+/// in the PSBT, for the host commitment of each input.
+pub fn decode_psbt_signer_commitments(
+    psbt: &[u8],
+    wallet: &mut Wallet,
+    password: &str,
+    network: Network,
+    allow_unverified_inputs: bool,
+    host_commitments: &[[u8; 32]],
+) -> Result<Vec<SignerCommitment>> {
+    let mut psbt = decode_psbt(psbt)?;
+    let mut commitments = Vec::new();
+    sign_with_wallet(
+        &mut psbt,
+        wallet,
+        password,
+        network,
+        allow_unverified_inputs,
+        Nonces::AntiExfilCommit(host_commitments, &mut commitments),
+    )?;
+    if commitments.is_empty() {
+        bail!("No private keys to sign this psbt");
+    }
+    Ok(commitments)
+}
+
+/// Second round of anti-exfil signing: signs as [`decode_psbt_and_sign`] does
+/// with the nonces committed to in the first round, tweaked by the revealed
+/// host data of each input.
+pub fn decode_psbt_and_sign_anti_exfil(
+    psbt: &[u8],
+    wallet: &mut Wallet,
+    password: &str,
+    network: Network,
+    allow_unverified_inputs: bool,
+    host_data: &[[u8; 32]],
+) -> Result<String> {
+    let mut psbt = decode_psbt(psbt)?;
+    let signed_inputs = sign_with_wallet(
+        &mut psbt,
+        wallet,
+        password,
+        network,
+        allow_unverified_inputs,
+        Nonces::AntiExfil(host_data),
+    )?;
+    if signed_inputs == 0 {
+        bail!("No private keys to sign this psbt");
+    }
+    Ok(to_base64(&serialize(&psbt)))
+}
 
-    Ok(to_base64(&serialize(&signed_psbt)))
+/// Host check of anti-exfil signing: the signature of each committed key uses
+/// its committed nonce tweaked by the host data of the input.
+pub fn verify_anti_exfil_signatures(
+    psbt: &[u8],
+    host_data: &[[u8; 32]],
+    commitments: &[SignerCommitment],
+) -> Result<()> {
+    let psbt = decode_psbt(psbt)?;
+    let secp = Secp256k1::verification_only();
+    let mut sighash_cache = SighashCache::new(&psbt.unsigned_tx);
+
+    for commitment in commitments {
+        let index = commitment.input;
+        let (input, host_data) = psbt
+            .inputs
+            .get(index)
+            .zip(host_data.get(index))
+            .with_context(|| format!("Input {index} isn't in the PSBT"))?;
+        let public_key = commitment.public_key;
+        let sig = input
+            .partial_sigs
+            .get(&PublicKey::new(public_key))
+            .with_context(|| format!("Input {index} isn't signed by {public_key}"))?;
+        let witness_script = input
+            .witness_script
+            .as_ref()
+            .context("Missing witness script")?;
+        let amount = input_utxo(&psbt, index)?.value;
+        let sighash =
+            sighash_cache.segwit_signature_hash(index, witness_script, amount, sig.hash_ty)?;
+        anti_exfil::verify(
+            &secp,
+            &public_key,
+            &Message::from_slice(&sighash)?,
+            &sig.sig,
+            host_data,
+            &commitment.commitment,
+        )
+        .with_context(|| format!("Input {index} signed by {public_key}"))?;
+    }
+    Ok(())
+}
+
+/// Whether `wallet` has keys in the PSBT, looking for its master fingerprint
+/// and derivation in the input key origins. `None` when the wallet doesn't
+/// know its master fingerprint.
+pub fn wallet_participates(wallet: &Wallet, psbt: &PartiallySignedTransaction) -> Option<bool> {
+    let master_fingerprint = wallet.master_fingerprint?;
+    let mut origins = psbt.inputs.iter().flat_map(|input| {
+        input
+            .bip32_derivation
+            .values()
+            .chain(input.tap_key_origins.values().map(|(_, origin)| origin))
+    });
+    Some(origins.any(|(fingerprint, path)| {
+        *fingerprint == master_fingerprint && path.as_ref().starts_with(wallet.derivation.as_ref())
+    }))
+}
+
+/// Wallets with keys in a PSBT, and the ones that can't be told apart because
+/// they don't know their master fingerprint.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SigningWallets {
+    pub matching: Vec<String>,
+    pub unknown: Vec<String>,
+}
+
+pub fn decode_psbt_signing_wallets(psbt: &[u8], wallets: &[Wallet]) -> Result<SigningWallets> {
+    let psbt = decode_psbt(psbt)?;
+    let mut signing_wallets = SigningWallets::default();
+    for wallet in wallets {
+        match wallet_participates(wallet, &psbt) {
+            Some(true) => signing_wallets.matching.push(wallet.name.clone()),
+            Some(false) => {}
+            None => signing_wallets.unknown.push(wallet.name.clone()),
+        }
+    }
+    Ok(signing_wallets)
 }
 
 #[test]
@@ -140,18 +855,780 @@ fn derives_partial() {
     assert_eq!(result, partial_derivation);
 }
 
+#[test]
+fn rejects_unrelated_derivation() {
+    let derivation = DerivationPath::from_str("m/48'/1'/1'").unwrap();
+    let sub_derivation = DerivationPath::from_str("m/44'/0'/5'/0/0").unwrap();
+    let error = get_partial_derivation(&derivation, &sub_derivation).unwrap_err();
+    assert!(error.to_string().contains("m/44'/0'/5'/0/0"));
+}
+
+#[test]
+fn allows_derivations() {
+    let allowed = [
+        "m/48'/1'/1'/0/242",
+        "m/48'/0'/0'/2'/1/5",
+        "m/84'/0'/0'/0/0",
+        "m/45'/3/0/7",
+    ];
+    let rejected = [
+        "m/48'/1'/1'/4'/0/0",
+        "m/84'/0'/0'/0'/0",
+        "m/84'/0'/0'/0",
+        "m/0/0",
+        "m/1'/1'/0/0",
+    ];
+    for path in allowed {
+        assert!(check_allowed_derivation(&DerivationPath::from_str(path).unwrap()).is_ok());
+    }
+    for path in rejected {
+        assert!(check_allowed_derivation(&DerivationPath::from_str(path).unwrap()).is_err());
+    }
+}
+
+#[cfg(test)]
+const PSBT: &[u8] = b"cHNidP8BAIcBAAAAAvTkJTkzr4vYO7aoN4AUZaL3BkMWYQ+mYPFBtjhVmvCSAAAAAAD/////glBxTfjYVipVX+gO+YUDCVSJdWtsFo7aTRQ25n0zqwMAAAAAAP////8BbcQAAAAAAAAiACCNY6b6UCml4qW1ojbPwqJ17aaifLtEGzgCwA97J8cCuAAAAABPAQQ1h88DLxEkLIAAAAH8O+5COgGM620iagZjKI7Q+17g+p15CrNw0DDgRzDmyAOAji4c1IK90HGWlDxagTPRaa+GAitrsDgXcWROwHRE1xAfzOTeMAAAgAEAAIABAACATwEENYfPA1asA/SAAAAB+QiuSmaw/6GrFLVa8M5I8qcCJcuQEJAtaQGvkLk+j4ECUa8V4Fu4t+rBiVs/X0f/7+zYMhoC7yIpj4nQogN98GAQYPOgszAAAIABAACAAQAAgE8BBDWHzwN9uUaNgAAAAfUicMdoejr7NR708bmjDBDzttOSFwaDrqlIjBOaEcmYAyIjhSM46mf7/Y3DUqsmJeeaJ3ZGISNr8cAhMJDTSJ6fEO0CEMgwAACAAQAAgAEAAIAAAQErU8MAAAAAAAAiACADQDxIJhQEIWqn08T37W/Ih8uXW40ehgg3slu0Uf7dRQEDBAIAAAABBWlSIQJDnVTFh6pHCQmatEEVvV6sf1P5+u9Bb7zhz0D829Ay2CEDC0VHn9A3/8QMIRBpZobsevmCDpADo8aiHknTJs9b234hA+HpTXYQbhjCPCQRmQlLH4fKO27ghsThI+ztTmujeM2ZU64iBgJDnVTFh6pHCQmatEEVvV6sf1P5+u9Bb7zhz0D829Ay2BjtAhDIMAAAgAEAAIABAACAAAAAAPIAAAAiBgMLRUef0Df/xAwhEGlmhux6+YIOkAOjxqIeSdMmz1vbfhgfzOTeMAAAgAEAAIABAACAAAAAAPIAAAAiBgPh6U12EG4YwjwkEZkJSx+Hyjtu4IbE4SPs7U5ro3jNmRhg86CzMAAAgAEAAIABAACAAAAAAPIAAAAAAQErOgUAAAAAAAAiACBdERsXcaX+/OJlazhLTNrrL9dmXPtIFX3+wl/vS3rlbgEDBAIAAAABBWlSIQI8/6zXULU4/iE7RqJR664w7V/JTpxAxkBflCGOxcM8CiECTbNidmQvwtF0A/gs/Q/H4yNY+Vhw0h7ZSQ+MZk/T0PMhAtEgc7jMqsCndxV78/4aPsQ5PCOZoIBbzLmbkAzh6xQLU64iBgI8/6zXULU4/iE7RqJR664w7V/JTpxAxkBflCGOxcM8ChgfzOTeMAAAgAEAAIABAACAAAAAAPUAAAAiBgJNs2J2ZC/C0XQD+Cz9D8fjI1j5WHDSHtlJD4xmT9PQ8xhg86CzMAAAgAEAAIABAACAAAAAAPUAAAAiBgLRIHO4zKrAp3cVe/P+Gj7EOTwjmaCAW8y5m5AM4esUCxjtAhDIMAAAgAEAAIABAACAAAAAAPUAAAAAAA==";
+
 #[test]
 fn sign() {
     let mut wallet = Wallet::default();
-    wallet.from_seed_str("wallet 1", "social mango annual basic work brain economy one safe physical junk other toy valid load cook napkin maple runway island oil fan legend stem", "Qwerty123").unwrap();
+    wallet.from_seed_str("wallet 1", "social mango annual basic work brain economy one safe physical junk other toy valid load cook napkin maple runway island oil fan legend stem", "m", Network::Regtest, "Qwerty123").unwrap();
+    // PSBT requests SIGHASH_NONE, as NodeGuard does
+    wallet.allow_any_sighash = true;
+
+    // wallet.from_xprv_str("wallet 1", "tprv8ZgxMBicQKsPduvXYAnkop1b1UoAY2pS68pe9jHuJwuMvx6G5sh4C67peYZkRawdBWbMbfoybgQJ3g8nTZAezEeHyaW9A9UjtpTRmSyJwUn", "m", None, Network::Regtest, "Qwerty123").unwrap();
+    let signed_psbt =
+        decode_psbt_and_sign(PSBT, &mut wallet, "Qwerty123", Network::Regtest, true, None);
+    dbg!("{:?}", &signed_psbt);
+    assert!(signed_psbt.is_ok());
+}
+
+#[test]
+fn sign_account_wallet() {
+    let mut wallet = Wallet::default();
+    wallet.from_seed_str("wallet 1", "social mango annual basic work brain economy one safe physical junk other toy valid load cook napkin maple runway island oil fan legend stem", "m", Network::Regtest, "Qwerty123").unwrap();
+    let mut account = wallet
+        .derive_account_wallet("wallet 2", "m/48'/1'/1'")
+        .unwrap();
+    account.allow_any_sighash = true;
+    let mut unrelated = wallet
+        .derive_account_wallet("wallet 3", "m/48'/1'/2'")
+        .unwrap();
+    unrelated.allow_any_sighash = true;
+
+    assert!(decode_psbt_and_sign(
+        PSBT,
+        &mut account,
+        "Qwerty123",
+        Network::Regtest,
+        true,
+        None
+    )
+    .is_ok());
+    assert!(decode_psbt_and_sign(
+        PSBT,
+        &mut unrelated,
+        "Qwerty123",
+        Network::Regtest,
+        true,
+        None
+    )
+    .is_err());
+}
+
+#[test]
+fn sign_wrong_network() {
+    let mut wallet = Wallet::default();
+    wallet.from_seed_str("wallet 1", "social mango annual basic work brain economy one safe physical junk other toy valid load cook napkin maple runway island oil fan legend stem", "m", Network::Bitcoin, "Qwerty123").unwrap();
+    wallet.allow_any_sighash = true;
+
+    let signed_psbt =
+        decode_psbt_and_sign(PSBT, &mut wallet, "Qwerty123", Network::Regtest, true, None);
+    assert!(signed_psbt.is_err());
+}
+
+#[test]
+fn refuses_unverified_inputs() {
+    let mut wallet = Wallet::default();
+    wallet.from_seed_str("wallet 1", "social mango annual basic work brain economy one safe physical junk other toy valid load cook napkin maple runway island oil fan legend stem", "m", Network::Regtest, "Qwerty123").unwrap();
 
-    // wallet.from_xprv_str("wallet 1", "tprv8ZgxMBicQKsPduvXYAnkop1b1UoAY2pS68pe9jHuJwuMvx6G5sh4C67peYZkRawdBWbMbfoybgQJ3g8nTZAezEeHyaW9A9UjtpTRmSyJwUn", "m/48'/1'/1'", "Qwerty123").unwrap();
-    let signed_psbt = decode_psbt_and_sign(
-        "cHNidP8BAIcBAAAAAvTkJTkzr4vYO7aoN4AUZaL3BkMWYQ+mYPFBtjhVmvCSAAAAAAD/////glBxTfjYVipVX+gO+YUDCVSJdWtsFo7aTRQ25n0zqwMAAAAAAP////8BbcQAAAAAAAAiACCNY6b6UCml4qW1ojbPwqJ17aaifLtEGzgCwA97J8cCuAAAAABPAQQ1h88DLxEkLIAAAAH8O+5COgGM620iagZjKI7Q+17g+p15CrNw0DDgRzDmyAOAji4c1IK90HGWlDxagTPRaa+GAitrsDgXcWROwHRE1xAfzOTeMAAAgAEAAIABAACATwEENYfPA1asA/SAAAAB+QiuSmaw/6GrFLVa8M5I8qcCJcuQEJAtaQGvkLk+j4ECUa8V4Fu4t+rBiVs/X0f/7+zYMhoC7yIpj4nQogN98GAQYPOgszAAAIABAACAAQAAgE8BBDWHzwN9uUaNgAAAAfUicMdoejr7NR708bmjDBDzttOSFwaDrqlIjBOaEcmYAyIjhSM46mf7/Y3DUqsmJeeaJ3ZGISNr8cAhMJDTSJ6fEO0CEMgwAACAAQAAgAEAAIAAAQErU8MAAAAAAAAiACADQDxIJhQEIWqn08T37W/Ih8uXW40ehgg3slu0Uf7dRQEDBAIAAAABBWlSIQJDnVTFh6pHCQmatEEVvV6sf1P5+u9Bb7zhz0D829Ay2CEDC0VHn9A3/8QMIRBpZobsevmCDpADo8aiHknTJs9b234hA+HpTXYQbhjCPCQRmQlLH4fKO27ghsThI+ztTmujeM2ZU64iBgJDnVTFh6pHCQmatEEVvV6sf1P5+u9Bb7zhz0D829Ay2BjtAhDIMAAAgAEAAIABAACAAAAAAPIAAAAiBgMLRUef0Df/xAwhEGlmhux6+YIOkAOjxqIeSdMmz1vbfhgfzOTeMAAAgAEAAIABAACAAAAAAPIAAAAiBgPh6U12EG4YwjwkEZkJSx+Hyjtu4IbE4SPs7U5ro3jNmRhg86CzMAAAgAEAAIABAACAAAAAAPIAAAAAAQErOgUAAAAAAAAiACBdERsXcaX+/OJlazhLTNrrL9dmXPtIFX3+wl/vS3rlbgEDBAIAAAABBWlSIQI8/6zXULU4/iE7RqJR664w7V/JTpxAxkBflCGOxcM8CiECTbNidmQvwtF0A/gs/Q/H4yNY+Vhw0h7ZSQ+MZk/T0PMhAtEgc7jMqsCndxV78/4aPsQ5PCOZoIBbzLmbkAzh6xQLU64iBgI8/6zXULU4/iE7RqJR664w7V/JTpxAxkBflCGOxcM8ChgfzOTeMAAAgAEAAIABAACAAAAAAPUAAAAiBgJNs2J2ZC/C0XQD+Cz9D8fjI1j5WHDSHtlJD4xmT9PQ8xhg86CzMAAAgAEAAIABAACAAAAAAPUAAAAiBgLRIHO4zKrAp3cVe/P+Gj7EOTwjmaCAW8y5m5AM4esUCxjtAhDIMAAAgAEAAIABAACAAAAAAPUAAAAAAA==",
+    let psbt = decode_psbt(PSBT).unwrap();
+    assert_eq!(unverified_inputs(&psbt), vec![0, 1]);
+    let error = decode_psbt_and_sign(
+        PSBT,
         &mut wallet,
         "Qwerty123",
         Network::Regtest,
+        false,
+        None,
+    )
+    .unwrap_err();
+    assert!(error.to_string().contains("previous transactions"));
+}
+
+#[test]
+fn refuses_sighash_types() {
+    let mut wallet = Wallet::default();
+    wallet.from_seed_str("wallet 1", "social mango annual basic work brain economy one safe physical junk other toy valid load cook napkin maple runway island oil fan legend stem", "m", Network::Regtest, "Qwerty123").unwrap();
+
+    let psbt = decode_psbt(PSBT).unwrap();
+    assert_eq!(sighash_types(&psbt), vec!["SIGHASH_NONE", "SIGHASH_NONE"]);
+    let error = decode_psbt_and_sign(PSBT, &mut wallet, "Qwerty123", Network::Regtest, true, None)
+        .unwrap_err();
+    assert!(error.to_string().contains("SIGHASH_NONE"));
+
+    let mut input = Input::default();
+    assert!(check_sighash_type(&input, 0, false).is_ok());
+    input.sighash_type = Some(bitcoin::psbt::PsbtSighashType::from_u32(0x81));
+    assert!(check_sighash_type(&input, 0, false).is_err());
+    assert!(check_sighash_type(&input, 0, true).is_ok());
+    input.sighash_type = Some(bitcoin::psbt::PsbtSighashType::from_u32(0x42));
+    assert!(check_sighash_type(&input, 0, true).is_err());
+}
+
+#[test]
+fn shows_taproot_sighash_types() {
+    use bitcoin::psbt::PsbtSighashType;
+    use bitcoin::{OutPoint, PackedLockTime, Script, Sequence, TxIn};
+
+    let txin = TxIn {
+        previous_output: OutPoint::null(),
+        script_sig: Script::new(),
+        sequence: Sequence::MAX,
+        witness: Default::default(),
+    };
+    let tx = Transaction {
+        version: 2,
+        lock_time: PackedLockTime::ZERO,
+        input: vec![txin.clone(), txin.clone(), txin],
+        output: Vec::new(),
+    };
+    let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx).unwrap();
+    let secp = Secp256k1::new();
+    let internal_key = KeyPair::from_seckey_slice(&secp, &[1; 32])
+        .unwrap()
+        .x_only_public_key()
+        .0;
+    for input in &mut psbt.inputs[..2] {
+        input.tap_internal_key = Some(internal_key);
+    }
+    psbt.inputs[0].sighash_type = Some(PsbtSighashType::from_u32(0x00));
+    psbt.inputs[2].sighash_type = Some(PsbtSighashType::from_u32(0x00));
+
+    assert_eq!(
+        sighash_types(&psbt),
+        vec![
+            "SIGHASH_DEFAULT",
+            "SIGHASH_DEFAULT",
+            "Invalid sighash type SIGHASH_DEFAULT"
+        ]
     );
-    dbg!("{:?}", &signed_psbt);
-    assert!(signed_psbt.is_ok());
+}
+
+#[test]
+fn checks_non_witness_utxo() {
+    use bitcoin::{OutPoint, PackedLockTime, Script, Sequence, Transaction, TxIn};
+
+    let utxo = TxOut {
+        value: 50_000,
+        script_pubkey: Script::new_op_return(&[1]),
+    };
+    let previous_tx = Transaction {
+        version: 2,
+        lock_time: PackedLockTime::ZERO,
+        input: vec![],
+        output: vec![utxo.clone()],
+    };
+    let spend = |txid| {
+        let tx = Transaction {
+            version: 2,
+            lock_time: PackedLockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint { txid, vout: 0 },
+                script_sig: Script::new(),
+                sequence: Sequence::MAX,
+                witness: Default::default(),
+            }],
+            output: vec![],
+        };
+        let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx).unwrap();
+        psbt.inputs[0].non_witness_utxo = Some(previous_tx.clone());
+        psbt.inputs[0].witness_utxo = Some(utxo.clone());
+        psbt
+    };
+
+    let mut psbt = spend(previous_tx.txid());
+    assert_eq!(input_utxo(&psbt, 0).unwrap(), utxo);
+    assert!(unverified_inputs(&psbt).is_empty());
+
+    psbt.inputs[0].witness_utxo.as_mut().unwrap().value = 40_000;
+    assert!(input_utxo(&psbt, 0).is_err());
+
+    assert!(input_utxo(&spend(OutPoint::null().txid), 0).is_err());
+}
+
+#[test]
+fn signs_tapscript_leaves() {
+    use bitcoin::blockdata::opcodes::all::OP_CHECKSIG;
+    use bitcoin::blockdata::script::Builder;
+    use bitcoin::secp256k1::SecretKey;
+    use bitcoin::util::taproot::{LeafVersion, TapLeafHash, TaprootBuilder};
+    use bitcoin::{OutPoint, PackedLockTime, Script, Sequence, TxIn};
+
+    let secp = Secp256k1::new();
+    let xprv = ExtendedPrivKey::new_master(Network::Regtest, &[5; 32]).unwrap();
+    let fingerprint = xprv.fingerprint(&secp);
+    let path = DerivationPath::from_str("m/86'/1'/0'/0/0").unwrap();
+    let (xonly, _) = xprv
+        .derive_priv(&secp, &path)
+        .unwrap()
+        .to_keypair(&secp)
+        .x_only_public_key();
+    let (internal_key, _) = SecretKey::from_slice(&[9; 32])
+        .unwrap()
+        .x_only_public_key(&secp);
+
+    let leaf = Builder::new()
+        .push_slice(&xonly.serialize())
+        .push_opcode(OP_CHECKSIG)
+        .into_script();
+    let leaf_hash = TapLeafHash::from_script(&leaf, LeafVersion::TapScript);
+    let spend_info = TaprootBuilder::new()
+        .add_leaf(0, leaf.clone())
+        .unwrap()
+        .finalize(&secp, internal_key)
+        .unwrap();
+    let control_block = spend_info
+        .control_block(&(leaf.clone(), LeafVersion::TapScript))
+        .unwrap();
+
+    let tx = Transaction {
+        version: 2,
+        lock_time: PackedLockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint::null(),
+            script_sig: Script::new(),
+            sequence: Sequence::MAX,
+            witness: Default::default(),
+        }],
+        output: vec![TxOut {
+            value: 1000,
+            script_pubkey: Script::new(),
+        }],
+    };
+    let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx).unwrap();
+    let input = &mut psbt.inputs[0];
+    input.witness_utxo = Some(TxOut {
+        value: 2000,
+        script_pubkey: Script::new_v1_p2tr(&secp, internal_key, spend_info.merkle_root()),
+    });
+    input.tap_internal_key = Some(internal_key);
+    input.tap_merkle_root = spend_info.merkle_root();
+    input
+        .tap_scripts
+        .insert(control_block, (leaf, LeafVersion::TapScript));
+    input
+        .tap_key_origins
+        .insert(xonly, (vec![leaf_hash], (fingerprint, path)));
+
+    let derivation = DerivationPath::master();
+    let mut signed = psbt.clone();
+    let signed_inputs = sign_psbt(
+        &mut signed,
+        xprv,
+        fingerprint,
+        &derivation,
+        &[],
+        false,
+        false,
+        Nonces::Deterministic,
+    );
+    assert_eq!(signed_inputs.unwrap(), 1);
+    assert!(signed.inputs[0].tap_key_sig.is_none());
+    assert!(signed.inputs[0]
+        .tap_script_sigs
+        .contains_key(&(xonly, leaf_hash)));
+
+    let other = ExtendedPrivKey::new_master(Network::Regtest, &[6; 32]).unwrap();
+    let other_fingerprint = other.fingerprint(&secp);
+    let signed_inputs = sign_psbt(
+        &mut psbt,
+        other,
+        other_fingerprint,
+        &derivation,
+        &[],
+        false,
+        false,
+        Nonces::Deterministic,
+    );
+    assert_eq!(signed_inputs.unwrap(), 0);
+}
+
+#[test]
+fn checks_cosigner_signatures() {
+    let mut wallet = Wallet::default();
+    wallet.from_seed_str("wallet 1", "social mango annual basic work brain economy one safe physical junk other toy valid load cook napkin maple runway island oil fan legend stem", "m", Network::Regtest, "Qwerty123").unwrap();
+    wallet.allow_any_sighash = true;
+
+    let signed =
+        decode_psbt_and_sign(PSBT, &mut wallet, "Qwerty123", Network::Regtest, true, None).unwrap();
+    let psbt = decode_psbt(signed.as_bytes()).unwrap();
+    assert!(invalid_partial_sigs(&psbt).is_empty());
+    assert!(decode_psbt_and_sign(
+        signed.as_bytes(),
+        &mut wallet,
+        "Qwerty123",
+        Network::Regtest,
+        true,
+        None
+    )
+    .is_ok());
+
+    let secp = Secp256k1::new();
+    let secret_key = bitcoin::secp256k1::SecretKey::from_slice(&[1; 32]).unwrap();
+    let message = Message::from_slice(&[1; 32]).unwrap();
+    let mut corrupted = psbt.clone();
+    let input = &mut corrupted.inputs[0];
+    let pubkey = input
+        .bip32_derivation
+        .keys()
+        .map(|key| PublicKey::new(*key))
+        .find(|key| !input.partial_sigs.contains_key(key))
+        .unwrap();
+    input.partial_sigs.insert(
+        pubkey,
+        EcdsaSig::sighash_all(secp.sign_ecdsa(&message, &secret_key)),
+    );
+    assert_eq!(
+        invalid_partial_sigs(&corrupted),
+        vec![format!("Input 0 has an invalid signature from {pubkey}")]
+    );
+    // the cosigner's signature doesn't stop ours, it's left for the user to review
+    let signed = decode_psbt_and_sign(
+        to_base64(&serialize(&corrupted)).as_bytes(),
+        &mut wallet,
+        "Qwerty123",
+        Network::Regtest,
+        true,
+        None,
+    )
+    .unwrap();
+    let signed = decode_psbt(signed.as_bytes()).unwrap();
+    assert_eq!(
+        invalid_partial_sigs(&signed),
+        invalid_partial_sigs(&corrupted)
+    );
+
+    let mut foreign = psbt;
+    let foreign_key = PublicKey::new(secret_key.public_key(&secp));
+    foreign.inputs[0].partial_sigs.insert(
+        foreign_key,
+        EcdsaSig::sighash_all(secp.sign_ecdsa(&message, &secret_key)),
+    );
+    assert_eq!(
+        invalid_partial_sigs(&foreign),
+        vec![format!(
+            "Input 0 has a signature from {foreign_key}, which isn't a key of its script"
+        )]
+    );
+}
+
+#[test]
+fn signs_batches() {
+    let mut wallet = Wallet::default();
+    wallet.from_seed_str("wallet 1", "social mango annual basic work brain economy one safe physical junk other toy valid load cook napkin maple runway island oil fan legend stem", "m", Network::Regtest, "Qwerty123").unwrap();
+    wallet.allow_any_sighash = true;
+
+    let psbts: [&[u8]; 3] = [PSBT, b"not a psbt", PSBT];
+    let results =
+        decode_psbts_and_sign(&psbts, &mut wallet, "Qwerty123", Network::Regtest, true).unwrap();
+    assert_eq!(results.len(), 3);
+    assert!(results[0].is_ok());
+    assert!(results[1].is_err());
+    assert_eq!(results[0].as_ref().unwrap(), results[2].as_ref().unwrap());
+
+    assert!(decode_psbts_and_sign(&psbts, &mut wallet, "wrong", Network::Regtest, true).is_err());
+}
+
+#[test]
+fn finds_signing_wallets() {
+    let mut wallet = Wallet::default();
+    wallet.from_seed_str("wallet 1", "social mango annual basic work brain economy one safe physical junk other toy valid load cook napkin maple runway island oil fan legend stem", "m", Network::Regtest, "Qwerty123").unwrap();
+    wallet.allow_any_sighash = true;
+    let mut account = wallet
+        .derive_account_wallet("wallet 2", "m/48'/1'/1'")
+        .unwrap();
+    account.allow_any_sighash = true;
+    let unrelated = wallet
+        .derive_account_wallet("wallet 3", "m/48'/1'/2'")
+        .unwrap();
+    let mut legacy = Wallet::default();
+    legacy.name = "wallet 4".to_string();
+
+    let wallets = vec![wallet, account, unrelated, legacy];
+    assert_eq!(
+        decode_psbt_signing_wallets(PSBT, &wallets).unwrap(),
+        SigningWallets {
+            matching: vec!["wallet 1".to_string(), "wallet 2".to_string()],
+            unknown: vec!["wallet 4".to_string()],
+        }
+    );
+
+    let mut wallets = wallets;
+    let signed = decode_psbt_and_sign_with_wallets(
+        PSBT,
+        wallets.iter_mut().take(2),
+        "Qwerty123",
+        Network::Regtest,
+        true,
+        None,
+    )
+    .unwrap();
+    let psbt = decode_psbt(signed.as_bytes()).unwrap();
+    assert!(psbt
+        .inputs
+        .iter()
+        .all(|input| input.partial_sigs.len() == 1));
+
+    // keys of another account of the seed aren't skipped, they fail naming their path
+    let error = decode_psbt_and_sign_with_wallets(
+        PSBT,
+        wallets.iter_mut().skip(2).take(1),
+        "Qwerty123",
+        Network::Regtest,
+        true,
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(
+        format!("{error:#}"),
+        "Wallet wallet 3: The derivation path m/48'/1'/1'/0/242 doesn't extend the wallet derivation m/48'/1'/2'"
+    );
+    // unless wallet 1 or wallet 2, whose derivations they extend, sign along
+    // and wallet 3 leaves them to it
+    for skip in [0, 1] {
+        let signed = decode_psbt_and_sign_with_wallets(
+            PSBT,
+            wallets.iter_mut().skip(skip).step_by(2 - skip).take(2),
+            "Qwerty123",
+            Network::Regtest,
+            true,
+            None,
+        )
+        .unwrap();
+        let psbt = decode_psbt(signed.as_bytes()).unwrap();
+        assert!(psbt
+            .inputs
+            .iter()
+            .all(|input| input.partial_sigs.len() == 1));
+    }
+    let error = decode_psbt_and_sign_with_wallets(
+        PSBT,
+        wallets.iter_mut().skip(3),
+        "Qwerty123",
+        Network::Regtest,
+        true,
+        None,
+    )
+    .unwrap_err();
+    assert!(error.to_string().starts_with("Wallet wallet 4"));
+    assert!(decode_psbt_and_sign_with_wallets(
+        PSBT,
+        Vec::new(),
+        "Qwerty123",
+        Network::Regtest,
+        true,
+        None
+    )
+    .is_err());
+}
+
+#[test]
+fn signs_inputs_of_several_wallets() {
+    use bitcoin::blockdata::opcodes::all::OP_CHECKSIG;
+    use bitcoin::blockdata::script::Builder;
+    use bitcoin::{OutPoint, PackedLockTime, Script, Sequence, TxIn};
+
+    let secp = Secp256k1::new();
+    let path = DerivationPath::from_str("m/84'/1'/0'/0/0").unwrap();
+    let mut wallets: Vec<Wallet> = [1u8, 2, 3]
+        .iter()
+        .map(|seed| {
+            let xprv = ExtendedPrivKey::new_master(Network::Regtest, &[*seed; 32]).unwrap();
+            let mut wallet = Wallet::default();
+            let name = format!("wallet {seed}");
+            wallet
+                .from_xprv_str(
+                    &name,
+                    &xprv.to_string(),
+                    "m",
+                    None,
+                    Network::Regtest,
+                    "Qwerty123",
+                )
+                .unwrap();
+            wallet
+        })
+        .collect();
+
+    let txin = |vout| TxIn {
+        previous_output: OutPoint {
+            txid: OutPoint::null().txid,
+            vout,
+        },
+        script_sig: Script::new(),
+        sequence: Sequence::MAX,
+        witness: Default::default(),
+    };
+    let tx = Transaction {
+        version: 2,
+        lock_time: PackedLockTime::ZERO,
+        input: vec![txin(0), txin(1)],
+        output: vec![TxOut {
+            value: 1000,
+            script_pubkey: Script::new(),
+        }],
+    };
+    let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx).unwrap();
+    // the first wallet owns the first input and the second wallet the other one
+    for (input, wallet) in psbt.inputs.iter_mut().zip(wallets.iter_mut()) {
+        let xprv = wallet.get_xprv("Qwerty123", Network::Regtest).unwrap();
+        let pubkey = xprv
+            .derive_priv(&secp, &path)
+            .unwrap()
+            .private_key
+            .public_key(&secp);
+        let witness_script = Builder::new()
+            .push_slice(&pubkey.serialize())
+            .push_opcode(OP_CHECKSIG)
+            .into_script();
+        input.witness_utxo = Some(TxOut {
+            value: 2000,
+            script_pubkey: witness_script.to_v0_p2wsh(),
+        });
+        input.witness_script = Some(witness_script);
+        input
+            .bip32_derivation
+            .insert(pubkey, (xprv.fingerprint(&secp), path.clone()));
+    }
+    let psbt = to_base64(&serialize(&psbt));
+
+    let signed = decode_psbt_and_sign(
+        psbt.as_bytes(),
+        &mut wallets[0],
+        "Qwerty123",
+        Network::Regtest,
+        true,
+        None,
+    )
+    .unwrap();
+    let signed = decode_psbt(signed.as_bytes()).unwrap();
+    assert_eq!(signed.inputs[0].partial_sigs.len(), 1);
+    assert!(signed.inputs[1].partial_sigs.is_empty());
+
+    let signed = decode_psbt_and_sign_with_wallets(
+        psbt.as_bytes(),
+        wallets.iter_mut(),
+        "Qwerty123",
+        Network::Regtest,
+        true,
+        None,
+    )
+    .unwrap();
+    let signed = decode_psbt(signed.as_bytes()).unwrap();
+    assert!(signed
+        .inputs
+        .iter()
+        .all(|input| input.partial_sigs.len() == 1));
+
+    let error = decode_psbt_and_sign_with_wallets(
+        psbt.as_bytes(),
+        wallets.iter_mut().skip(2),
+        "Qwerty123",
+        Network::Regtest,
+        true,
+        None,
+    )
+    .unwrap_err();
+    assert!(error.to_string().contains("No private keys"));
+}
+
+#[test]
+fn signs_anti_exfil() {
+    let mut wallet = Wallet::default();
+    wallet.from_seed_str("wallet 1", "social mango annual basic work brain economy one safe physical junk other toy valid load cook napkin maple runway island oil fan legend stem", "m", Network::Regtest, "Qwerty123").unwrap();
+    wallet.allow_any_sighash = true;
+
+    let host_data = [[7; 32], [8; 32]];
+    let host_commitments = host_data.map(|data| anti_exfil::host_commitment(&data));
+    let commitments = decode_psbt_signer_commitments(
+        PSBT,
+        &mut wallet,
+        "Qwerty123",
+        Network::Regtest,
+        true,
+        &host_commitments,
+    )
+    .unwrap();
+    assert_eq!(
+        commitments.iter().map(|c| c.input).collect::<Vec<_>>(),
+        vec![0, 1]
+    );
+
+    let signed = decode_psbt_and_sign_anti_exfil(
+        PSBT,
+        &mut wallet,
+        "Qwerty123",
+        Network::Regtest,
+        true,
+        &host_data,
+    )
+    .unwrap();
+    assert!(verify_anti_exfil_signatures(signed.as_bytes(), &host_data, &commitments).is_ok());
+    let other_data = [[7; 32], [9; 32]];
+    let error = verify_anti_exfil_signatures(signed.as_bytes(), &other_data, &commitments);
+    assert!(error.unwrap_err().to_string().starts_with("Input 1"));
+
+    // the usual signing doesn't use the committed nonces
+    let signed =
+        decode_psbt_and_sign(PSBT, &mut wallet, "Qwerty123", Network::Regtest, true, None).unwrap();
+    assert!(verify_anti_exfil_signatures(signed.as_bytes(), &host_data, &commitments).is_err());
+    assert!(decode_psbt_and_sign_anti_exfil(
+        PSBT,
+        &mut wallet,
+        "Qwerty123",
+        Network::Regtest,
+        true,
+        &host_data[..1],
+    )
+    .is_err());
+}
+
+#[test]
+fn checks_requested_operation() {
+    let mut wallet = Wallet::default();
+    wallet.from_seed_str("wallet 1", "social mango annual basic work brain economy one safe physical junk other toy valid load cook napkin maple runway island oil fan legend stem", "m", Network::Regtest, "Qwerty123").unwrap();
+    wallet.allow_any_sighash = true;
+
+    let sign = |wallet: &mut Wallet, request: &OperationRequest| {
+        decode_psbt_and_sign(
+            PSBT,
+            wallet,
+            "Qwerty123",
+            Network::Regtest,
+            true,
+            Some(request),
+        )
+    };
+    let withdrawal = OperationRequest {
+        request_type: Some("Withdrawal"),
+        ..Default::default()
+    };
+    assert!(sign(&mut wallet, &withdrawal).is_ok());
+
+    // the only output isn't a channel funding output of the stated amount
+    let channel = OperationRequest {
+        request_type: Some("Channel Request"),
+        amount: Some("0.001"),
+        ..Default::default()
+    };
+    let error = sign(&mut wallet, &channel).unwrap_err();
+    assert!(error.to_string().starts_with("The funding output pays"));
+    let channel = OperationRequest {
+        amount: Some("0.00050285"),
+        ..channel
+    };
+    let error = sign(&mut wallet, &channel).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "The funding output doesn't include its witness script"
+    );
+
+    let error = decode_psbt_and_sign_with_wallets(
+        PSBT,
+        [&mut wallet],
+        "Qwerty123",
+        Network::Regtest,
+        true,
+        Some(&channel),
+    )
+    .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "The funding output doesn't include its witness script"
+    );
+}
+
+#[test]
+fn rejects_keys_outside_wallet_derivation() {
+    use bitcoin::blockdata::opcodes::all::OP_CHECKSIG;
+    use bitcoin::blockdata::script::Builder;
+    use bitcoin::{OutPoint, PackedLockTime, Script, Sequence, TxIn};
+
+    let secp = Secp256k1::new();
+    let mut wallet = Wallet::default();
+    wallet.from_seed_str("wallet 1", "social mango annual basic work brain economy one safe physical junk other toy valid load cook napkin maple runway island oil fan legend stem", "m", Network::Bitcoin, "Qwerty123").unwrap();
+    let mut account = wallet
+        .derive_account_wallet("wallet 2", "m/48'/0'/1'")
+        .unwrap();
+    let master = wallet.get_xprv("Qwerty123", Network::Bitcoin).unwrap();
+
+    // a key of our seed from another account than the wallet's
+    let path = DerivationPath::from_str("m/44'/0'/5'/0/0").unwrap();
+    let pubkey = master
+        .derive_priv(&secp, &path)
+        .unwrap()
+        .private_key
+        .public_key(&secp);
+    let witness_script = Builder::new()
+        .push_slice(&pubkey.serialize())
+        .push_opcode(OP_CHECKSIG)
+        .into_script();
+    let tx = Transaction {
+        version: 2,
+        lock_time: PackedLockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint::null(),
+            script_sig: Script::new(),
+            sequence: Sequence::MAX,
+            witness: Default::default(),
+        }],
+        output: vec![TxOut {
+            value: 1000,
+            script_pubkey: Script::new(),
+        }],
+    };
+    let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx).unwrap();
+    psbt.inputs[0].witness_utxo = Some(TxOut {
+        value: 2000,
+        script_pubkey: witness_script.to_v0_p2wsh(),
+    });
+    psbt.inputs[0].witness_script = Some(witness_script);
+    psbt.inputs[0]
+        .bip32_derivation
+        .insert(pubkey, (master.fingerprint(&secp), path));
+    let psbt = to_base64(&serialize(&psbt));
+
+    let error = decode_psbt_and_sign(
+        psbt.as_bytes(),
+        &mut account,
+        "Qwerty123",
+        Network::Bitcoin,
+        false,
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "The derivation path m/44'/0'/5'/0/0 doesn't extend the wallet derivation m/48'/0'/1'"
+    );
+    assert!(decode_psbt_and_sign(
+        psbt.as_bytes(),
+        &mut wallet,
+        "Qwerty123",
+        Network::Bitcoin,
+        false,
+        None
+    )
+    .is_ok());
 }
@@ -0,0 +1,640 @@
+//! Shamir's Secret-Sharing for Mnemonic Codes, as specified in SLIP-39.
+//! <https://github.com/satoshilabs/slips/blob/master/slip-0039.md>
+
+mod wordlist;
+
+use anyhow::{anyhow, bail, Result};
+use bitcoin::hashes::{sha256, Hash, HashEngine, Hmac, HmacEngine};
+use rand::Rng;
+use std::collections::BTreeMap;
+
+use wordlist::WORDLIST;
+
+const RADIX_BITS: usize = 10;
+const CHECKSUM_WORDS: usize = 3;
+const HEADER_WORDS: usize = 4;
+const MIN_MNEMONIC_WORDS: usize = 20;
+const DIGEST_LENGTH: usize = 4;
+const DIGEST_INDEX: u8 = 254;
+const SECRET_INDEX: u8 = 255;
+const BASE_ITERATION_COUNT: u32 = 10000;
+const ROUND_COUNT: u8 = 4;
+const MAX_SHARE_COUNT: u8 = 16;
+const CUSTOMIZATION_STRING: &[u8] = b"shamir";
+const CUSTOMIZATION_STRING_EXTENDABLE: &[u8] = b"shamir_extendable";
+
+/// Thresholds of a group of shares, i.e. `member_threshold` of `member_count`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct GroupSpec {
+    pub member_threshold: u8,
+    pub member_count: u8,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Share {
+    pub identifier: u16,
+    pub extendable: bool,
+    pub iteration_exponent: u8,
+    pub group_index: u8,
+    pub group_threshold: u8,
+    pub group_count: u8,
+    pub member_index: u8,
+    pub member_threshold: u8,
+    pub value: Vec<u8>,
+}
+
+struct GaloisField {
+    exp: [u8; 255],
+    log: [u8; 256],
+}
+
+impl GaloisField {
+    /// Builds the log and exp tables of GF(256) with the Rijndael polynomial
+    /// x^8 + x^4 + x^3 + x + 1, using 3 as generator.
+    fn new() -> Self {
+        let mut exp = [0u8; 255];
+        let mut log = [0u8; 256];
+        let mut poly: u16 = 1;
+        for (i, e) in exp.iter_mut().enumerate() {
+            *e = poly as u8;
+            log[poly as usize] = i as u8;
+            poly = (poly << 1) ^ poly;
+            if poly & 0x100 != 0 {
+                poly ^= 0x11b;
+            }
+        }
+        Self { exp, log }
+    }
+
+    fn interpolate(&self, shares: &[(u8, Vec<u8>)], x: u8) -> Result<Vec<u8>> {
+        if let Some((_, value)) = shares.iter().find(|(index, _)| *index == x) {
+            return Ok(value.clone());
+        }
+
+        let length = shares.first().map(|(_, v)| v.len()).unwrap_or_default();
+        if shares.iter().any(|(_, v)| v.len() != length) {
+            bail!("All share values must have the same length");
+        }
+
+        let log = |v: u8| u32::from(self.log[v as usize]);
+        let log_prod: u32 = shares.iter().map(|(index, _)| log(index ^ x)).sum();
+
+        let mut result = vec![0u8; length];
+        for (index, value) in shares {
+            let others: u32 = shares
+                .iter()
+                .filter(|(other, _)| other != index)
+                .map(|(other, _)| log(index ^ other))
+                .sum();
+            let log_basis = (log_prod + 255 * shares.len() as u32 - log(index ^ x) - others) % 255;
+
+            for (r, v) in result.iter_mut().zip(value.iter()) {
+                if *v != 0 {
+                    *r ^= self.exp[((log(*v) + log_basis) % 255) as usize];
+                }
+            }
+        }
+        Ok(result)
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut engine = HmacEngine::<sha256::Hash>::new(key);
+    engine.input(data);
+    Hmac::<sha256::Hash>::from_engine(engine).into_inner()
+}
+
+fn pbkdf2_sha256(password: &[u8], salt: &[u8], iterations: u32, length: usize) -> Vec<u8> {
+    let mut output = Vec::with_capacity(length);
+    let mut block: u32 = 1;
+    while output.len() < length {
+        let mut u = hmac_sha256(password, &[salt, &block.to_be_bytes()].concat());
+        let mut t = u;
+        for _ in 1..iterations {
+            u = hmac_sha256(password, &u);
+            t.iter_mut().zip(u.iter()).for_each(|(a, b)| *a ^= b);
+        }
+        output.extend_from_slice(&t);
+        block += 1;
+    }
+    output.truncate(length);
+    output
+}
+
+fn customization_string(extendable: bool) -> &'static [u8] {
+    if extendable {
+        CUSTOMIZATION_STRING_EXTENDABLE
+    } else {
+        CUSTOMIZATION_STRING
+    }
+}
+
+fn rs1024_polymod(values: impl Iterator<Item = u32>) -> u32 {
+    const GEN: [u32; 10] = [
+        0xe0e040, 0x1c1c080, 0x3838100, 0x7070200, 0xe0e0009, 0x1c0c2412, 0x38086c24, 0x3090fc48,
+        0x21b1f890, 0x3f3f120,
+    ];
+    values.fold(1, |chk, v| {
+        let b = chk >> 20;
+        let chk = ((chk & 0xfffff) << 10) ^ v;
+        GEN.iter()
+            .enumerate()
+            .filter(|(i, _)| (b >> i) & 1 == 1)
+            .fold(chk, |chk, (_, g)| chk ^ g)
+    })
+}
+
+fn rs1024_create_checksum(data: &[u32], extendable: bool) -> Vec<u32> {
+    let values = customization_string(extendable)
+        .iter()
+        .map(|c| u32::from(*c))
+        .chain(data.iter().copied())
+        .chain([0; CHECKSUM_WORDS]);
+    let polymod = rs1024_polymod(values) ^ 1;
+    (0..CHECKSUM_WORDS)
+        .map(|i| (polymod >> (RADIX_BITS * (CHECKSUM_WORDS - 1 - i))) & 1023)
+        .collect()
+}
+
+fn rs1024_verify_checksum(data: &[u32], extendable: bool) -> bool {
+    let values = customization_string(extendable)
+        .iter()
+        .map(|c| u32::from(*c))
+        .chain(data.iter().copied());
+    rs1024_polymod(values) == 1
+}
+
+fn round_function(round: u8, passphrase: &[u8], exponent: u8, salt: &[u8], data: &[u8]) -> Vec<u8> {
+    let password = [&[round], passphrase].concat();
+    let iterations = (BASE_ITERATION_COUNT / u32::from(ROUND_COUNT)) << exponent;
+    pbkdf2_sha256(&password, &[salt, data].concat(), iterations, data.len())
+}
+
+fn salt(identifier: u16, extendable: bool) -> Vec<u8> {
+    if extendable {
+        vec![]
+    } else {
+        [CUSTOMIZATION_STRING, &identifier.to_be_bytes()].concat()
+    }
+}
+
+fn feistel(
+    input: &[u8],
+    passphrase: &str,
+    identifier: u16,
+    extendable: bool,
+    exponent: u8,
+    rounds: impl Iterator<Item = u8>,
+) -> Vec<u8> {
+    let half = input.len() / 2;
+    let mut left = input[..half].to_vec();
+    let mut right = input[half..].to_vec();
+    let salt = salt(identifier, extendable);
+
+    for round in rounds {
+        let f = round_function(round, passphrase.as_bytes(), exponent, &salt, &right);
+        let new_right: Vec<u8> = left.iter().zip(f.iter()).map(|(l, f)| l ^ f).collect();
+        left = right;
+        right = new_right;
+    }
+
+    [right, left].concat()
+}
+
+fn encrypt(
+    master_secret: &[u8],
+    passphrase: &str,
+    identifier: u16,
+    extendable: bool,
+    exponent: u8,
+) -> Vec<u8> {
+    feistel(
+        master_secret,
+        passphrase,
+        identifier,
+        extendable,
+        exponent,
+        0..ROUND_COUNT,
+    )
+}
+
+fn decrypt(
+    encrypted_secret: &[u8],
+    passphrase: &str,
+    identifier: u16,
+    extendable: bool,
+    exponent: u8,
+) -> Vec<u8> {
+    feistel(
+        encrypted_secret,
+        passphrase,
+        identifier,
+        extendable,
+        exponent,
+        (0..ROUND_COUNT).rev(),
+    )
+}
+
+fn split_secret(
+    field: &GaloisField,
+    threshold: u8,
+    share_count: u8,
+    secret: &[u8],
+) -> Result<Vec<(u8, Vec<u8>)>> {
+    if threshold == 0 || threshold > share_count || share_count > MAX_SHARE_COUNT {
+        bail!("Threshold must be between 1 and the share count, with at most {MAX_SHARE_COUNT} shares");
+    }
+
+    if threshold == 1 {
+        return Ok((0..share_count).map(|i| (i, secret.to_vec())).collect());
+    }
+
+    let mut rng = rand::thread_rng();
+    let random_share_count = threshold - 2;
+    let mut shares: Vec<(u8, Vec<u8>)> = (0..random_share_count)
+        .map(|i| (i, (0..secret.len()).map(|_| rng.gen()).collect()))
+        .collect();
+
+    let random_part: Vec<u8> = (0..secret.len() - DIGEST_LENGTH)
+        .map(|_| rng.gen())
+        .collect();
+    let digest = &hmac_sha256(&random_part, secret)[..DIGEST_LENGTH];
+
+    let mut base_shares = shares.clone();
+    base_shares.push((DIGEST_INDEX, [digest, &random_part].concat()));
+    base_shares.push((SECRET_INDEX, secret.to_vec()));
+
+    for i in random_share_count..share_count {
+        shares.push((i, field.interpolate(&base_shares, i)?));
+    }
+    Ok(shares)
+}
+
+fn recover_secret(field: &GaloisField, threshold: u8, shares: &[(u8, Vec<u8>)]) -> Result<Vec<u8>> {
+    if threshold == 1 {
+        return shares
+            .first()
+            .map(|(_, value)| value.clone())
+            .ok_or_else(|| anyhow!("No shares provided"));
+    }
+
+    let secret = field.interpolate(shares, SECRET_INDEX)?;
+    let digest_share = field.interpolate(shares, DIGEST_INDEX)?;
+    let (digest, random_part) = digest_share.split_at(DIGEST_LENGTH);
+    if digest != &hmac_sha256(random_part, &secret)[..DIGEST_LENGTH] {
+        bail!("Invalid digest of the shared secret");
+    }
+    Ok(secret)
+}
+
+fn word_index(word: &str) -> Result<u32> {
+    let word = word.to_lowercase();
+    WORDLIST
+        .iter()
+        .position(|w| *w == word || (word.len() >= 4 && w.starts_with(&word)))
+        .map(|i| i as u32)
+        .ok_or_else(|| anyhow!("Invalid SLIP-39 word {word}"))
+}
+
+fn bits_to_words(bits: &[bool]) -> Vec<u32> {
+    bits.chunks(RADIX_BITS)
+        .map(|chunk| chunk.iter().fold(0, |acc, b| (acc << 1) | u32::from(*b)))
+        .collect()
+}
+
+fn words_to_bits(words: &[u32]) -> Vec<bool> {
+    words
+        .iter()
+        .flat_map(|w| (0..RADIX_BITS).rev().map(move |i| (w >> i) & 1 == 1))
+        .collect()
+}
+
+impl Share {
+    pub fn from_mnemonic(mnemonic: &str) -> Result<Self> {
+        let words = mnemonic
+            .split_whitespace()
+            .map(word_index)
+            .collect::<Result<Vec<u32>>>()?;
+
+        if words.len() < MIN_MNEMONIC_WORDS {
+            bail!("Invalid SLIP-39 share, it must have at least {MIN_MNEMONIC_WORDS} words");
+        }
+
+        let header = words[..HEADER_WORDS]
+            .iter()
+            .fold(0u64, |acc, w| (acc << RADIX_BITS) | u64::from(*w));
+        let extendable = (header >> 24) & 1 == 1;
+
+        if !rs1024_verify_checksum(&words, extendable) {
+            bail!("Invalid SLIP-39 share checksum");
+        }
+
+        let value_words = &words[HEADER_WORDS..words.len() - CHECKSUM_WORDS];
+        let padding = (RADIX_BITS * value_words.len()) % 16;
+        if padding > 8 {
+            bail!("Invalid SLIP-39 share length");
+        }
+        let bits = words_to_bits(value_words);
+        if bits[..padding].iter().any(|b| *b) {
+            bail!("Invalid SLIP-39 share padding");
+        }
+        let value: Vec<u8> = bits[padding..]
+            .chunks(8)
+            .map(|chunk| chunk.iter().fold(0u8, |acc, b| (acc << 1) | u8::from(*b)))
+            .collect();
+
+        let share = Self {
+            identifier: (header >> 25) as u16,
+            extendable,
+            iteration_exponent: ((header >> 20) & 0xf) as u8,
+            group_index: ((header >> 16) & 0xf) as u8,
+            group_threshold: ((header >> 12) & 0xf) as u8 + 1,
+            group_count: ((header >> 8) & 0xf) as u8 + 1,
+            member_index: ((header >> 4) & 0xf) as u8,
+            member_threshold: (header & 0xf) as u8 + 1,
+            value,
+        };
+
+        if share.group_threshold > share.group_count {
+            bail!("Invalid SLIP-39 share, group threshold greater than group count");
+        }
+        if share.group_index >= share.group_count {
+            bail!("Invalid SLIP-39 share, group index greater than group count");
+        }
+        Ok(share)
+    }
+
+    pub fn to_mnemonic(&self) -> String {
+        let header = (u64::from(self.identifier) << 25)
+            | (u64::from(self.extendable) << 24)
+            | (u64::from(self.iteration_exponent) << 20)
+            | (u64::from(self.group_index) << 16)
+            | (u64::from(self.group_threshold - 1) << 12)
+            | (u64::from(self.group_count - 1) << 8)
+            | (u64::from(self.member_index) << 4)
+            | u64::from(self.member_threshold - 1);
+
+        let value_bits: Vec<bool> = self
+            .value
+            .iter()
+            .flat_map(|b| (0..8).rev().map(move |i| (b >> i) & 1 == 1))
+            .collect();
+        let padding = (RADIX_BITS - value_bits.len() % RADIX_BITS) % RADIX_BITS;
+        let padded: Vec<bool> = std::iter::repeat_n(false, padding)
+            .chain(value_bits)
+            .collect();
+
+        let mut words: Vec<u32> = (0..HEADER_WORDS)
+            .map(|i| ((header >> (RADIX_BITS * (HEADER_WORDS - 1 - i))) & 1023) as u32)
+            .collect();
+        words.extend(bits_to_words(&padded));
+        words.extend(rs1024_create_checksum(&words, self.extendable));
+
+        words
+            .iter()
+            .map(|w| WORDLIST[*w as usize])
+            .collect::<Vec<&str>>()
+            .join(" ")
+    }
+}
+
+/// Splits a master secret into groups of SLIP-39 mnemonic shares. The secret can
+/// be recovered with `member_threshold` shares of `group_threshold` groups.
+pub fn generate_mnemonics(
+    group_threshold: u8,
+    groups: &[GroupSpec],
+    master_secret: &[u8],
+    passphrase: &str,
+    iteration_exponent: u8,
+) -> Result<Vec<Vec<String>>> {
+    if master_secret.len() < 16 || !master_secret.len().is_multiple_of(2) {
+        bail!("The master secret must be at least 128 bits and an even number of bytes");
+    }
+    if !passphrase.chars().all(|c| (' '..='~').contains(&c)) {
+        bail!("The passphrase must only contain printable ASCII characters");
+    }
+    if groups.is_empty() || group_threshold == 0 || usize::from(group_threshold) > groups.len() {
+        bail!("The group threshold must be between 1 and the number of groups");
+    }
+    if groups
+        .iter()
+        .any(|g| g.member_threshold == 1 && g.member_count > 1)
+    {
+        bail!("Creating multiple member shares with member threshold 1 is not allowed, use 1-of-1 instead");
+    }
+    if iteration_exponent > 0xf {
+        bail!("Invalid iteration exponent");
+    }
+
+    let field = GaloisField::new();
+    let identifier: u16 = rand::thread_rng().gen_range(0..1 << 15);
+    let extendable = false;
+    let encrypted_secret = encrypt(
+        master_secret,
+        passphrase,
+        identifier,
+        extendable,
+        iteration_exponent,
+    );
+
+    let group_shares = split_secret(
+        &field,
+        group_threshold,
+        u8::try_from(groups.len())?,
+        &encrypted_secret,
+    )?;
+
+    groups
+        .iter()
+        .zip(group_shares)
+        .map(|(group, (group_index, group_secret))| {
+            split_secret(
+                &field,
+                group.member_threshold,
+                group.member_count,
+                &group_secret,
+            )?
+            .into_iter()
+            .map(|(member_index, value)| {
+                Ok(Share {
+                    identifier,
+                    extendable,
+                    iteration_exponent,
+                    group_index,
+                    group_threshold,
+                    group_count: u8::try_from(groups.len())?,
+                    member_index,
+                    member_threshold: group.member_threshold,
+                    value,
+                }
+                .to_mnemonic())
+            })
+            .collect()
+        })
+        .collect()
+}
+
+/// Recovers the master secret from a quorum of SLIP-39 mnemonic shares.
+pub fn combine_mnemonics<S: AsRef<str>>(mnemonics: &[S], passphrase: &str) -> Result<Vec<u8>> {
+    let shares = mnemonics
+        .iter()
+        .map(|m| Share::from_mnemonic(m.as_ref()))
+        .collect::<Result<Vec<Share>>>()?;
+
+    let first = shares
+        .first()
+        .ok_or_else(|| anyhow!("No shares provided"))?;
+    if shares.iter().any(|s| {
+        s.identifier != first.identifier
+            || s.extendable != first.extendable
+            || s.iteration_exponent != first.iteration_exponent
+            || s.group_threshold != first.group_threshold
+            || s.group_count != first.group_count
+            || s.value.len() != first.value.len()
+    }) {
+        bail!("All shares must belong to the same secret");
+    }
+
+    let mut groups: BTreeMap<u8, Vec<&Share>> = BTreeMap::new();
+    for share in &shares {
+        groups.entry(share.group_index).or_default().push(share);
+    }
+
+    let field = GaloisField::new();
+    let mut group_secrets = Vec::new();
+    for (group_index, members) in &groups {
+        let member_threshold = members[0].member_threshold;
+        if members
+            .iter()
+            .any(|m| m.member_threshold != member_threshold)
+        {
+            bail!(
+                "Shares of group {} have different thresholds",
+                group_index + 1
+            );
+        }
+        let mut member_shares: Vec<(u8, Vec<u8>)> = members
+            .iter()
+            .map(|m| (m.member_index, m.value.clone()))
+            .collect();
+        member_shares.sort();
+        member_shares.dedup();
+        if member_shares.len() < usize::from(member_threshold) {
+            continue;
+        }
+        member_shares.truncate(usize::from(member_threshold));
+        group_secrets.push((
+            *group_index,
+            recover_secret(&field, member_threshold, &member_shares)?,
+        ));
+    }
+
+    if group_secrets.len() < usize::from(first.group_threshold) {
+        bail!(
+            "Insufficient shares, {} of {} groups are complete",
+            group_secrets.len(),
+            first.group_threshold
+        );
+    }
+    group_secrets.truncate(usize::from(first.group_threshold));
+
+    let encrypted_secret = recover_secret(&field, first.group_threshold, &group_secrets)?;
+    Ok(decrypt(
+        &encrypted_secret,
+        passphrase,
+        first.identifier,
+        first.extendable,
+        first.iteration_exponent,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{combine_mnemonics, generate_mnemonics, GroupSpec, Share};
+    use bitcoin::hashes::hex::ToHex;
+
+    #[test]
+    fn combines_reference_vectors() {
+        let single = ["duckling enlarge academic academic agency result length solution fridge kidney coal piece deal husband erode duke ajar critical decision keyboard"];
+        assert_eq!(
+            combine_mnemonics(&single, "TREZOR").unwrap().to_hex(),
+            "bb54aac4b89dc868ba37d9cc21b2cece"
+        );
+
+        let two_of_three = [
+            "shadow pistol academic always adequate wildlife fancy gross oasis cylinder mustang wrist rescue view short owner flip making coding armed",
+            "shadow pistol academic acid actress prayer class unknown daughter sweater depict flip twice unkind craft early superior advocate guest smoking",
+        ];
+        assert_eq!(
+            combine_mnemonics(&two_of_three, "TREZOR").unwrap().to_hex(),
+            "b43ceb7e57a0ea8766221624d01b0864"
+        );
+        assert!(combine_mnemonics(&two_of_three[..1], "TREZOR").is_err());
+    }
+
+    #[test]
+    fn generates_and_combines_groups() {
+        let master_secret: Vec<u8> = (0..32).collect();
+        let groups = [
+            GroupSpec {
+                member_threshold: 1,
+                member_count: 1,
+            },
+            GroupSpec {
+                member_threshold: 2,
+                member_count: 3,
+            },
+            GroupSpec {
+                member_threshold: 3,
+                member_count: 5,
+            },
+        ];
+        let mnemonics = generate_mnemonics(2, &groups, &master_secret, "", 0).unwrap();
+        assert_eq!(
+            mnemonics.iter().map(Vec::len).collect::<Vec<_>>(),
+            [1, 3, 5]
+        );
+        assert_eq!(mnemonics[1][0].split_whitespace().count(), 33);
+
+        let quorum = [
+            mnemonics[1][2].clone(),
+            mnemonics[2][0].clone(),
+            mnemonics[1][0].clone(),
+            mnemonics[2][3].clone(),
+            mnemonics[2][4].clone(),
+        ];
+        assert_eq!(combine_mnemonics(&quorum, "").unwrap(), master_secret);
+        assert!(combine_mnemonics(&quorum[..4], "").is_err());
+
+        let share = Share::from_mnemonic(&mnemonics[2][1]).unwrap();
+        assert_eq!(share.to_mnemonic(), mnemonics[2][1]);
+    }
+
+    #[test]
+    fn rejects_malformed_shares() {
+        let mnemonics = generate_mnemonics(
+            1,
+            &[GroupSpec {
+                member_threshold: 1,
+                member_count: 1,
+            }],
+            &[0; 16],
+            "",
+            0,
+        )
+        .unwrap();
+        let share = Share::from_mnemonic(&mnemonics[0][0]).unwrap();
+
+        // a well formed checksum over a header with a group out of range
+        let mut malformed = share.clone();
+        malformed.group_index = share.group_count;
+        let error = Share::from_mnemonic(&malformed.to_mnemonic()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid SLIP-39 share, group index greater than group count"
+        );
+        let mut malformed = share;
+        malformed.group_threshold = 2;
+        assert!(Share::from_mnemonic(&malformed.to_mnemonic()).is_err());
+    }
+}
@@ -1,8 +1,9 @@
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use argon2::Config;
 use bitcoin::Network;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fmt;
 use std::str::FromStr;
 
@@ -11,6 +12,7 @@ pub use crate::wallet::Wallet;
 pub enum StorageKeys {
     User,
     Settings,
+    MuSig2,
 }
 
 impl fmt::Display for StorageKeys {
@@ -18,6 +20,7 @@ impl fmt::Display for StorageKeys {
         match self {
             StorageKeys::User => write!(f, "user"),
             StorageKeys::Settings => write!(f, "settings"),
+            StorageKeys::MuSig2 => write!(f, "musig2"),
         }
     }
 }
@@ -102,6 +105,75 @@ impl UserStorage {
     pub fn get_wallet_mut(&mut self, wallet_name: &str) -> Option<&mut Wallet> {
         self.wallets.iter_mut().find(|w| w.name.eq(wallet_name))
     }
+
+    fn wallet_index(&self, wallet_name: &str) -> Result<usize> {
+        self.wallets
+            .iter()
+            .position(|w| w.name.eq(wallet_name))
+            .ok_or_else(|| anyhow!("Wallet {} not found", wallet_name))
+    }
+
+    pub fn set_default_wallet(&mut self, wallet_name: &str) -> Result<()> {
+        self.wallet_index(wallet_name)?;
+        self.default_wallet = Some(wallet_name.to_string());
+        Ok(())
+    }
+
+    pub fn rename_wallet(&mut self, wallet_name: &str, new_name: &str) -> Result<()> {
+        let new_name = new_name.trim();
+        if new_name.is_empty() {
+            bail!("Wallet name is mandatory");
+        }
+        let index = self.wallet_index(wallet_name)?;
+        if wallet_name != new_name && self.get_wallet_ref(new_name).is_some() {
+            bail!("There is already a wallet with that name");
+        }
+
+        self.wallets[index].name = new_name.to_string();
+        if self.default_wallet.as_deref() == Some(wallet_name) {
+            self.default_wallet = Some(new_name.to_string());
+        }
+        Ok(())
+    }
+
+    /// Removes a wallet and its encrypted secret, after checking the user's password.
+    pub fn delete_wallet(&mut self, wallet_name: &str, password: &str) -> Result<Wallet> {
+        if !self.verify_password(password.as_bytes())? {
+            bail!("Incorrect password");
+        }
+        let index = self.wallet_index(wallet_name)?;
+        if self.default_wallet.as_deref() == Some(wallet_name) {
+            self.default_wallet = None;
+        }
+        Ok(self.wallets.remove(index))
+    }
+
+    /// Lets a wallet sign with sighash types other than SIGHASH_ALL, which
+    /// needs the user's password as only allowing SIGHASH_ALL doesn't.
+    pub fn set_allow_any_sighash(
+        &mut self,
+        wallet_name: &str,
+        allow_any_sighash: bool,
+        password: Option<&str>,
+    ) -> Result<()> {
+        if allow_any_sighash && !self.verify_password(password.unwrap_or_default().as_bytes())? {
+            bail!("Incorrect password");
+        }
+        let index = self.wallet_index(wallet_name)?;
+        self.wallets[index].allow_any_sighash = allow_any_sighash;
+        Ok(())
+    }
+
+    /// Moves a wallet to `new_index`, shifting the wallets in between.
+    pub fn move_wallet(&mut self, wallet_name: &str, new_index: usize) -> Result<()> {
+        if new_index >= self.wallets.len() {
+            bail!("Invalid wallet position {}", new_index);
+        }
+        let index = self.wallet_index(wallet_name)?;
+        let wallet = self.wallets.remove(index);
+        self.wallets.insert(new_index, wallet);
+        Ok(())
+    }
 }
 
 #[derive(Serialize, Deserialize, Default)]
@@ -109,6 +181,10 @@ pub struct SettingsStorage {
     #[serde(skip_serializing, skip_deserializing)]
     store: Option<Box<dyn Store>>,
     network: String,
+    /// Sign multi-input PSBTs without the previous transactions of their
+    /// inputs, whose amounts can't be verified.
+    #[serde(default)]
+    allow_unverified_inputs: bool,
 }
 
 impl SettingsStorage {
@@ -137,4 +213,142 @@ impl SettingsStorage {
     pub fn set_network(&mut self, network: &str) {
         self.network = network.to_string();
     }
+
+    pub fn get_allow_unverified_inputs(&self) -> bool {
+        self.allow_unverified_inputs
+    }
+
+    pub fn set_allow_unverified_inputs(&mut self, allow: bool) {
+        self.allow_unverified_inputs = allow;
+    }
+}
+
+/// Pending MuSig2 signing sessions. Only the session randomness is kept, the
+/// secret nonces can't be recomputed from it without the wallet's key.
+#[derive(Serialize, Deserialize, Default)]
+pub struct MuSig2Storage {
+    #[serde(skip_serializing, skip_deserializing)]
+    store: Option<Box<dyn Store>>,
+    /// Session randomness in hex by the hex public nonce handed out.
+    sessions: BTreeMap<String, String>,
+}
+
+impl MuSig2Storage {
+    pub fn read(store: impl Store + 'static) -> MuSig2Storage {
+        let mut musig2_storage: MuSig2Storage = store
+            .get_item(&StorageKeys::MuSig2.to_string())
+            .and_then(|value| serde_json::from_str(&value).map_err(|e| anyhow!("{}", e)))
+            .unwrap_or_default();
+
+        musig2_storage.store = Some(Box::new(store));
+        musig2_storage
+    }
+
+    pub fn save(&mut self) -> Result<()> {
+        let data = serde_json::to_string(&self)?;
+        self.store
+            .as_mut()
+            .context("Store not found")?
+            .set_item(&StorageKeys::MuSig2.to_string(), &data)
+    }
+
+    pub fn insert_session(&mut self, pub_nonce: &str, randomness: &str) {
+        self.sessions
+            .insert(pub_nonce.to_string(), randomness.to_string());
+    }
+
+    /// Removes a session so its nonce can't be used twice.
+    pub fn take_session(&mut self, pub_nonce: &str) -> Option<String> {
+        self.sessions.remove(pub_nonce)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Store, UserStorage, Wallet};
+    use anyhow::{anyhow, Result};
+    use serde_json::json;
+
+    struct NoStore;
+
+    impl Store for NoStore {
+        fn get_item(&self, _key: &str) -> Result<String> {
+            Err(anyhow!("Not found"))
+        }
+
+        fn set_item(&self, _key: &str, _data: &str) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn storage(names: &[&str]) -> UserStorage {
+        let mut storage = UserStorage::read(NoStore);
+        storage.wallets = names
+            .iter()
+            .map(|name| {
+                let wallet = json!({"name": name, "derivation": "m"});
+                serde_json::from_value::<Wallet>(wallet).unwrap()
+            })
+            .collect();
+        storage
+    }
+
+    fn names(storage: &UserStorage) -> Vec<&str> {
+        storage.wallets.iter().map(|w| w.name.as_str()).collect()
+    }
+
+    #[test]
+    fn rename_wallet() {
+        let mut storage = storage(&["a", "b"]);
+        storage.set_default_wallet("a").unwrap();
+
+        assert!(storage.rename_wallet("a", "b").is_err());
+        assert!(storage.rename_wallet("a", " ").is_err());
+        assert!(storage.rename_wallet("c", "d").is_err());
+
+        storage.rename_wallet("a", "c").unwrap();
+        assert_eq!(names(&storage), vec!["c", "b"]);
+        assert_eq!(storage.get_default_wallet(), "c");
+    }
+
+    #[test]
+    fn delete_wallet() {
+        let mut storage = storage(&["a", "b"]);
+        storage.set_password("password").unwrap();
+        storage.set_default_wallet("b").unwrap();
+
+        assert!(storage.delete_wallet("b", "wrong").is_err());
+        assert_eq!(storage.delete_wallet("b", "password").unwrap().name, "b");
+        assert_eq!(names(&storage), vec!["a"]);
+        assert_eq!(storage.get_default_wallet(), "a");
+    }
+
+    #[test]
+    fn set_allow_any_sighash() {
+        let mut storage = storage(&["a"]);
+        storage.set_password("password").unwrap();
+
+        assert!(storage.set_allow_any_sighash("a", true, None).is_err());
+        assert!(storage
+            .set_allow_any_sighash("a", true, Some("wrong"))
+            .is_err());
+        assert!(!storage.wallets[0].allow_any_sighash);
+        storage
+            .set_allow_any_sighash("a", true, Some("password"))
+            .unwrap();
+        assert!(storage.wallets[0].allow_any_sighash);
+        storage.set_allow_any_sighash("a", false, None).unwrap();
+        assert!(!storage.wallets[0].allow_any_sighash);
+    }
+
+    #[test]
+    fn move_wallet() {
+        let mut storage = storage(&["a", "b", "c"]);
+
+        storage.move_wallet("c", 0).unwrap();
+        assert_eq!(names(&storage), vec!["c", "a", "b"]);
+        storage.move_wallet("c", 2).unwrap();
+        assert_eq!(names(&storage), vec!["a", "b", "c"]);
+        assert!(storage.move_wallet("a", 3).is_err());
+    }
 }
@@ -0,0 +1,73 @@
+use anyhow::{anyhow, Result};
+
+/// Alphabet used by Electrum to encode transactions and PSBTs in QR codes.
+const ALPHABET: &[u8; 43] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ$*+-./:";
+
+pub fn from_base43(data: &str) -> Result<Vec<u8>> {
+    let mut bytes: Vec<u8> = Vec::with_capacity(data.len());
+
+    for c in data.bytes() {
+        let mut carry = ALPHABET
+            .iter()
+            .position(|a| *a == c)
+            .ok_or_else(|| anyhow!("Invalid base43 character {}", c as char))?
+            as u32;
+
+        for byte in bytes.iter_mut().rev() {
+            carry += u32::from(*byte) * 43;
+            *byte = (carry & 0xff) as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.insert(0, (carry & 0xff) as u8);
+            carry >>= 8;
+        }
+    }
+
+    let leading_zeros = data.bytes().take_while(|c| *c == ALPHABET[0]).count();
+    let mut result = vec![0; leading_zeros];
+    result.extend(bytes.into_iter().skip_while(|b| *b == 0));
+    Ok(result)
+}
+
+pub fn to_base43<T: AsRef<[u8]>>(data: &T) -> String {
+    let data = data.as_ref();
+    let mut digits: Vec<u8> = Vec::with_capacity(data.len() * 3 / 2);
+
+    for byte in data {
+        let mut carry = u32::from(*byte);
+        for digit in digits.iter_mut() {
+            carry += u32::from(*digit) << 8;
+            *digit = (carry % 43) as u8;
+            carry /= 43;
+        }
+        while carry > 0 {
+            digits.push((carry % 43) as u8);
+            carry /= 43;
+        }
+    }
+
+    let leading_zeros = data.iter().take_while(|b| **b == 0).count();
+    std::iter::repeat_n(ALPHABET[0], leading_zeros)
+        .chain(digits.iter().rev().map(|d| ALPHABET[*d as usize]))
+        .map(char::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{from_base43, to_base43};
+
+    #[test]
+    fn base43_roundtrip() {
+        let data = [0u8, 0, 0x70, 0x73, 0x62, 0x74, 0xff, 0x01, 0x00];
+        let encoded = to_base43(&data);
+        assert!(encoded.starts_with("00"));
+        assert_eq!(from_base43(&encoded).unwrap(), data);
+    }
+
+    #[test]
+    fn base43_invalid_character() {
+        assert!(from_base43("abc").is_err());
+    }
+}
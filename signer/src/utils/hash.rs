@@ -0,0 +1,30 @@
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::secp256k1::Scalar;
+
+/// BIP340 tagged hash of the concatenation of `data`.
+pub fn tagged_hash(tag: &[u8], data: &[&[u8]]) -> [u8; 32] {
+    let tag = sha256::Hash::hash(tag);
+    let mut engine = sha256::Hash::engine();
+    engine.input(&tag[..]);
+    engine.input(&tag[..]);
+    for d in data {
+        engine.input(d);
+    }
+    sha256::Hash::from_engine(engine).into_inner()
+}
+
+/// Reduces 32 bytes modulo the curve order, which only needs one subtraction.
+pub fn reduce(bytes: [u8; 32]) -> Scalar {
+    Scalar::from_be_bytes(bytes).unwrap_or_else(|_| {
+        let mut order = Scalar::MAX.to_be_bytes();
+        order[31] += 1;
+        let mut reduced = [0u8; 32];
+        let mut borrow = 0i16;
+        for i in (0..32).rev() {
+            let difference = i16::from(bytes[i]) - i16::from(order[i]) - borrow;
+            borrow = i16::from(difference < 0);
+            reduced[i] = (difference + (borrow << 8)) as u8;
+        }
+        Scalar::from_be_bytes(reduced).expect("reduced below the curve order")
+    })
+}
@@ -1,2 +1,5 @@
+pub mod base43;
 pub mod base64;
 pub mod encryption;
+pub mod hash;
+pub mod ur;
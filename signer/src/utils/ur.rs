@@ -0,0 +1,677 @@
+//! Decoder for Blockchain Commons Uniform Resources (BCR-2020-005), including
+//! the multi-part fountain encoding used by animated QR codes.
+//! <https://github.com/BlockchainCommons/Research/blob/master/papers/bcr-2020-005-ur.md>
+
+use anyhow::{anyhow, bail, Context, Result};
+use bitcoin::hashes::{sha256, Hash};
+use std::collections::{BTreeSet, HashMap};
+
+/// Upper bounds for the fragment count and message length announced by a
+/// multi-part UR, which come straight from the scanned text and size the
+/// allocations made while decoding.
+const MAX_SEQ_LEN: usize = 10_000;
+const MAX_MESSAGE_LEN: usize = 4_000_000;
+
+const BYTEWORDS: &str = "ableacidalsoapexaquaarchatomauntawayaxisbackbaldbarnbeltbetabiasbluebodybragbrewbulbbuzzcalmcashcatschefcityclawcodecolacookcostcruxcurlcuspcyandarkdatadaysdelidicedietdoordowndrawdropdrumdulldutyeacheasyechoedgeepicevenexamexiteyesfactfairfernfigsfilmfishfizzflapflewfluxfoxyfreefrogfuelfundgalagamegeargemsgiftgirlglowgoodgraygrimgurugushgyrohalfhanghardhawkheathelphighhillholyhopehornhutsicedideaidleinchinkyintoirisironitemjadejazzjoinjoltjowljudojugsjumpjunkjurykeepkenokeptkeyskickkilnkingkitekiwiknoblamblavalazyleaflegsliarlimplionlistlogoloudloveluaulucklungmainmanymathmazememomenumeowmildmintmissmonknailnavyneednewsnextnoonnotenumbobeyoboeomitonyxopenovalowlspaidpartpeckplaypluspoempoolposepuffpumapurrquadquizraceramprealredorichroadrockroofrubyruinrunsrustsafesagascarsetssilkskewslotsoapsolosongstubsurfswantacotasktaxitenttiedtimetinytoiltombtoystriptunatwinuglyundouniturgeuservastveryvetovialvibeviewvisavoidvowswallwandwarmwaspwavewaxywebswhatwhenwhizwolfworkyankyawnyellyogayurtzapszerozestzinczonezoom";
+
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffff_u32;
+    for byte in data {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+fn byteword(index: usize) -> &'static [u8] {
+    &BYTEWORDS.as_bytes()[index * 4..index * 4 + 4]
+}
+
+/// Decodes the minimal bytewords encoding (first and last letter of every word)
+/// and verifies the trailing CRC32 checksum.
+pub fn from_bytewords_minimal(data: &str) -> Result<Vec<u8>> {
+    let data = data.to_ascii_lowercase();
+    if !data.len().is_multiple_of(2) {
+        bail!("Invalid bytewords length");
+    }
+
+    let lookup: HashMap<(u8, u8), u8> = (0..256)
+        .map(|i| {
+            let word = byteword(i);
+            ((word[0], word[3]), i as u8)
+        })
+        .collect();
+
+    let mut bytes = data
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            lookup
+                .get(&(pair[0], pair[1]))
+                .copied()
+                .ok_or_else(|| anyhow!("Invalid byteword {}", String::from_utf8_lossy(pair)))
+        })
+        .collect::<Result<Vec<u8>>>()?;
+
+    if bytes.len() < 4 {
+        bail!("Bytewords payload too short");
+    }
+    let checksum = bytes.split_off(bytes.len() - 4);
+    if crc32(&bytes).to_be_bytes()[..] != checksum[..] {
+        bail!("Invalid bytewords checksum");
+    }
+    Ok(bytes)
+}
+
+pub fn to_bytewords_minimal(data: &[u8]) -> String {
+    data.iter()
+        .chain(crc32(data).to_be_bytes().iter())
+        .flat_map(|b| {
+            let word = byteword(*b as usize);
+            [char::from(word[0]), char::from(word[3])]
+        })
+        .collect()
+}
+
+struct CborReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> CborReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self.position.checked_add(len).context("CBOR overflow")?;
+        let slice = self
+            .data
+            .get(self.position..end)
+            .context("Unexpected end of CBOR data")?;
+        self.position = end;
+        Ok(slice)
+    }
+
+    fn header(&mut self, expected_major: u8) -> Result<u64> {
+        let initial = self.take(1)?[0];
+        if initial >> 5 != expected_major {
+            bail!("Unexpected CBOR major type {}", initial >> 5);
+        }
+        let additional = initial & 0x1f;
+        let size = match additional {
+            0..=23 => return Ok(u64::from(additional)),
+            24 => 1,
+            25 => 2,
+            26 => 4,
+            27 => 8,
+            _ => bail!("Unsupported CBOR length encoding"),
+        };
+        Ok(self
+            .take(size)?
+            .iter()
+            .fold(0u64, |acc, b| (acc << 8) | u64::from(*b)))
+    }
+
+    fn unsigned(&mut self) -> Result<u64> {
+        self.header(0)
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8]> {
+        let len = self.header(2)?;
+        self.take(usize::try_from(len)?)
+    }
+
+    fn array(&mut self) -> Result<u64> {
+        self.header(4)
+    }
+}
+
+fn cbor_header(major: u8, value: u64) -> Vec<u8> {
+    match value {
+        0..=23 => vec![(major << 5) | value as u8],
+        24..=0xff => vec![(major << 5) | 24, value as u8],
+        0x100..=0xffff => [
+            vec![(major << 5) | 25],
+            (value as u16).to_be_bytes().to_vec(),
+        ]
+        .concat(),
+        0x1_0000..=0xffff_ffff => [
+            vec![(major << 5) | 26],
+            (value as u32).to_be_bytes().to_vec(),
+        ]
+        .concat(),
+        _ => [vec![(major << 5) | 27], value.to_be_bytes().to_vec()].concat(),
+    }
+}
+
+/// Unwraps the CBOR byte string carried by `crypto-psbt` and `psbt` URs.
+pub fn decode_cbor_bytes(data: &[u8]) -> Result<Vec<u8>> {
+    let mut reader = CborReader::new(data);
+    reader.bytes().map(<[u8]>::to_vec)
+}
+
+pub fn encode_cbor_bytes(data: &[u8]) -> Vec<u8> {
+    [cbor_header(2, data.len() as u64), data.to_vec()].concat()
+}
+
+/// Xoshiro256** seeded with the SHA256 of the given bytes, as specified for
+/// fountain codes in BCR-2020-005.
+struct Xoshiro256 {
+    s: [u64; 4],
+}
+
+impl Xoshiro256 {
+    fn new(seed: &[u8]) -> Self {
+        let digest = sha256::Hash::hash(seed).into_inner();
+        let mut s = [0u64; 4];
+        for (i, chunk) in digest.chunks(8).enumerate() {
+            s[i] = chunk.iter().fold(0u64, |acc, b| (acc << 8) | u64::from(*b));
+        }
+        Self { s }
+    }
+
+    fn next(&mut self) -> u64 {
+        let result = self.s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = self.s[1] << 17;
+
+        self.s[2] ^= self.s[0];
+        self.s[3] ^= self.s[1];
+        self.s[1] ^= self.s[2];
+        self.s[0] ^= self.s[3];
+
+        self.s[2] ^= t;
+        self.s[3] = self.s[3].rotate_left(45);
+
+        result
+    }
+
+    fn next_double(&mut self) -> f64 {
+        self.next() as f64 / (u64::MAX as f64 + 1.0)
+    }
+
+    fn next_int(&mut self, low: u64, high: u64) -> u64 {
+        (self.next_double() * (high - low + 1) as f64) as u64 + low
+    }
+
+    fn shuffled(&mut self, mut items: Vec<usize>) -> Vec<usize> {
+        let mut result = Vec::with_capacity(items.len());
+        while !items.is_empty() {
+            let index = self.next_int(0, (items.len() - 1) as u64) as usize;
+            result.push(items.remove(index));
+        }
+        result
+    }
+
+    /// Chooses a fragment degree with probability proportional to `1 / degree`
+    /// using Walker's alias method.
+    fn choose_degree(&mut self, seq_len: usize) -> usize {
+        let weights: Vec<f64> = (1..=seq_len).map(|i| 1.0 / i as f64).collect();
+        let sum: f64 = weights.iter().sum();
+        let n = weights.len();
+        let mut scaled: Vec<f64> = weights.iter().map(|p| p * n as f64 / sum).collect();
+
+        let mut small = Vec::with_capacity(n);
+        let mut large = Vec::with_capacity(n);
+        for i in (0..n).rev() {
+            if scaled[i] < 1.0 {
+                small.push(i);
+            } else {
+                large.push(i);
+            }
+        }
+
+        let mut probs = vec![0.0; n];
+        let mut aliases = vec![0; n];
+        while let (Some(&a), Some(&g)) = (small.last(), large.last()) {
+            small.pop();
+            large.pop();
+            probs[a] = scaled[a];
+            aliases[a] = g;
+            scaled[g] += scaled[a] - 1.0;
+            if scaled[g] < 1.0 {
+                small.push(g);
+            } else {
+                large.push(g);
+            }
+        }
+        for i in large.into_iter().chain(small) {
+            probs[i] = 1.0;
+        }
+
+        let r1 = self.next_double();
+        let r2 = self.next_double();
+        let i = (n as f64 * r1) as usize;
+        let sample = if r2 < probs[i] { i } else { aliases[i] };
+        sample + 1
+    }
+}
+
+fn choose_fragments(seq_num: u32, seq_len: usize, checksum: u32) -> BTreeSet<usize> {
+    if seq_num as usize <= seq_len {
+        return BTreeSet::from([seq_num as usize - 1]);
+    }
+
+    let seed = [seq_num.to_be_bytes(), checksum.to_be_bytes()].concat();
+    let mut rng = Xoshiro256::new(&seed);
+    let degree = rng.choose_degree(seq_len);
+    let indexes = rng.shuffled((0..seq_len).collect());
+    indexes.into_iter().take(degree).collect()
+}
+
+fn xor_into(target: &mut [u8], other: &[u8]) {
+    target
+        .iter_mut()
+        .zip(other.iter())
+        .for_each(|(t, o)| *t ^= o);
+}
+
+#[derive(Clone, PartialEq, Eq)]
+struct FountainPart {
+    seq_num: u32,
+    seq_len: usize,
+    message_len: usize,
+    checksum: u32,
+    data: Vec<u8>,
+}
+
+impl FountainPart {
+    fn from_cbor(data: &[u8]) -> Result<Self> {
+        let mut reader = CborReader::new(data);
+        if reader.array()? != 5 {
+            bail!("Invalid multi-part UR fragment");
+        }
+        Ok(Self {
+            seq_num: u32::try_from(reader.unsigned()?)?,
+            seq_len: usize::try_from(reader.unsigned()?)?,
+            message_len: usize::try_from(reader.unsigned()?)?,
+            checksum: u32::try_from(reader.unsigned()?)?,
+            data: reader.bytes()?.to_vec(),
+        })
+    }
+
+    fn to_cbor(&self) -> Vec<u8> {
+        [
+            cbor_header(4, 5),
+            cbor_header(0, u64::from(self.seq_num)),
+            cbor_header(0, self.seq_len as u64),
+            cbor_header(0, self.message_len as u64),
+            cbor_header(0, u64::from(self.checksum)),
+            encode_cbor_bytes(&self.data),
+        ]
+        .concat()
+    }
+}
+
+/// Accumulates the parts of a single or multi-part UR until the full message
+/// can be reassembled.
+#[derive(Default)]
+pub struct URDecoder {
+    ur_type: Option<String>,
+    message: Option<Vec<u8>>,
+    params: Option<(usize, usize, u32, usize)>,
+    simple_parts: HashMap<usize, Vec<u8>>,
+    mixed_parts: Vec<(BTreeSet<usize>, Vec<u8>)>,
+    received: BTreeSet<u32>,
+}
+
+impl URDecoder {
+    pub fn ur_type(&self) -> Option<&str> {
+        self.ur_type.as_deref()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.message.is_some()
+    }
+
+    pub fn message(&self) -> Option<&[u8]> {
+        self.message.as_deref()
+    }
+
+    /// Returns how many of the original fragments are known and how many are
+    /// expected.
+    pub fn progress(&self) -> (usize, usize) {
+        match (&self.message, &self.params) {
+            (Some(_), Some((seq_len, ..))) => (*seq_len, *seq_len),
+            (Some(_), None) => (1, 1),
+            (None, Some((seq_len, ..))) => (self.simple_parts.len(), *seq_len),
+            (None, None) => (0, 0),
+        }
+    }
+
+    pub fn receive(&mut self, part: &str) -> Result<()> {
+        if self.is_complete() {
+            return Ok(());
+        }
+
+        let part = part.trim().to_ascii_lowercase();
+        let body = part
+            .strip_prefix("ur:")
+            .ok_or_else(|| anyhow!("UR must start with \"ur:\""))?;
+        let components: Vec<&str> = body.split('/').collect();
+
+        let ur_type = components[0];
+        if ur_type.is_empty()
+            || !ur_type
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        {
+            bail!("Invalid UR type");
+        }
+        match &self.ur_type {
+            Some(t) if t != ur_type => bail!("Mixed UR types {t} and {ur_type}"),
+            _ => self.ur_type = Some(ur_type.to_string()),
+        }
+
+        match components[1..] {
+            [payload] => {
+                self.message = Some(from_bytewords_minimal(payload)?);
+                Ok(())
+            }
+            [sequence, payload] => {
+                let (seq_num, seq_len) = sequence
+                    .split_once('-')
+                    .context("Invalid UR sequence component")?;
+                let seq_num: u32 = seq_num.parse()?;
+                let seq_len: usize = seq_len.parse()?;
+                let fountain_part = FountainPart::from_cbor(&from_bytewords_minimal(payload)?)?;
+                if fountain_part.seq_num != seq_num || fountain_part.seq_len != seq_len {
+                    bail!("UR sequence component doesn't match its payload");
+                }
+                self.receive_fountain_part(fountain_part)
+            }
+            _ => bail!("Invalid UR path"),
+        }
+    }
+
+    fn receive_fountain_part(&mut self, part: FountainPart) -> Result<()> {
+        if part.seq_num == 0 || part.seq_len == 0 || part.data.is_empty() {
+            bail!("Invalid multi-part UR fragment");
+        }
+        if part.seq_len > MAX_SEQ_LEN {
+            bail!("Multi-part UR has too many fragments ({})", part.seq_len);
+        }
+        if part.message_len > MAX_MESSAGE_LEN {
+            bail!(
+                "Multi-part UR message is too long ({} bytes)",
+                part.message_len
+            );
+        }
+
+        let params = (
+            part.seq_len,
+            part.message_len,
+            part.checksum,
+            part.data.len(),
+        );
+        match self.params {
+            Some(p) if p != params => bail!("UR part belongs to a different message"),
+            _ => self.params = Some(params),
+        }
+
+        if !self.received.insert(part.seq_num) {
+            return Ok(());
+        }
+
+        let indexes = choose_fragments(part.seq_num, part.seq_len, part.checksum);
+        self.reduce_and_store(indexes, part.data);
+
+        if self.simple_parts.len() == part.seq_len {
+            let mut message: Vec<u8> = (0..part.seq_len)
+                .flat_map(|i| self.simple_parts[&i].clone())
+                .collect();
+            message.truncate(part.message_len);
+            if crc32(&message) != part.checksum {
+                bail!("Invalid multi-part UR checksum");
+            }
+            self.message = Some(message);
+        }
+
+        Ok(())
+    }
+
+    fn reduce_and_store(&mut self, indexes: BTreeSet<usize>, data: Vec<u8>) {
+        let mut queue = vec![(indexes, data)];
+
+        while let Some((mut indexes, mut data)) = queue.pop() {
+            for index in indexes.clone() {
+                if let Some(simple) = self.simple_parts.get(&index) {
+                    xor_into(&mut data, simple);
+                    indexes.remove(&index);
+                }
+            }
+            for (mixed_indexes, mixed_data) in &self.mixed_parts {
+                if mixed_indexes.is_subset(&indexes) && mixed_indexes != &indexes {
+                    xor_into(&mut data, mixed_data);
+                    indexes = indexes.difference(mixed_indexes).copied().collect();
+                }
+            }
+
+            match indexes.len() {
+                0 => {}
+                1 => {
+                    let index = *indexes.iter().next().unwrap();
+                    self.simple_parts.insert(index, data);
+                    // A new simple part can unlock previously stored mixed parts.
+                    let mixed_parts = std::mem::take(&mut self.mixed_parts);
+                    let (affected, unaffected): (Vec<_>, Vec<_>) = mixed_parts
+                        .into_iter()
+                        .partition(|(mixed_indexes, _)| mixed_indexes.contains(&index));
+                    self.mixed_parts = unaffected;
+                    queue.extend(affected);
+                }
+                _ => {
+                    if !self.mixed_parts.iter().any(|(i, _)| i == &indexes) {
+                        self.mixed_parts.push((indexes, data));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Splits a message into a multi-part UR, emitting `count` parts. Parts past
+/// the fragment count are fountain-mixed, as produced by animated QR encoders.
+pub fn encode_multipart(
+    ur_type: &str,
+    message: &[u8],
+    fragment_len: usize,
+    count: u32,
+) -> Vec<String> {
+    let seq_len = message.len().div_ceil(fragment_len);
+    let checksum = crc32(message);
+    let mut padded = message.to_vec();
+    padded.resize(seq_len * fragment_len, 0);
+    let fragments: Vec<&[u8]> = padded.chunks(fragment_len).collect();
+
+    (1..=count)
+        .map(|seq_num| {
+            let mut data = vec![0u8; fragment_len];
+            for index in choose_fragments(seq_num, seq_len, checksum) {
+                xor_into(&mut data, fragments[index]);
+            }
+            let part = FountainPart {
+                seq_num,
+                seq_len,
+                message_len: message.len(),
+                checksum,
+                data,
+            };
+            format!(
+                "ur:{ur_type}/{seq_num}-{seq_len}/{}",
+                to_bytewords_minimal(&part.to_cbor())
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn crc32_success() {
+        assert_eq!(crc32(b"Hello, world!"), 0xebe6c6e6);
+        assert_eq!(crc32(b"Wolf"), 0x598c84dc);
+    }
+
+    #[test]
+    fn bytewords_minimal_success() {
+        let data = [0u8, 1, 2, 128, 255];
+        assert_eq!(to_bytewords_minimal(&data), "aeadaolazmjendeoti");
+        assert_eq!(from_bytewords_minimal("aeadaolazmjendeoti").unwrap(), data);
+        assert!(from_bytewords_minimal("aeadaolazmjendeota").is_err());
+    }
+
+    #[test]
+    fn xoshiro_matches_reference() {
+        let mut rng = Xoshiro256::new(b"Wolf");
+        let numbers: Vec<u64> = (0..10).map(|_| rng.next() % 100).collect();
+        assert_eq!(numbers, vec![42, 81, 85, 8, 82, 84, 76, 73, 70, 88]);
+    }
+
+    /// The pseudo-random message used by the BCR-2020-005 reference tests.
+    fn make_message(seed: &str, len: usize) -> Vec<u8> {
+        let mut rng = Xoshiro256::new(seed.as_bytes());
+        (0..len).map(|_| rng.next_int(0, 255) as u8).collect()
+    }
+
+    #[test]
+    fn choose_fragments_matches_reference() {
+        let message = make_message("Wolf", 1024);
+        let expected: Vec<Vec<usize>> = vec![
+            vec![0],
+            vec![1],
+            vec![2],
+            vec![3],
+            vec![4],
+            vec![5],
+            vec![6],
+            vec![7],
+            vec![8],
+            vec![9],
+            vec![10],
+            vec![9],
+            vec![2, 5, 6, 8, 9, 10],
+            vec![8],
+            vec![1, 5],
+            vec![1],
+            vec![0, 2, 4, 5, 8, 10],
+            vec![5],
+            vec![2],
+            vec![2],
+            vec![0, 1, 3, 4, 5, 7, 9, 10],
+            vec![0, 1, 2, 3, 5, 6, 8, 9, 10],
+            vec![0, 2, 4, 5, 7, 8, 9, 10],
+            vec![3, 5],
+            vec![4],
+            vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10],
+            vec![0, 1, 3, 4, 5, 6, 7, 9, 10],
+            vec![6],
+            vec![5, 6],
+            vec![7],
+        ];
+        for (seq_num, indexes) in (1..).zip(expected) {
+            let chosen = choose_fragments(seq_num, 11, crc32(&message));
+            assert_eq!(chosen.into_iter().collect::<Vec<_>>(), indexes);
+        }
+    }
+
+    #[test]
+    fn single_part_matches_reference() {
+        let mut decoder = URDecoder::default();
+        decoder.receive("ur:bytes/hdeymejtswhhylkepmykhhtsytsnoyoyaxaedsuttydmmhhpktpmsrjtgwdpfnsboxgwlbaawzuefywkdplrsrjynbvygabwjldapfcsdwkbrkch").unwrap();
+        let message = encode_cbor_bytes(&make_message("Wolf", 50));
+        assert_eq!(decoder.message(), Some(&message[..]));
+    }
+
+    #[test]
+    fn multipart_matches_reference() {
+        let message = encode_cbor_bytes(&make_message("Wolf", 256));
+        let expected = [
+            "ur:bytes/1-9/lpadascfadaxcywenbpljkhdcahkadaemejtswhhylkepmykhhtsytsnoyoyaxaedsuttydmmhhpktpmsrjtdkgslpgh",
+            "ur:bytes/2-9/lpaoascfadaxcywenbpljkhdcagwdpfnsboxgwlbaawzuefywkdplrsrjynbvygabwjldapfcsgmghhkhstlrdcxaefz",
+            "ur:bytes/3-9/lpaxascfadaxcywenbpljkhdcahelbknlkuejnbadmssfhfrdpsbiegecpasvssovlgeykssjykklronvsjksopdzmol",
+            "ur:bytes/4-9/lpaaascfadaxcywenbpljkhdcasotkhemthydawydtaxneurlkosgwcekonertkbrlwmplssjtammdplolsbrdzcrtas",
+            "ur:bytes/5-9/lpahascfadaxcywenbpljkhdcatbbdfmssrkzmcwnezelennjpfzbgmuktrhtejscktelgfpdlrkfyfwdajldejokbwf",
+            "ur:bytes/6-9/lpamascfadaxcywenbpljkhdcackjlhkhybssklbwefectpfnbbectrljectpavyrolkzczcpkmwidmwoxkilghdsowp",
+            "ur:bytes/7-9/lpatascfadaxcywenbpljkhdcavszmwnjkwtclrtvaynhpahrtoxmwvwatmedibkaegdosftvandiodagdhthtrlnnhy",
+            "ur:bytes/8-9/lpayascfadaxcywenbpljkhdcadmsponkkbbhgsoltjntegepmttmoonftnbuoiyrehfrtsabzsttorodklubbuyaetk",
+            "ur:bytes/9-9/lpasascfadaxcywenbpljkhdcajskecpmdckihdyhphfotjojtfmlnwmadspaxrkytbztpbauotbgtgtaeaevtgavtny",
+            "ur:bytes/10-9/lpbkascfadaxcywenbpljkhdcahkadaemejtswhhylkepmykhhtsytsnoyoyaxaedsuttydmmhhpktpmsrjtwdkiplzs",
+            "ur:bytes/11-9/lpbdascfadaxcywenbpljkhdcahelbknlkuejnbadmssfhfrdpsbiegecpasvssovlgeykssjykklronvsjkvetiiapk",
+            "ur:bytes/12-9/lpbnascfadaxcywenbpljkhdcarllaluzmdmgstospeyiefmwejlwtpedamktksrvlcygmzemovovllarodtmtbnptrs",
+            "ur:bytes/13-9/lpbtascfadaxcywenbpljkhdcamtkgtpknghchchyketwsvwgwfdhpgmgtylctotzopdrpayoschcmhplffziachrfgd",
+            "ur:bytes/14-9/lpbaascfadaxcywenbpljkhdcapazewnvonnvdnsbyleynwtnsjkjndeoldydkbkdslgjkbbkortbelomueekgvstegt",
+            "ur:bytes/15-9/lpbsascfadaxcywenbpljkhdcaynmhpddpzmversbdqdfyrehnqzlugmjzmnmtwmrouohtstgsbsahpawkditkckynwt",
+            "ur:bytes/16-9/lpbeascfadaxcywenbpljkhdcawygekobamwtlihsnpalnsghenskkiynthdzotsimtojetprsttmukirlrsbtamjtpd",
+            "ur:bytes/17-9/lpbyascfadaxcywenbpljkhdcamklgftaxykpewyrtqzhydntpnytyisincxmhtbceaykolduortotiaiaiafhiaoyce",
+            "ur:bytes/18-9/lpbgascfadaxcywenbpljkhdcahkadaemejtswhhylkepmykhhtsytsnoyoyaxaedsuttydmmhhpktpmsrjtntwkbkwy",
+            "ur:bytes/19-9/lpbwascfadaxcywenbpljkhdcadekicpaajootjzpsdrbalpeywllbdsnbinaerkurspbncxgslgftvtsrjtksplcpeo",
+            "ur:bytes/20-9/lpbbascfadaxcywenbpljkhdcayapmrleeleaxpasfrtrdkncffwjyjzgyetdmlewtkpktgllepfrltataztksmhkbot",
+        ];
+        // 259 bytes in at most 30 byte fragments are split as 9 fragments of 29 bytes
+        assert_eq!(encode_multipart("bytes", &message, 29, 20), expected);
+
+        let mut decoder = URDecoder::default();
+        for part in &expected[3..] {
+            decoder.receive(part).unwrap();
+        }
+        assert_eq!(decoder.message(), Some(&message[..]));
+    }
+
+    #[test]
+    fn multipart_with_mixed_parts_success() {
+        let message: Vec<u8> = (0..=255u8).cycle().take(1000).collect();
+        let parts = encode_multipart("bytes", &message, 100, 40);
+
+        let mut decoder = URDecoder::default();
+        // skip a few of the simple parts so the mixed ones have to fill the gaps
+        for part in parts
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| i % 3 != 0)
+            .map(|(_, p)| p)
+        {
+            decoder.receive(part).unwrap();
+            if decoder.is_complete() {
+                break;
+            }
+        }
+
+        assert_eq!(decoder.ur_type(), Some("bytes"));
+        assert_eq!(decoder.message(), Some(&message[..]));
+    }
+
+    #[test]
+    fn rejects_oversized_multipart() {
+        let part = FountainPart {
+            seq_num: 1,
+            seq_len: 4_294_967_295,
+            message_len: 100,
+            checksum: 0,
+            data: vec![0; 10],
+        };
+        let ur = format!(
+            "ur:crypto-psbt/1-4294967295/{}",
+            to_bytewords_minimal(&part.to_cbor())
+        );
+        let error = URDecoder::default().receive(&ur).unwrap_err();
+        assert!(error.to_string().contains("too many fragments"));
+
+        let part = FountainPart {
+            seq_len: 2,
+            message_len: usize::MAX,
+            ..part
+        };
+        let ur = format!(
+            "ur:crypto-psbt/1-2/{}",
+            to_bytewords_minimal(&part.to_cbor())
+        );
+        let error = URDecoder::default().receive(&ur).unwrap_err();
+        assert!(error.to_string().contains("too long"));
+    }
+}
@@ -1,20 +1,28 @@
+use crate::bip85::{self, Bip85Application};
+use crate::bsms;
+use crate::entropy::{self, EntropySource, UserEntropy};
+use crate::message::{self, AddressType, SignatureFormat, SignedMessage};
+use crate::slip39::{self, GroupSpec};
 use crate::utils::encryption::{decrypt, encrypt, get_encryption_key, AEAD_NONCE_SIZE_BYTES};
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use bdk::keys::bip39::{Language, Mnemonic, WordCount};
 use bdk::keys::{DerivableKey, GeneratedKey};
 use bdk::keys::{ExtendedKey, GeneratableKey};
 use bdk::miniscript::Segwitv0;
+use bitcoin::hashes::hex::{FromHex, ToHex};
 use bitcoin::secp256k1::Secp256k1;
-use bitcoin::util::bip32::{DerivationPath, ExtendedPrivKey, ExtendedPubKey};
+use bitcoin::util::bip32::{DerivationPath, ExtendedPrivKey, ExtendedPubKey, Fingerprint};
 use bitcoin::Network;
+use rand::seq::index::sample;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum Secret {
     Seed(String),
     XPRV(String),
+    Slip39(String),
 }
 
 #[derive(Serialize, Deserialize, Debug, Default)]
@@ -24,6 +32,76 @@ pub struct Wallet {
     nonce: Option<[u8; AEAD_NONCE_SIZE_BYTES]>,
     secret: Option<Secret>,
     pub derivation: DerivationPath,
+    #[serde(default)]
+    pub network: Option<Network>,
+    /// Fingerprint of the master key the secret was derived from, needed to
+    /// match PSBT key origins for non-master xprvs.
+    #[serde(default)]
+    pub master_fingerprint: Option<Fingerprint>,
+    /// Multisig descriptor this wallet's key was registered in, set from a
+    /// BSMS descriptor record.
+    #[serde(default)]
+    pub descriptor: Option<String>,
+    /// Sign inputs requesting a sighash type other than SIGHASH_ALL, whose
+    /// signatures can be reused with different outputs or inputs.
+    #[serde(default)]
+    pub allow_any_sighash: bool,
+}
+
+/// Testnet, signet and regtest keys share the same version bytes, so `network`
+/// is used to tell them apart.
+fn xprv_network(xprv_network: Network, network: Network) -> Network {
+    match (xprv_network, network) {
+        (Network::Bitcoin, _) => Network::Bitcoin,
+        (_, Network::Bitcoin) => Network::Testnet,
+        (_, network) => network,
+    }
+}
+
+/// Master fingerprint that can be computed from the key itself, only possible
+/// for master keys and their direct children.
+fn known_master_fingerprint(xprv: &ExtendedPrivKey) -> Option<Fingerprint> {
+    match xprv.depth {
+        0 => Some(xprv.fingerprint(&Secp256k1::new())),
+        1 => Some(xprv.parent_fingerprint),
+        _ => None,
+    }
+}
+
+/// Checks the xprv depth and child number agree with `derivation` and with the
+/// expected master fingerprint, returning the master fingerprint if known.
+fn check_xprv_derivation(
+    xprv: &ExtendedPrivKey,
+    derivation: &DerivationPath,
+    master_fingerprint: Option<Fingerprint>,
+) -> Result<Option<Fingerprint>> {
+    if usize::from(xprv.depth) != derivation.len() {
+        bail!(
+            "The private key depth {} doesn't match the derivation {derivation}",
+            xprv.depth
+        );
+    }
+    if let Some(child_number) = derivation.as_ref().last() {
+        if *child_number != xprv.child_number {
+            bail!(
+                "The private key child number {} doesn't match the derivation {derivation}",
+                xprv.child_number
+            );
+        }
+    }
+
+    match (known_master_fingerprint(xprv), master_fingerprint) {
+        (Some(known), Some(expected)) if known != expected => {
+            bail!("The master fingerprint {known} doesn't match the expected {expected}")
+        }
+        (known, expected) => Ok(known.or(expected)),
+    }
+}
+
+fn parse_fingerprint(master_fingerprint: Option<&str>) -> Result<Option<Fingerprint>> {
+    master_fingerprint
+        .map(|f| Fingerprint::from_str(f.trim()).context("Error parsing master fingerprint"))
+        .transpose()
 }
 
 impl Wallet {
@@ -56,10 +134,33 @@ impl Wallet {
         )
     }
 
+    /// Network the wallet was imported for, wallets stored before it was
+    /// recorded fall back to `network`.
+    pub fn get_network(&self, network: Network) -> Network {
+        self.network.unwrap_or(network)
+    }
+
+    /// Key at the wallet's derivation, i.e. the account key for seed wallets
+    /// scoped to an account.
     pub fn get_xprv(
         &mut self,
         password: &str,
         network: Network,
+    ) -> anyhow::Result<ExtendedPrivKey> {
+        let root_xprv = self.get_root_xprv(password, network)?;
+        match self.secret {
+            Some(Secret::Seed(_)) | Some(Secret::Slip39(_)) if !self.derivation.is_master() => {
+                Ok(root_xprv.derive_priv(&Secp256k1::new(), &self.derivation)?)
+            }
+            _ => Ok(root_xprv),
+        }
+    }
+
+    /// Key stored in the secret, the master key for seed and SLIP-39 wallets.
+    fn get_root_xprv(
+        &mut self,
+        password: &str,
+        network: Network,
     ) -> anyhow::Result<ExtendedPrivKey> {
         let salt = self.get_salt();
         let nonce = self.get_nonce();
@@ -67,6 +168,7 @@ impl Wallet {
         let encrypted_secret = match &mut self.secret {
             Some(Secret::Seed(seed)) => seed,
             Some(Secret::XPRV(xprv)) => xprv,
+            Some(Secret::Slip39(master_secret)) => master_secret,
             None => return Err(anyhow!("No secret found")),
         };
 
@@ -78,15 +180,26 @@ impl Wallet {
             Some(Secret::Seed(_)) => {
                 let seed = Mnemonic::parse(decrypted_secret)?;
                 let xkey: ExtendedKey = seed.into_extended_key()?;
-                let xprv = xkey.into_xprv(network).context("No private key found")?;
+                let xprv = xkey
+                    .into_xprv(self.get_network(network))
+                    .context("No private key found")?;
                 Ok(xprv)
             }
             Some(Secret::XPRV(_)) => {
                 let mut xprv =
                     ExtendedPrivKey::from_str(&decrypted_secret).map_err(|e| anyhow!("{}", e))?;
-                xprv.network = network;
+                xprv.network = self
+                    .network
+                    .unwrap_or_else(|| xprv_network(xprv.network, network));
                 Ok(xprv)
             }
+            Some(Secret::Slip39(_)) => {
+                let master_secret = Vec::<u8>::from_hex(&decrypted_secret)?;
+                Ok(ExtendedPrivKey::new_master(
+                    self.get_network(network),
+                    &master_secret,
+                )?)
+            }
             None => unreachable!(),
         }
     }
@@ -98,6 +211,7 @@ impl Wallet {
         let encrypted_secret = match &mut self.secret {
             Some(Secret::Seed(seed)) => seed,
             Some(Secret::XPRV(xprv)) => xprv,
+            Some(Secret::Slip39(master_secret)) => master_secret,
             None => return Err(anyhow!("No secret found")),
         };
 
@@ -108,12 +222,27 @@ impl Wallet {
         Ok(decrypted_secret)
     }
 
-    pub fn from_seed_str(&mut self, name: &str, seed: &str, password: &str) -> Result<()> {
+    /// Imports a seed scoped to the account at `derivation`, use `m` to sign
+    /// from the master key.
+    pub fn from_seed_str(
+        &mut self,
+        name: &str,
+        seed: &str,
+        derivation: &str,
+        network: Network,
+        password: &str,
+    ) -> Result<()> {
+        let derivation =
+            DerivationPath::from_str(derivation).context("Error parsing derivation path")?;
+        let xkey: ExtendedKey = Mnemonic::parse(seed)?.into_extended_key()?;
+        let master_xprv = xkey.into_xprv(network).context("No private key found")?;
         let encrypted_seed = self.encrypt_secret(password, seed.to_string())?;
 
         self.name = name.to_string();
         self.secret = Some(Secret::Seed(encrypted_seed));
-        self.derivation = DerivationPath::default();
+        self.derivation = derivation;
+        self.network = Some(network);
+        self.master_fingerprint = Some(master_xprv.fingerprint(&Secp256k1::new()));
 
         Ok(())
     }
@@ -123,24 +252,68 @@ impl Wallet {
         name: &str,
         xprv: &str,
         derivation: &str,
+        master_fingerprint: Option<&str>,
+        network: Network,
         password: &str,
     ) -> Result<()> {
+        let parsed_xprv = ExtendedPrivKey::from_str(xprv).context("Error parsing private key")?;
+        let derivation =
+            DerivationPath::from_str(derivation).context("Error parsing derivation path")?;
+        let master_fingerprint = check_xprv_derivation(
+            &parsed_xprv,
+            &derivation,
+            parse_fingerprint(master_fingerprint)?,
+        )?;
         let encrypted_xprv = self.encrypt_secret(password, xprv.to_string())?;
 
         self.name = name.to_string();
         self.secret = Some(Secret::XPRV(encrypted_xprv));
-        self.derivation =
-            DerivationPath::from_str(derivation).context("Error parsing derivation path")?;
+        self.derivation = derivation;
+        self.network = Some(xprv_network(parsed_xprv.network, network));
+        self.master_fingerprint = master_fingerprint;
 
         Ok(())
     }
 
-    pub fn validate(xprv: &str, derivation: &str) -> Result<()> {
-        ExtendedPrivKey::from_str(xprv)?;
-        DerivationPath::from_str(derivation)?;
+    /// Recovers the master secret from a quorum of SLIP-39 shares and stores it
+    /// encrypted, the shares themselves are not kept.
+    pub fn from_slip39_shares<S: AsRef<str>>(
+        &mut self,
+        name: &str,
+        shares: &[S],
+        passphrase: &str,
+        network: Network,
+        password: &str,
+    ) -> Result<()> {
+        let master_secret = slip39::combine_mnemonics(shares, passphrase)?;
+        let master_xprv = ExtendedPrivKey::new_master(network, &master_secret)?;
+        let encrypted_secret = self.encrypt_secret(password, master_secret.to_hex())?;
+
+        self.name = name.to_string();
+        self.secret = Some(Secret::Slip39(encrypted_secret));
+        self.derivation = DerivationPath::default();
+        self.network = Some(network);
+        self.master_fingerprint = Some(master_xprv.fingerprint(&Secp256k1::new()));
+
         Ok(())
     }
 
+    pub fn validate(xprv: &str, derivation: &str, master_fingerprint: Option<&str>) -> Result<()> {
+        check_xprv_derivation(
+            &ExtendedPrivKey::from_str(xprv)?,
+            &DerivationPath::from_str(derivation)?,
+            parse_fingerprint(master_fingerprint)?,
+        )?;
+        Ok(())
+    }
+
+    /// Fingerprint of the wallet's master key, `xprv` being the wallet's key.
+    pub fn get_master_fingerprint(&self, xprv: &ExtendedPrivKey) -> Result<Fingerprint> {
+        self.master_fingerprint
+            .or_else(|| known_master_fingerprint(xprv))
+            .context("Unknown master fingerprint, import the wallet again providing it")
+    }
+
     pub fn generate_seed() -> Result<String> {
         let seed: GeneratedKey<_, Segwitv0> =
             Mnemonic::generate((WordCount::Words24, Language::English))
@@ -148,20 +321,229 @@ impl Wallet {
         Ok(seed.to_string())
     }
 
+    /// Picks `count` random word positions, sorted and zero based, to ask for
+    /// when checking the user wrote the seed down.
+    pub fn seed_quiz_positions(word_count: usize, count: usize) -> Vec<usize> {
+        let mut positions =
+            sample(&mut rand::thread_rng(), word_count, count.min(word_count)).into_vec();
+        positions.sort_unstable();
+        positions
+    }
+
+    /// Checks the words given for each quizzed position match the seed.
+    pub fn check_seed_quiz(seed: &[String], answers: &[(usize, String)]) -> Result<()> {
+        let wrong: Vec<String> = answers
+            .iter()
+            .filter(|(position, word)| {
+                !seed
+                    .get(*position)
+                    .is_some_and(|w| w.eq_ignore_ascii_case(word.trim()))
+            })
+            .map(|(position, _)| (position + 1).to_string())
+            .collect();
+        if !wrong.is_empty() {
+            bail!("Wrong words at positions {}", wrong.join(", "));
+        }
+        Ok(())
+    }
+
+    /// Checks a re-entered seed is the backup of this wallet by comparing
+    /// master fingerprints, so the stored secret is never revealed.
+    pub fn verify_backup(&mut self, seed: &str, password: &str, network: Network) -> Result<bool> {
+        if !matches!(self.secret, Some(Secret::Seed(_))) {
+            bail!("Only seed wallets can be verified against a seed");
+        }
+        let master_fingerprint = match self.master_fingerprint {
+            Some(fingerprint) => fingerprint,
+            None => self
+                .get_root_xprv(password, network)?
+                .fingerprint(&Secp256k1::new()),
+        };
+
+        let normalized = seed.split_whitespace().collect::<Vec<&str>>().join(" ");
+        let xkey: ExtendedKey = Mnemonic::parse(normalized.to_lowercase())?.into_extended_key()?;
+        let xprv = xkey
+            .into_xprv(self.get_network(network))
+            .context("No private key found")?;
+
+        Ok(xprv.fingerprint(&Secp256k1::new()) == master_fingerprint)
+    }
+
+    /// Generates a seed from user supplied dice rolls or coin flips, mixed with
+    /// the platform RNG when `mix_rng` is set.
+    pub fn generate_seed_from_rolls(
+        source: EntropySource,
+        rolls: &str,
+        mix_rng: bool,
+    ) -> Result<UserEntropy> {
+        let rng = mix_rng.then(|| rand::thread_rng().gen());
+        entropy::entropy_from_rolls(source, rolls, rng)
+    }
+
+    /// Generates a random 256 bits master secret split into SLIP-39 shares.
+    pub fn generate_slip39(
+        group_threshold: u8,
+        groups: &[GroupSpec],
+        passphrase: &str,
+    ) -> Result<Vec<Vec<String>>> {
+        let master_secret: [u8; 32] = rand::thread_rng().gen();
+        slip39::generate_mnemonics(group_threshold, groups, &master_secret, passphrase, 0)
+    }
+
+    /// Derives the key at `derivation`, which must extend the wallet's
+    /// derivation, along with the master fingerprint.
+    fn derive_xprv(
+        &mut self,
+        derivation: &DerivationPath,
+        password: &str,
+        network: Network,
+    ) -> Result<(Fingerprint, ExtendedPrivKey)> {
+        let xprv = self.get_xprv(password, network)?;
+        let relative_path = derivation
+            .as_ref()
+            .strip_prefix(self.derivation.as_ref())
+            .with_context(|| {
+                format!(
+                    "The derivation {derivation} doesn't extend the wallet derivation {}",
+                    self.derivation
+                )
+            })?;
+        let derived_xprv = xprv.derive_priv(&Secp256k1::new(), &relative_path)?;
+
+        Ok((self.get_master_fingerprint(&xprv)?, derived_xprv))
+    }
+
     pub fn derive_xpub(
         &mut self,
         derivation: &str,
         password: &str,
         network: Network,
     ) -> Result<(String, String)> {
-        let xprv = self.get_xprv(password, network)?;
         let path = DerivationPath::from_str(derivation)?;
-        let secp = Secp256k1::new();
-        let derived_xprv = xprv.derive_priv(&secp, &path)?;
-        let xpub = ExtendedPubKey::from_priv(&secp, &derived_xprv);
+        let (master_fingerprint, derived_xprv) = self.derive_xprv(&path, password, network)?;
+        let xpub = ExtendedPubKey::from_priv(&Secp256k1::new(), &derived_xprv);
+
+        Ok((master_fingerprint.to_string(), xpub.to_string()))
+    }
+
+    /// Generates the BSMS key record to hand to the coordinator for the key
+    /// at `derivation`.
+    pub fn bsms_key_record(
+        &mut self,
+        token: &str,
+        description: &str,
+        derivation: &str,
+        password: &str,
+        network: Network,
+    ) -> Result<String> {
+        let path = DerivationPath::from_str(derivation).context("Error parsing derivation path")?;
+        let (master_fingerprint, derived_xprv) = self.derive_xprv(&path, password, network)?;
+        bsms::key_record(token, &derived_xprv, master_fingerprint, &path, description)
+    }
+
+    /// Imports the coordinator's BSMS descriptor record, checking it includes
+    /// this wallet's key before storing the descriptor.
+    pub fn import_bsms_descriptor_record(
+        &mut self,
+        record: &str,
+        password: &str,
+        network: Network,
+    ) -> Result<()> {
+        let xprv = self.get_xprv(password, network)?;
+        let master_fingerprint = self.get_master_fingerprint(&xprv)?;
+        let wallet_network = self.get_network(network);
+        let descriptor =
+            bsms::check_descriptor_record(record, master_fingerprint, wallet_network, |path| {
+                let (_, derived_xprv) = self.derive_xprv(path, password, network)?;
+                Ok(ExtendedPubKey::from_priv(&Secp256k1::new(), &derived_xprv))
+            })?;
+
+        self.descriptor = Some(descriptor);
+        Ok(())
+    }
+
+    /// Signs `message` with the key at `derivation`, the address type being
+    /// inferred from the derivation purpose when not given.
+    pub fn sign_message(
+        &mut self,
+        message: &str,
+        derivation: &str,
+        address_type: Option<AddressType>,
+        format: SignatureFormat,
+        password: &str,
+        network: Network,
+    ) -> Result<SignedMessage> {
+        let path = DerivationPath::from_str(derivation).context("Error parsing derivation path")?;
+        let address_type = address_type
+            .or_else(|| AddressType::from_derivation(&path))
+            .with_context(|| format!("Unknown address type for the derivation {path}"))?;
+        let (_, derived_xprv) = self.derive_xprv(&path, password, network)?;
+
+        message::sign_message(
+            &derived_xprv.private_key,
+            message,
+            address_type,
+            format,
+            self.get_network(network),
+        )
+    }
 
-        let master_fingerprint = xprv.fingerprint(&secp).to_string();
-        Ok((master_fingerprint, xpub.to_string()))
+    pub fn derive_bip85(
+        &mut self,
+        application: Bip85Application,
+        index: u32,
+        password: &str,
+        network: Network,
+    ) -> Result<String> {
+        let xprv = self.get_root_xprv(password, network)?;
+        bip85::derive(&xprv, application, index)
+    }
+
+    /// Creates a new wallet scoped to another account of this wallet's seed,
+    /// sharing the same encrypted secret.
+    pub fn derive_account_wallet(&self, name: &str, derivation: &str) -> Result<Wallet> {
+        if !matches!(self.secret, Some(Secret::Seed(_)) | Some(Secret::Slip39(_))) {
+            bail!("Only seed wallets can be used for other accounts");
+        }
+
+        Ok(Wallet {
+            name: name.to_string(),
+            salt: self.salt,
+            nonce: self.nonce,
+            secret: self.secret.clone(),
+            derivation: DerivationPath::from_str(derivation)
+                .context("Error parsing derivation path")?,
+            network: self.network,
+            master_fingerprint: self.master_fingerprint,
+            descriptor: None,
+            allow_any_sighash: false,
+        })
+    }
+
+    /// Creates a new wallet from a BIP85 child of this wallet, so it can be
+    /// recovered later from this wallet's backup.
+    pub fn derive_bip85_wallet(
+        &mut self,
+        name: &str,
+        application: Bip85Application,
+        index: u32,
+        password: &str,
+        network: Network,
+    ) -> Result<Wallet> {
+        let child_secret = self.derive_bip85(application, index, password, network)?;
+        let network = self.get_network(network);
+
+        let mut wallet = Wallet::default();
+        match application {
+            Bip85Application::Mnemonic { .. } => {
+                wallet.from_seed_str(name, &child_secret, "m", network, password)?
+            }
+            Bip85Application::Xprv => {
+                wallet.from_xprv_str(name, &child_secret, "m", None, network, password)?
+            }
+            _ => bail!("Only BIP39 and XPRV children can be imported as a wallet"),
+        }
+        Ok(wallet)
     }
 }
 
@@ -171,7 +553,7 @@ fn encrypt_decrypt_seed_success() {
     let password = "Qwerty123";
     let seed_str = "solar goat auto bachelor chronic input twin depth fork scale divorce fury mushroom column image sauce car public artist announce treat spend jacket physical";
     wallet
-        .from_seed_str("Wallet 1", seed_str, password)
+        .from_seed_str("Wallet 1", seed_str, "m", Network::Bitcoin, password)
         .unwrap();
 
     let seed = Mnemonic::parse(seed_str).unwrap();
@@ -186,13 +568,262 @@ fn encrypt_decrypt_xpriv_success() {
     let mut wallet = Wallet::default();
     let password = "Qwerty123";
     let xprv_str = "tprv8aXrDeJbcYaRPWkuqtzTMR2Gui4T6A9bwfq6pScH4GSFFzrvXTQ21Fj9fjLzcv4MQxE8yyBtVjrCDn21kbjVvSrghAWU7hGDGQUFZTNADg4";
-    let derivation = "m/48'/1'/1'";
+    let derivation = "m";
     wallet
-        .from_xprv_str("Wallet 1", xprv_str, derivation, password)
+        .from_xprv_str(
+            "Wallet 1",
+            xprv_str,
+            derivation,
+            None,
+            Network::Regtest,
+            password,
+        )
         .unwrap();
 
     let mut xprv = ExtendedPrivKey::from_str(xprv_str).unwrap();
-    xprv.network = Network::Bitcoin;
+    xprv.network = Network::Regtest;
+
+    assert_eq!(wallet.network, Some(Network::Regtest));
+    assert_eq!(xprv, wallet.get_xprv(password, Network::Bitcoin).unwrap())
+}
+
+#[test]
+fn wallet_network_is_kept() {
+    let mut wallet = Wallet::default();
+    let password = "Qwerty123";
+    let xprv_str = "xprv9s21ZrQH143K2LBWUUQRFXhucrQqBpKdRRxNVq2zBqsx8HVqFk2uYo8kmbaLLHRdqtQpUm98uKfu3vca1LqdGhUtyoFnCNkfmXRyPXLjbKb";
+    wallet
+        .from_xprv_str("Wallet 1", xprv_str, "m", None, Network::Regtest, password)
+        .unwrap();
+
+    assert_eq!(wallet.network, Some(Network::Bitcoin));
+    assert_eq!(
+        wallet.get_xprv(password, Network::Regtest).unwrap().network,
+        Network::Bitcoin
+    );
+
+    wallet.network = None;
+    assert_eq!(
+        wallet.get_xprv(password, Network::Regtest).unwrap().network,
+        Network::Bitcoin
+    );
+}
+
+#[test]
+fn xprv_derivation_mismatch() {
+    let master = "xprv9s21ZrQH143K2LBWUUQRFXhucrQqBpKdRRxNVq2zBqsx8HVqFk2uYo8kmbaLLHRdqtQpUm98uKfu3vca1LqdGhUtyoFnCNkfmXRyPXLjbKb";
+    let account = ExtendedPrivKey::from_str(master)
+        .unwrap()
+        .derive_priv(
+            &Secp256k1::new(),
+            &DerivationPath::from_str("m/48'/1'/1'").unwrap(),
+        )
+        .unwrap()
+        .to_string();
+    let account = account.as_str();
+
+    assert!(Wallet::validate(master, "m", None).is_ok());
+    assert!(Wallet::validate(master, "m/48'/1'/1'", None).is_err());
+    assert!(Wallet::validate(master, "m", Some("00000000")).is_err());
+    assert!(Wallet::validate(account, "m", None).is_err());
+    assert!(Wallet::validate(account, "m/48'/1'/2'", None).is_err());
+    assert!(Wallet::validate(account, "m/48'/1'/1'", Some("1fcce4de")).is_ok());
+
+    let mut wallet = Wallet::default();
+    wallet
+        .from_xprv_str(
+            "Wallet 1",
+            account,
+            "m/48'/1'/1'",
+            Some("1fcce4de"),
+            Network::Bitcoin,
+            "Qwerty123",
+        )
+        .unwrap();
+    let (fingerprint, _) = wallet
+        .derive_xpub("m/48'/1'/1'/0", "Qwerty123", Network::Bitcoin)
+        .unwrap();
+    assert_eq!(fingerprint, "1fcce4de");
+}
+
+#[test]
+fn seed_quiz() {
+    let seed: Vec<String> = "solar goat auto bachelor chronic input twin depth fork scale divorce fury mushroom column image sauce car public artist announce treat spend jacket physical"
+        .split_whitespace()
+        .map(ToString::to_string)
+        .collect();
+    let positions = Wallet::seed_quiz_positions(seed.len(), 4);
+    assert_eq!(positions.len(), 4);
+    assert!(positions.windows(2).all(|w| w[0] < w[1]));
+
+    let answers: Vec<(usize, String)> = positions
+        .iter()
+        .map(|p| (*p, seed[*p].to_uppercase()))
+        .collect();
+    assert!(Wallet::check_seed_quiz(&seed, &answers).is_ok());
+    assert!(Wallet::check_seed_quiz(&seed, &[(0, "goat".to_string())]).is_err());
+    assert!(Wallet::check_seed_quiz(&seed, &[(24, "solar".to_string())]).is_err());
+}
+
+#[test]
+fn verify_backup() {
+    let mut wallet = Wallet::default();
+    let password = "Qwerty123";
+    let seed_str = "solar goat auto bachelor chronic input twin depth fork scale divorce fury mushroom column image sauce car public artist announce treat spend jacket physical";
+    wallet
+        .from_seed_str(
+            "Wallet 1",
+            seed_str,
+            "m/84'/0'/0'",
+            Network::Bitcoin,
+            password,
+        )
+        .unwrap();
+
+    assert!(wallet
+        .verify_backup(&seed_str.to_uppercase(), "", Network::Bitcoin)
+        .unwrap());
+    let other = "social mango annual basic work brain economy one safe physical junk other toy valid load cook napkin maple runway island oil fan legend stem";
+    assert!(!wallet.verify_backup(other, "", Network::Bitcoin).unwrap());
+
+    wallet.master_fingerprint = None;
+    assert!(wallet
+        .verify_backup(seed_str, password, Network::Bitcoin)
+        .unwrap());
+}
+
+#[test]
+fn bsms_roundtrip() {
+    let password = "Qwerty123";
+    let derivation = "m/48'/1'/0'/2'";
+    let mut key_records = Vec::new();
+    let mut wallets = Vec::new();
+    for seed_str in [
+        "solar goat auto bachelor chronic input twin depth fork scale divorce fury mushroom column image sauce car public artist announce treat spend jacket physical",
+        "social mango annual basic work brain economy one safe physical junk other toy valid load cook napkin maple runway island oil fan legend stem",
+    ] {
+        let mut wallet = Wallet::default();
+        wallet
+            .from_seed_str("Wallet", seed_str, derivation, Network::Testnet, password)
+            .unwrap();
+        key_records.push(
+            wallet
+                .bsms_key_record("00", "Signer", derivation, password, Network::Testnet)
+                .unwrap(),
+        );
+        wallets.push(wallet);
+    }
+    assert!(wallets[0]
+        .bsms_key_record("00", "Signer", "m/84'/1'/0'", password, Network::Testnet)
+        .is_err());
+
+    let keys: Vec<&str> = key_records
+        .iter()
+        .map(|r| r.lines().nth(2).unwrap())
+        .collect();
+    let descriptor = format!("wsh(sortedmulti(1,{}/**,{}/**))", keys[0], keys[1]);
+    let descriptor = format!(
+        "{descriptor}#{}",
+        bdk::descriptor::checksum::calc_checksum(&descriptor).unwrap()
+    );
+    let address = crate::bsms::parse_descriptor(&descriptor)
+        .unwrap()
+        .at_derivation_index(0)
+        .address(Network::Testnet)
+        .unwrap();
+    let record = format!("BSMS 1.0\n{descriptor}\nNo path restrictions\n{address}");
+
+    for wallet in wallets.iter_mut() {
+        wallet
+            .import_bsms_descriptor_record(&record, password, Network::Testnet)
+            .unwrap();
+        assert_eq!(wallet.descriptor, Some(descriptor.clone()));
+    }
+
+    let mut other_account = wallets[0]
+        .derive_account_wallet("Wallet 2", "m/48'/1'/1'/2'")
+        .unwrap();
+    assert!(other_account
+        .import_bsms_descriptor_record(&record, password, Network::Testnet)
+        .is_err());
+}
+
+#[test]
+fn sign_message_success() {
+    let mut wallet = Wallet::default();
+    let password = "Qwerty123";
+    let seed_str = "solar goat auto bachelor chronic input twin depth fork scale divorce fury mushroom column image sauce car public artist announce treat spend jacket physical";
+    wallet
+        .from_seed_str(
+            "Wallet 1",
+            seed_str,
+            "m/84'/0'/0'",
+            Network::Bitcoin,
+            password,
+        )
+        .unwrap();
+
+    let signed = wallet
+        .sign_message(
+            "Hello World",
+            "m/84'/0'/0'/0/0",
+            None,
+            SignatureFormat::Bip322Simple,
+            password,
+            Network::Bitcoin,
+        )
+        .unwrap();
+    assert!(signed.address.to_string().starts_with("bc1q"));
+    assert!(message::verify_message(
+        &signed.address.to_string(),
+        "Hello World",
+        &signed.signature
+    )
+    .unwrap());
+    assert!(wallet
+        .sign_message(
+            "Hello World",
+            "m/84'/0'/1'/0/0",
+            None,
+            SignatureFormat::Bip137,
+            password,
+            Network::Bitcoin,
+        )
+        .is_err());
+}
+
+#[test]
+fn derive_bip85_wallet_success() {
+    let mut wallet = Wallet::default();
+    let password = "Qwerty123";
+    let seed_str = "solar goat auto bachelor chronic input twin depth fork scale divorce fury mushroom column image sauce car public artist announce treat spend jacket physical";
+    wallet
+        .from_seed_str("Wallet 1", seed_str, "m", Network::Bitcoin, password)
+        .unwrap();
+
+    let application = Bip85Application::Mnemonic { words: 12 };
+    let child_seed = wallet
+        .derive_bip85(application, 1, password, Network::Bitcoin)
+        .unwrap();
+    let mut child = wallet
+        .derive_bip85_wallet("Wallet 2", application, 1, password, Network::Bitcoin)
+        .unwrap();
+
+    assert_eq!(child_seed.split_whitespace().count(), 12);
+    assert_eq!(child.reveal_secret(password).unwrap(), child_seed);
+}
+
+#[test]
+fn import_slip39_success() {
+    let mut wallet = Wallet::default();
+    let password = "Qwerty123";
+    let shares = ["duckling enlarge academic academic agency result length solution fridge kidney coal piece deal husband erode duke ajar critical decision keyboard"];
+    wallet
+        .from_slip39_shares("Wallet 1", &shares, "TREZOR", Network::Bitcoin, password)
+        .unwrap();
+
+    let master_secret = Vec::<u8>::from_hex("bb54aac4b89dc868ba37d9cc21b2cece").unwrap();
+    let xprv = ExtendedPrivKey::new_master(Network::Bitcoin, &master_secret).unwrap();
 
     assert_eq!(xprv, wallet.get_xprv(password, Network::Bitcoin).unwrap())
 }
@@ -0,0 +1,23 @@
+//! The consolidation benchmark in the `wasm32-unknown-unknown` build the
+//! extension ships, run in a browser with
+//! `wasm-pack test --release --headless --chrome signer -- --test wasm_sign`.
+#![cfg(target_arch = "wasm32")]
+
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use wasm_bindgen_test::{console_log, wasm_bindgen_test, wasm_bindgen_test_configure};
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[wasm_bindgen_test]
+fn signs_consolidation_within_budget() {
+    let elapsed = common::time_signing(js_sys::Date::now);
+    console_log!(
+        "Signed {} inputs in {elapsed:.0} ms, {:.3} ms per input (budget {} ms)",
+        common::INPUTS,
+        elapsed / f64::from(common::INPUTS),
+        common::BUDGET_MS
+    );
+    assert!(elapsed < common::BUDGET_MS, "Signing is over budget");
+}